@@ -0,0 +1,285 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, TcpListener, TcpStream, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+
+use {AsInner, IntoInner};
+use error;
+use ext;
+use socket::Socket;
+use sys::c;
+#[cfg(unix)]
+use {uds, UnixSocketAddr};
+
+/// The type of socket created by a [`SocketBuilder`][link].
+///
+/// [link]: struct.SocketBuilder.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketType {
+    /// `SOCK_STREAM`, a reliable, connection based byte stream.
+    Stream,
+    /// `SOCK_DGRAM`, connectionless datagrams of a fixed maximum length.
+    Dgram,
+    /// `SOCK_RAW`, raw network protocol access.
+    Raw,
+    /// `SOCK_SEQPACKET`, a reliable, connection based stream of datagrams.
+    SeqPacket,
+}
+
+impl SocketType {
+    fn as_raw(&self) -> i32 {
+        match *self {
+            SocketType::Stream => c::SOCK_STREAM,
+            SocketType::Dgram => c::SOCK_DGRAM,
+            SocketType::Raw => c::SOCK_RAW,
+            SocketType::SeqPacket => c::SOCK_SEQPACKET,
+        }
+    }
+}
+
+/// An "in progress" socket of an arbitrary domain, type and protocol.
+///
+/// Allows configuration of a socket before it is converted into one of the
+/// standard library's networking types. Sockets which none of them fit, such
+/// as raw or `SOCK_SEQPACKET` sockets, can be taken out with
+/// `IntoRawFd` or `IntoRawSocket`.
+pub struct SocketBuilder {
+    socket: RefCell<Option<Socket>>,
+}
+
+impl SocketBuilder {
+    /// Constructs a new SocketBuilder with the specified domain, type and
+    /// protocol.
+    ///
+    /// The `domain` and `protocol` arguments are passed straight through to
+    /// socket(2), for example `AF_INET6` and `IPPROTO_SCTP`. A protocol of 0
+    /// selects the default protocol for the domain and type.
+    pub fn new(domain: i32, ty: SocketType, protocol: i32) -> io::Result<SocketBuilder> {
+        Socket::with_protocol(domain, ty.as_raw(), protocol)
+            .map(::FromInner::from_inner)
+    }
+
+    /// Binds this socket to the specified address.
+    ///
+    /// This function directly corresponds to the bind(2) function on Windows
+    /// and Unix.
+    pub fn bind<T>(&self, addr: T) -> io::Result<&SocketBuilder>
+        where T: ToSocketAddrs
+    {
+        self.with_socket(|sock| {
+            let addr = try!(::one_addr(addr));
+            sock.bind(&addr)
+        }).map(|()| self)
+    }
+
+    /// Mark a socket as ready to accept incoming connection requests using
+    /// accept()
+    ///
+    /// This function directly corresponds to the listen(2) function on Windows
+    /// and Unix. The socket stays in the builder, use a method such as
+    /// `to_tcp_listener` to take it out.
+    pub fn listen(&self, backlog: i32) -> io::Result<&SocketBuilder> {
+        self.with_socket(|sock| sock.listen(backlog)).map(|()| self)
+    }
+
+    /// Initiate a connection on this socket to the specified address.
+    ///
+    /// This function directly corresponds to the connect(2) function on Windows
+    /// and Unix. The socket stays in the builder, use a method such as
+    /// `to_tcp_stream` to take it out. If every resolved address fails the
+    /// returned error wraps a `ConnectError`.
+    pub fn connect<T>(&self, addr: T) -> io::Result<&SocketBuilder>
+        where T: ToSocketAddrs
+    {
+        self.with_socket(|sock| {
+            error::each_addr(addr, |_, addr| sock.connect(addr))
+        }).map(|()| self)
+    }
+
+    /// Converts this builder into a `TcpStream`
+    ///
+    /// This function will consume the internal socket and return it re-wrapped
+    /// as a `TcpStream`. An error will be returned if the internal socket has
+    /// already been consumed.
+    pub fn to_tcp_stream(&self) -> io::Result<TcpStream> {
+        self.take().map(|s| s.into_inner().into_tcp_stream())
+    }
+
+    /// Converts this builder into a `TcpListener`
+    ///
+    /// This function will consume the internal socket and return it re-wrapped
+    /// as a `TcpListener`. An error will be returned if the internal socket has
+    /// already been consumed.
+    pub fn to_tcp_listener(&self) -> io::Result<TcpListener> {
+        self.take().map(|s| s.into_inner().into_tcp_listener())
+    }
+
+    /// Converts this builder into a `UdpSocket`
+    ///
+    /// This function will consume the internal socket and return it re-wrapped
+    /// as a `UdpSocket`. An error will be returned if the internal socket has
+    /// already been consumed.
+    pub fn to_udp_socket(&self) -> io::Result<UdpSocket> {
+        self.take().map(|s| s.into_inner().into_udp_socket())
+    }
+
+    /// Binds this socket to the specified Unix domain address.
+    ///
+    /// This function directly corresponds to the bind(2) function, for an
+    /// `AF_UNIX` socket.
+    #[cfg(unix)]
+    pub fn bind_unix(&self, addr: &UnixSocketAddr) -> io::Result<&SocketBuilder> {
+        self.with_socket(|sock| uds::bind(sock, addr)).map(|()| self)
+    }
+
+    /// Initiate a connection on this socket to the specified Unix domain
+    /// address.
+    ///
+    /// This function directly corresponds to the connect(2) function, for an
+    /// `AF_UNIX` socket. The socket stays in the builder, use a method such as
+    /// `to_unix_stream` to take it out.
+    #[cfg(unix)]
+    pub fn connect_unix(&self, addr: &UnixSocketAddr) -> io::Result<&SocketBuilder> {
+        self.with_socket(|sock| uds::connect(sock, addr)).map(|()| self)
+    }
+
+    /// Converts this builder into a `UnixStream`
+    ///
+    /// This function will consume the internal socket and return it re-wrapped
+    /// as a `UnixStream`, which also fits a `SOCK_SEQPACKET` socket. An error
+    /// will be returned if the internal socket has already been consumed.
+    #[cfg(unix)]
+    pub fn to_unix_stream(&self) -> io::Result<UnixStream> {
+        self.take().map(|s| s.into_inner().into_unix_stream())
+    }
+
+    /// Converts this builder into a `UnixListener`
+    ///
+    /// This function will consume the internal socket and return it re-wrapped
+    /// as a `UnixListener`. An error will be returned if the internal socket
+    /// has already been consumed.
+    #[cfg(unix)]
+    pub fn to_unix_listener(&self) -> io::Result<UnixListener> {
+        self.take().map(|s| s.into_inner().into_unix_listener())
+    }
+
+    /// Converts this builder into a `UnixDatagram`
+    ///
+    /// This function will consume the internal socket and return it re-wrapped
+    /// as a `UnixDatagram`. An error will be returned if the internal socket
+    /// has already been consumed.
+    #[cfg(unix)]
+    pub fn to_unix_datagram(&self) -> io::Result<UnixDatagram> {
+        self.take().map(|s| s.into_inner().into_unix_datagram())
+    }
+
+    /// Returns the address of the local half of this socket.
+    ///
+    /// Only `AF_INET` and `AF_INET6` addresses are returned, use
+    /// `unix_local_addr` for an `AF_UNIX` socket. An error will be returned
+    /// if the socket has already been consumed.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match *self.socket.borrow() {
+            Some(ref s) => s.getsockname(),
+            None => Err(error::finished()),
+        }
+    }
+
+    /// Returns the address of the remote half of this socket.
+    ///
+    /// This is mostly useful for connected sockets adopted through
+    /// `FromRaw{Fd,Socket}`. Only `AF_INET` and `AF_INET6` addresses are
+    /// returned, use `unix_peer_addr` for an `AF_UNIX` socket. An error will
+    /// be returned if the socket is not connected or has already been
+    /// consumed.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match *self.socket.borrow() {
+            Some(ref s) => s.getpeername(),
+            None => Err(error::finished()),
+        }
+    }
+
+    /// Returns the address of the local half of this `AF_UNIX` socket.
+    ///
+    /// An error will be returned if the socket has already been consumed.
+    #[cfg(unix)]
+    pub fn unix_local_addr(&self) -> io::Result<UnixSocketAddr> {
+        match *self.socket.borrow() {
+            Some(ref s) => uds::getsockname(s),
+            None => Err(error::finished()),
+        }
+    }
+
+    /// Returns the address of the remote half of this `AF_UNIX` socket.
+    ///
+    /// An error will be returned if the socket is not connected or has
+    /// already been consumed.
+    #[cfg(unix)]
+    pub fn unix_peer_addr(&self) -> io::Result<UnixSocketAddr> {
+        match *self.socket.borrow() {
+            Some(ref s) => uds::getpeername(s),
+            None => Err(error::finished()),
+        }
+    }
+
+    /// Returns the raw socket for setting options on it, or an error if the
+    /// socket has been taken out of the builder.
+    pub(crate) fn sock(&self) -> io::Result<ext::Socket> {
+        match *self.socket.borrow() {
+            Some(ref s) => Ok(s.as_inner().raw()),
+            None => Err(error::finished()),
+        }
+    }
+
+    fn take(&self) -> io::Result<Socket> {
+        self.socket.borrow_mut().take()
+            .ok_or(error::consumed())
+    }
+
+    fn with_socket<F>(&self, f: F) -> io::Result<()>
+        where F: FnOnce(&Socket) -> io::Result<()>
+    {
+        match *self.socket.borrow() {
+            Some(ref s) => f(s),
+            None => Err(error::finished()),
+        }
+    }
+}
+
+impl fmt::Debug for SocketBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.socket.borrow() {
+            Some(ref s) => write!(f, "SocketBuilder {{ socket: {:?} }}", s),
+            None => write!(f, "SocketBuilder {{ socket: (consumed) }}"),
+        }
+    }
+}
+
+impl ::AsInner for SocketBuilder {
+    type Inner = RefCell<Option<Socket>>;
+    fn as_inner(&self) -> &RefCell<Option<Socket>> { &self.socket }
+}
+
+impl ::IntoInner for SocketBuilder {
+    type Inner = Option<Socket>;
+    fn into_inner(self) -> Option<Socket> { self.socket.into_inner() }
+}
+
+impl ::FromInner for SocketBuilder {
+    type Inner = Socket;
+    fn from_inner(sock: Socket) -> SocketBuilder {
+        SocketBuilder { socket: RefCell::new(Some(sock)) }
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::ops::Deref;
+
+use ext::TcpStreamExt;
+
+/// Corks a `TcpStream` for as long as it is alive, with the `TCP_CORK`
+/// option.
+///
+/// Writes made through the guard, or through the stream while the guard is
+/// alive, are held back until they fill whole segments. The stream is
+/// uncorked, sending any remaining data, when the guard is dropped or
+/// [`uncork`][link] is called. This is only supported on Linux and Android.
+///
+/// [link]: #method.uncork
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::Write;
+/// use std::net::TcpStream;
+/// use net2::CorkGuard;
+///
+/// let stream = TcpStream::connect("127.0.0.1:80").unwrap();
+/// let mut corked = CorkGuard::new(&stream).unwrap();
+/// corked.write_all(b"HTTP/1.1 200 OK\r\n").unwrap();
+/// corked.write_all(b"Content-Length: 0\r\n\r\n").unwrap();
+/// corked.uncork().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct CorkGuard<'a> {
+    stream: &'a TcpStream,
+    corked: bool,
+}
+
+impl<'a> CorkGuard<'a> {
+    /// Sets `TCP_CORK` on `stream` until the returned guard is dropped.
+    pub fn new(stream: &'a TcpStream) -> io::Result<CorkGuard<'a>> {
+        try!(stream.set_cork(true));
+        Ok(CorkGuard { stream, corked: true })
+    }
+
+    /// Clears `TCP_CORK`, sending any data held back, and returns the error
+    /// doing so which dropping the guard would ignore.
+    pub fn uncork(mut self) -> io::Result<()> {
+        self.corked = false;
+        self.stream.set_cork(false)
+    }
+}
+
+impl<'a> Deref for CorkGuard<'a> {
+    type Target = TcpStream;
+
+    fn deref(&self) -> &TcpStream {
+        self.stream
+    }
+}
+
+impl<'a> Write for CorkGuard<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self.stream).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self.stream).flush()
+    }
+}
+
+impl<'a> Drop for CorkGuard<'a> {
+    fn drop(&mut self) {
+        if self.corked {
+            let _ = self.stream.set_cork(false);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use ext;
+
+/// An error from an operation on a socket, recording which operation failed.
+///
+/// Fallible methods in this crate return an `io::Error` wrapping this type.
+/// Its kind is the kind of the underlying error, and the underlying error,
+/// including any raw OS error code, can be recovered through
+/// [`Error::from_io`][link]:
+///
+/// ```no_run
+/// use net2::{Error, TcpBuilder};
+///
+/// let tcp = TcpBuilder::new_v4().unwrap();
+/// if let Err(e) = tcp.bind("0.0.0.0:80") {
+///     let code = Error::from_io(&e).and_then(|e| e.raw_os_error());
+///     println!("{} (os error {:?})", e, code);
+/// }
+/// ```
+///
+/// [link]: #method.from_io
+#[derive(Debug)]
+pub struct Error {
+    op: Operation,
+    inner: io::Error,
+    context: Option<String>,
+}
+
+/// The operation that produced an [`Error`][link].
+///
+/// [link]: struct.Error.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Creating a socket with socket(2).
+    Socket,
+    /// Setting the socket option `name` at `level` with setsockopt(2).
+    SetSockOpt {
+        /// The protocol level of the option, such as `IPPROTO_TCP`.
+        level: i32,
+        /// The option name, such as `TCP_NODELAY`.
+        name: i32,
+    },
+    /// Reading the socket option `name` at `level` with getsockopt(2).
+    GetSockOpt {
+        /// The protocol level of the option, such as `IPPROTO_TCP`.
+        level: i32,
+        /// The option name, such as `TCP_NODELAY`.
+        name: i32,
+    },
+    /// Binding to an address with bind(2).
+    Bind(SocketAddr),
+    /// Connecting to an address with connect(2).
+    Connect(SocketAddr),
+    /// Marking a socket as listening with listen(2).
+    Listen,
+    /// Using a builder whose socket was already taken out of it.
+    Consumed,
+}
+
+impl Error {
+    /// Returns the operation that failed.
+    pub fn operation(&self) -> Operation {
+        self.op
+    }
+
+    /// Returns the kind of the underlying error.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.inner.kind()
+    }
+
+    /// Returns the OS error code of the underlying error, if any.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.inner.raw_os_error()
+    }
+
+    /// Returns a reference to the underlying error.
+    pub fn get_ref(&self) -> &io::Error {
+        &self.inner
+    }
+
+    /// Consumes this error, returning the underlying error.
+    pub fn into_inner(self) -> io::Error {
+        self.inner
+    }
+
+    /// Returns the `Error` wrapped by `err`, if any.
+    pub fn from_io(err: &io::Error) -> Option<&Error> {
+        err.get_ref().and_then(|e| e.downcast_ref::<Error>())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.op {
+            Operation::Consumed => self.inner.fmt(f),
+            ref op => {
+                try!(write!(f, "{}: {}", op, self.inner));
+                match self.context {
+                    Some(ref context) => write!(f, " ({})", context),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.inner)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operation::Socket => write!(f, "socket"),
+            Operation::SetSockOpt { level, name } => {
+                try!(write!(f, "setsockopt("));
+                try!(fmt_opt(f, level, name));
+                write!(f, ")")
+            }
+            Operation::GetSockOpt { level, name } => {
+                try!(write!(f, "getsockopt("));
+                try!(fmt_opt(f, level, name));
+                write!(f, ")")
+            }
+            Operation::Bind(addr) => write!(f, "bind({})", addr),
+            Operation::Connect(addr) => write!(f, "connect({})", addr),
+            Operation::Listen => write!(f, "listen"),
+            Operation::Consumed => write!(f, "builder"),
+        }
+    }
+}
+
+fn fmt_opt(f: &mut fmt::Formatter, level: i32, name: i32) -> fmt::Result {
+    let (level_name, opt_name) = ext::opt_names(level, name);
+    match level_name {
+        Some(s) => try!(write!(f, "{}, ", s)),
+        None => try!(write!(f, "{}, ", level)),
+    }
+    match opt_name {
+        Some(s) => write!(f, "{}", s),
+        None => write!(f, "{}", name),
+    }
+}
+
+/// Wraps `err` in an `Error` recording that `op` failed.
+pub(crate) fn wrap(op: Operation, err: io::Error) -> io::Error {
+    Error { op, inner: err, context: None }.into()
+}
+
+/// Adds `context`, such as the argument the operation was given, to the
+/// message of the `Error` wrapped by `err`, keeping the underlying error.
+pub(crate) fn with_context(err: io::Error, context: String) -> io::Error {
+    if Error::from_io(&err).is_none() {
+        return err
+    }
+    let kind = err.kind();
+    match err.into_inner().map(|e| e.downcast::<Error>()) {
+        Some(Ok(mut e)) => {
+            e.context = Some(context);
+            io::Error::new(kind, *e)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// The error returned when a builder's socket has been taken out of it.
+pub(crate) fn consumed() -> io::Error {
+    wrap(Operation::Consumed,
+         io::Error::other("socket has already been consumed"))
+}
+
+/// The error returned when a builder's socket has been taken out of it and
+/// is then configured.
+pub(crate) fn finished() -> io::Error {
+    wrap(Operation::Consumed,
+         io::Error::other("builder has already finished its socket"))
+}
+
+/// Returns the raw OS error of `err`, looking through an `Error` or
+/// `ConnectError` wrapping.
+pub(crate) fn raw_os_error(err: &io::Error) -> Option<i32> {
+    err.raw_os_error().or_else(|| {
+        Error::from_io(err).and_then(|e| e.raw_os_error())
+    }).or_else(|| {
+        ConnectError::from_io(err).and_then(|e| e.raw_os_error())
+    })
+}
+
+/// The error returned when a connection could not be established to any of
+/// the addresses a target resolved to.
+///
+/// Connecting methods in this crate return an `io::Error` wrapping this type,
+/// however many addresses were tried, whose kind is that of the last failed
+/// attempt. Use [`ConnectError::from_io`][link] to get at the individual
+/// attempts, or at the raw OS error of the last one through
+/// [`raw_os_error`][raw].
+///
+/// [link]: #method.from_io
+/// [raw]: #method.raw_os_error
+#[derive(Debug)]
+pub struct ConnectError {
+    attempts: Vec<ConnectAttempt>,
+}
+
+/// A single failed connection attempt recorded in a `ConnectError`.
+#[derive(Debug)]
+pub struct ConnectAttempt {
+    addr: SocketAddr,
+    error: io::Error,
+    elapsed: Duration,
+}
+
+impl ConnectError {
+    pub(crate) fn new() -> ConnectError {
+        ConnectError { attempts: Vec::new() }
+    }
+
+    pub(crate) fn push(&mut self, addr: SocketAddr, error: io::Error,
+                       elapsed: Duration) {
+        self.attempts.push(ConnectAttempt {
+            addr,
+            error,
+            elapsed,
+        });
+    }
+
+    /// Returns the failed attempts in the order they were made.
+    ///
+    /// This is empty if the target did not resolve to any address.
+    pub fn attempts(&self) -> &[ConnectAttempt] {
+        &self.attempts
+    }
+
+    /// Returns the `ConnectError` wrapped by `err`, if any.
+    pub fn from_io(err: &io::Error) -> Option<&ConnectError> {
+        err.get_ref().and_then(|e| e.downcast_ref::<ConnectError>())
+    }
+
+    /// Returns the kind of the last failed attempt.
+    ///
+    /// If no address was resolved this is `ErrorKind::Other`.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.attempts.last().map(|a| a.error.kind())
+            .unwrap_or(io::ErrorKind::Other)
+    }
+
+    /// Returns the OS error code of the last failed attempt, if any.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.attempts.last().and_then(|a| raw_os_error(&a.error))
+    }
+}
+
+impl ConnectAttempt {
+    /// Returns the address this attempt tried to connect to.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the error this attempt failed with.
+    pub fn error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Returns how long this attempt took before failing.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.attempts.is_empty() {
+            return write!(f, "no socket addresses resolved")
+        }
+        try!(write!(f, "failed to connect to any address"));
+        for (i, attempt) in self.attempts.iter().enumerate() {
+            try!(write!(f, "{} {}: {} (after {:?})",
+                        if i == 0 { ":" } else { ";" },
+                        attempt.addr, attempt.error, attempt.elapsed));
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ConnectError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.attempts.last().map(|a| &a.error as &(dyn error::Error + 'static))
+    }
+}
+
+impl From<ConnectError> for io::Error {
+    fn from(err: ConnectError) -> io::Error {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+/// Calls `f` with every address `addr` resolves to until it succeeds,
+/// recording each failure in a `ConnectError`.
+///
+/// The index of the attempt is passed along with the address.
+pub(crate) fn each_addr<A, F>(addr: A, mut f: F) -> io::Result<()>
+    where A: ToSocketAddrs,
+          F: FnMut(usize, &SocketAddr) -> io::Result<()>
+{
+    let mut err = ConnectError::new();
+    for (i, addr) in try!(addr.to_socket_addrs()).enumerate() {
+        let start = Instant::now();
+        match f(i, &addr) {
+            Ok(()) => return Ok(()),
+            Err(e) => err.push(addr, e, start.elapsed()),
+        }
+    }
+    Err(err.into())
+}
@@ -11,14 +11,22 @@
 #![allow(bad_style, dead_code)]
 
 use std::io;
-use std::mem;
+use std::mem::{self, ManuallyDrop};
 use std::net::{TcpStream, TcpListener, UdpSocket, Ipv4Addr, Ipv6Addr};
+use std::net::SocketAddr;
 use std::net::ToSocketAddrs;
 
-use {TcpBuilder, UdpBuilder, FromInner};
+use {TcpBuilder, UdpBuilder, SocketBuilder, FromInner};
+use {OwnedTcpBuilder, OwnedUdpBuilder, TcpKeepalive, TcpInfo, Dscp, Ecn};
+use PacketInfo;
+use keepalive;
+use qos;
+use tcp_info;
 use sys;
 use sys::c;
+use error::{self, Operation};
 use socket;
+use AsInner;
 
 cfg_if! {
     if #[cfg(any(target_os = "dragonfly",
@@ -41,7 +49,7 @@ cfg_if! {
 use std::time::Duration;
 
 #[cfg(any(unix, target_os = "wasi"))] use libc::*;
-#[cfg(any(unix))] use std::os::unix::prelude::*;
+#[cfg(unix)] use std::os::unix::prelude::*;
 #[cfg(target_os = "wasi")] use std::os::wasi::prelude::*;
 #[cfg(unix)] pub type Socket = c_int;
 #[cfg(target_os = "wasi")] pub type Socket = std::os::wasi::io::RawFd;
@@ -58,8 +66,8 @@ struct tcp_keepalive {
     keepaliveinterval: c_ulong,
 }
 
-#[cfg(any(unix, target_os = "wasi"))] fn v(opt: c_int) -> c_int { opt }
-#[cfg(windows)] fn v(opt: IPPROTO) -> c_int { opt as c_int }
+#[cfg(any(unix, target_os = "wasi"))] pub(crate) fn v(opt: c_int) -> c_int { opt }
+#[cfg(windows)] pub(crate) fn v(opt: IPPROTO) -> c_int { opt as c_int }
 
 #[cfg(target_os = "wasi")]
 pub fn set_opt<T: Copy>(_sock: Socket, _opt: c_int, _val: c_int,
@@ -73,7 +81,26 @@ pub fn set_opt<T: Copy>(sock: Socket, opt: c_int, val: c_int,
     unsafe {
         let payload = &payload as *const T as *const c_void;
         try!(::cvt(setsockopt(sock, opt, val, payload as *const _,
-                              mem::size_of::<T>() as socklen_t)));
+                              mem::size_of::<T>() as socklen_t))
+             .map_err(|e| set_err(opt, val, e)));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "wasi")]
+pub fn set_opt_bytes(_sock: Socket, _opt: c_int, _val: c_int,
+                     _payload: &[u8]) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "socket options are not supported by platform"))
+}
+
+#[cfg(not(target_os = "wasi"))]
+pub fn set_opt_bytes(sock: Socket, opt: c_int, val: c_int,
+                     payload: &[u8]) -> io::Result<()> {
+    unsafe {
+        try!(::cvt(setsockopt(sock, opt, val, payload.as_ptr() as *const _,
+                              payload.len() as socklen_t))
+             .map_err(|e| set_err(opt, val, e)));
     }
     Ok(())
 }
@@ -89,14 +116,187 @@ pub fn get_opt<T: Copy>(sock: Socket, opt: c_int, val: c_int) -> io::Result<T> {
         let mut len = mem::size_of::<T>() as socklen_t;
         try!(::cvt(getsockopt(sock, opt, val,
                               &mut slot as *mut _ as *mut _,
-                              &mut len)));
+                              &mut len))
+             .map_err(|e| {
+                 error::wrap(Operation::GetSockOpt { level: opt, name: val }, e)
+             }));
         assert_eq!(len as usize, mem::size_of::<T>());
         Ok(slot)
     }
 }
 
+/// Reads a socket option of variable length into `buf`, returning the
+/// length of the option.
+#[cfg(target_os = "wasi")]
+pub fn get_opt_bytes(_sock: Socket, _opt: c_int, _val: c_int,
+                     _buf: &mut [u8]) -> io::Result<usize> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "socket options are not supported by platform"))
+}
+
+/// Reads a socket option of variable length into `buf`, returning the
+/// length of the option.
+#[cfg(not(target_os = "wasi"))]
+pub fn get_opt_bytes(sock: Socket, opt: c_int, val: c_int,
+                     buf: &mut [u8]) -> io::Result<usize> {
+    unsafe {
+        let mut len = buf.len() as socklen_t;
+        try!(::cvt(getsockopt(sock, opt, val,
+                              buf.as_mut_ptr() as *mut _,
+                              &mut len))
+             .map_err(|e| {
+                 error::wrap(Operation::GetSockOpt { level: opt, name: val }, e)
+             }));
+        Ok(len as usize)
+    }
+}
+
+#[cfg(not(target_os = "wasi"))]
+fn set_err(opt: c_int, val: c_int, err: io::Error) -> io::Error {
+    error::wrap(Operation::SetSockOpt { level: opt, name: val }, err)
+}
+
+/// Returns the symbolic names of a socket option level and option, if they
+/// are ones this crate knows about.
+#[cfg(target_os = "wasi")]
+pub fn opt_names(_opt: c_int, _val: c_int)
+                 -> (Option<&'static str>, Option<&'static str>) {
+    (None, None)
+}
+
+/// Returns the symbolic names of a socket option level and option, if they
+/// are ones this crate knows about.
+#[cfg(not(target_os = "wasi"))]
+pub fn opt_names(opt: c_int, val: c_int)
+                 -> (Option<&'static str>, Option<&'static str>) {
+    let levels = [
+        (SOL_SOCKET, "SOL_SOCKET"),
+        (IPPROTO_IP, "IPPROTO_IP"),
+        (v(IPPROTO_IPV6), "IPPROTO_IPV6"),
+        (v(IPPROTO_TCP), "IPPROTO_TCP"),
+        (v(IPPROTO_UDP), "IPPROTO_UDP"),
+    ];
+    let opts = [
+        (SOL_SOCKET, SO_REUSEADDR, "SO_REUSEADDR"),
+        (SOL_SOCKET, SO_KEEPALIVE, "SO_KEEPALIVE"),
+        (SOL_SOCKET, SO_BROADCAST, "SO_BROADCAST"),
+        (SOL_SOCKET, SO_RCVBUF, "SO_RCVBUF"),
+        (SOL_SOCKET, SO_SNDBUF, "SO_SNDBUF"),
+        (SOL_SOCKET, SO_RCVTIMEO, "SO_RCVTIMEO"),
+        (SOL_SOCKET, SO_SNDTIMEO, "SO_SNDTIMEO"),
+        (SOL_SOCKET, SO_LINGER, "SO_LINGER"),
+        (SOL_SOCKET, SO_ERROR, "SO_ERROR"),
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        (SOL_SOCKET, SO_REUSEPORT, "SO_REUSEPORT"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (SOL_SOCKET, SO_BINDTODEVICE, "SO_BINDTODEVICE"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (SOL_SOCKET, SO_MARK, "SO_MARK"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (SOL_SOCKET, SO_PRIORITY, "SO_PRIORITY"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (SOL_SOCKET, c::SO_BINDTOIFINDEX, "SO_BINDTOIFINDEX"),
+        #[cfg(target_os = "linux")]
+        (SOL_SOCKET, c::SO_ATTACH_REUSEPORT_CBPF, "SO_ATTACH_REUSEPORT_CBPF"),
+        #[cfg(target_os = "linux")]
+        (SOL_SOCKET, c::SO_DETACH_REUSEPORT_BPF, "SO_DETACH_REUSEPORT_BPF"),
+        (v(IPPROTO_TCP), TCP_NODELAY, "TCP_NODELAY"),
+        #[cfg(unix)]
+        (IPPROTO_TCP, TCP_MAXSEG, "TCP_MAXSEG"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_TCP, TCP_CORK, "TCP_CORK"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_TCP, TCP_QUICKACK, "TCP_QUICKACK"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_TCP, TCP_NOTSENT_LOWAT, "TCP_NOTSENT_LOWAT"),
+        #[cfg(all(unix, not(any(target_os = "macos", target_os = "ios",
+                                target_os = "nto", target_os = "haiku",
+                                target_os = "netbsd", target_os = "openbsd"))))]
+        (IPPROTO_TCP, KEEPALIVE_OPTION, "TCP_KEEPIDLE"),
+        #[cfg(windows)]
+        (v(IPPROTO_TCP), c::TCP_KEEPIDLE, "TCP_KEEPIDLE"),
+        #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos",
+                  target_os = "ios", target_os = "freebsd", target_os = "netbsd",
+                  target_os = "dragonfly", windows))]
+        (v(IPPROTO_TCP), c::TCP_KEEPINTVL, "TCP_KEEPINTVL"),
+        #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos",
+                  target_os = "ios", target_os = "freebsd", target_os = "netbsd",
+                  target_os = "dragonfly", windows))]
+        (v(IPPROTO_TCP), c::TCP_KEEPCNT, "TCP_KEEPCNT"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_TCP, TCP_USER_TIMEOUT, "TCP_USER_TIMEOUT"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_TCP, TCP_INFO, "TCP_INFO"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_TCP, TCP_CONGESTION, "TCP_CONGESTION"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_TCP, TCP_FASTOPEN, "TCP_FASTOPEN"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_TCP, TCP_FASTOPEN_CONNECT, "TCP_FASTOPEN_CONNECT"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_TCP, TCP_FASTOPEN_KEY, "TCP_FASTOPEN_KEY"),
+        (IPPROTO_IP, IP_TTL, "IP_TTL"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IP, IP_TOS, "IP_TOS"),
+        (IPPROTO_IP, IP_MULTICAST_TTL, "IP_MULTICAST_TTL"),
+        (IPPROTO_IP, IP_MULTICAST_LOOP, "IP_MULTICAST_LOOP"),
+        (IPPROTO_IP, IP_MULTICAST_IF, "IP_MULTICAST_IF"),
+        (IPPROTO_IP, IP_ADD_MEMBERSHIP, "IP_ADD_MEMBERSHIP"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IP, IP_TRANSPARENT, "IP_TRANSPARENT"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IP, IP_BIND_ADDRESS_NO_PORT, "IP_BIND_ADDRESS_NO_PORT"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IP, c::IP_LOCAL_PORT_RANGE, "IP_LOCAL_PORT_RANGE"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IP, IP_FREEBIND, "IP_FREEBIND"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IP, IP_RECVORIGDSTADDR, "IP_RECVORIGDSTADDR"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IP, IP_PKTINFO, "IP_PKTINFO"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IP, SO_ORIGINAL_DST, "SO_ORIGINAL_DST"),
+        #[cfg(any(target_os = "linux", target_os = "android", windows))]
+        (IPPROTO_IP, c::IP_UNICAST_IF, "IP_UNICAST_IF"),
+        (IPPROTO_IP, IP_DROP_MEMBERSHIP, "IP_DROP_MEMBERSHIP"),
+        (v(IPPROTO_IPV6), IPV6_V6ONLY, "IPV6_V6ONLY"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IPV6, IPV6_TCLASS, "IPV6_TCLASS"),
+        (v(IPPROTO_IPV6), IPV6_UNICAST_HOPS, "IPV6_UNICAST_HOPS"),
+        (v(IPPROTO_IPV6), IPV6_MULTICAST_HOPS, "IPV6_MULTICAST_HOPS"),
+        (v(IPPROTO_IPV6), IPV6_MULTICAST_LOOP, "IPV6_MULTICAST_LOOP"),
+        (v(IPPROTO_IPV6), IPV6_MULTICAST_IF, "IPV6_MULTICAST_IF"),
+        (v(IPPROTO_IPV6), IPV6_ADD_MEMBERSHIP, "IPV6_ADD_MEMBERSHIP"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IPV6, IPV6_TRANSPARENT, "IPV6_TRANSPARENT"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IPV6, IPV6_RECVORIGDSTADDR, "IPV6_RECVORIGDSTADDR"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IPV6, IPV6_RECVPKTINFO, "IPV6_RECVPKTINFO"),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        (IPPROTO_IPV6, IP6T_SO_ORIGINAL_DST, "IP6T_SO_ORIGINAL_DST"),
+        #[cfg(any(target_os = "linux", target_os = "android", windows))]
+        (v(IPPROTO_IPV6), c::IPV6_UNICAST_IF, "IPV6_UNICAST_IF"),
+        (v(IPPROTO_IPV6), IPV6_DROP_MEMBERSHIP, "IPV6_DROP_MEMBERSHIP"),
+    ];
+    let level = levels.iter().find(|l| l.0 == opt).map(|l| l.1);
+    let name = opts.iter().find(|o| o.0 == opt && o.1 == val).map(|o| o.2);
+    (level, name)
+}
+
+/// The error returned by the default implementations of extension trait
+/// methods, for types outside this crate which do not override them.
+fn not_overridden() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported,
+                   "not supported by this type")
+}
+
 /// Extension methods for the standard [`TcpStream` type][link] in `std::net`.
 ///
+/// Methods added after the first release of this trait have default
+/// implementations returning an error of kind `Unsupported`, so that
+/// implementations outside this crate keep compiling.
+///
 /// [link]: https://doc.rust-lang.org/std/net/struct.TcpStream.html
 pub trait TcpStreamExt {
     /// Sets the value of the `TCP_NODELAY` option on this socket.
@@ -281,7 +481,15 @@ pub trait TcpStreamExt {
     /// `TcpStream::connect` function call or `TcpBuilder::connect` method call.
     ///
     /// This should only be necessary if an unconnected socket was extracted
-    /// from a `TcpBuilder` and then needs to be connected.
+    /// from a `TcpBuilder` and then needs to be connected. If every resolved
+    /// address fails the returned error wraps a `ConnectError`.
+    ///
+    /// On Unix every attempt after a failed one is made on a fresh socket
+    /// which takes over this socket's descriptor. Only the options this crate
+    /// can set, the local address and nonblocking mode are carried over to
+    /// it, so options set through other means may be lost. On Windows the
+    /// socket can't be replaced, so further addresses are only tried after an
+    /// attempt which was refused, timed out or found the network unreachable.
     fn connect<T: ToSocketAddrs>(&self, addr: T) -> io::Result<()>;
 
     /// Get the value of the `SO_ERROR` option on this socket.
@@ -302,10 +510,220 @@ pub trait TcpStreamExt {
 
     /// reads the linger duration for this socket by getting the SO_LINGER option
     fn linger(&self) -> io::Result<Option<Duration>>;
+
+    /// Enables `SO_KEEPALIVE` and sets the parameters of keepalive probes,
+    /// or disables keepalive probes if `None` is specified.
+    ///
+    /// Unlike [`set_keepalive`][link] this sets the interval between probes
+    /// and the number of probes as well as the idle time, leaving any
+    /// parameter which is `None` at its system default. An error of kind
+    /// `Unsupported` is returned if a parameter is given which this platform
+    /// does not support.
+    ///
+    /// [link]: #tymethod.set_keepalive
+    fn set_tcp_keepalive(&self, _keepalive: Option<TcpKeepalive>)
+                         -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Returns the parameters of keepalive probes if they are enabled on
+    /// this socket.
+    ///
+    /// Parameters which cannot be read on this platform are `None`.
+    fn tcp_keepalive(&self) -> io::Result<Option<TcpKeepalive>> {
+        Err(not_overridden())
+    }
+
+    /// Sets the value of the `TCP_USER_TIMEOUT` option on this socket.
+    ///
+    /// This is the maximum time transmitted data may remain unacknowledged,
+    /// or keepalive probes unanswered, before the connection is forcibly
+    /// closed. `None` restores the system default. This is only supported on
+    /// Linux and Android.
+    fn set_user_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `TCP_USER_TIMEOUT` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_user_timeout`][link].
+    ///
+    /// [link]: #method.set_user_timeout
+    fn user_timeout(&self) -> io::Result<Option<Duration>> {
+        Err(not_overridden())
+    }
+
+    /// Returns statistics about this connection, read from the `TCP_INFO`
+    /// option.
+    ///
+    /// Fields which the running kernel does not fill in are `None`. This is
+    /// only supported on Linux and Android.
+    fn tcp_info(&self) -> io::Result<TcpInfo> {
+        Err(not_overridden())
+    }
+
+    /// Selects the congestion control algorithm used by this socket, such as
+    /// `"cubic"` or `"bbr"`, with the `TCP_CONGESTION` option.
+    ///
+    /// Unprivileged processes may only select the algorithms listed in
+    /// [`allowed_congestion_control`][link], and the kernel fails with
+    /// `ENOENT` if the algorithm is not available. This is only supported on
+    /// Linux and Android.
+    ///
+    /// [link]: unix/fn.allowed_congestion_control.html
+    fn set_congestion_control(&self, _algorithm: &str) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Returns the name of the congestion control algorithm used by this
+    /// socket.
+    fn congestion_control(&self) -> io::Result<String> {
+        Err(not_overridden())
+    }
+
+    /// Sets the value of the `TCP_CORK` option on this socket.
+    ///
+    /// If set, partial segments are not sent until the option is cleared,
+    /// which lets several writes be sent as full segments. The kernel still
+    /// sends corked data after 200ms. This is only supported on Linux and
+    /// Android, see also [`CorkGuard`][link].
+    ///
+    /// [link]: struct.CorkGuard.html
+    fn set_cork(&self, _cork: bool) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `TCP_CORK` option on this socket.
+    ///
+    /// For more information about this option, see [`set_cork`][link].
+    ///
+    /// [link]: #method.set_cork
+    fn cork(&self) -> io::Result<bool> {
+        Err(not_overridden())
+    }
+
+    /// Sets the value of the `TCP_NOTSENT_LOWAT` option on this socket.
+    ///
+    /// The socket is only reported writable once fewer than `bytes` bytes
+    /// are waiting to be sent, which keeps the send queue short without
+    /// shrinking the send buffer. 0 restores the system default. This is only
+    /// supported on Linux 3.12 and later and on Android.
+    fn set_notsent_lowat(&self, _bytes: u32) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `TCP_NOTSENT_LOWAT` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_notsent_lowat`][link].
+    ///
+    /// [link]: #method.set_notsent_lowat
+    fn notsent_lowat(&self) -> io::Result<u32> {
+        Err(not_overridden())
+    }
+
+    /// Sets the value of the `TCP_QUICKACK` option on this socket.
+    ///
+    /// If set, acknowledgements are sent immediately instead of being
+    /// delayed. The kernel may clear this again on its own, so it is usually
+    /// set after every read. This is only supported on Linux and Android.
+    fn set_quickack(&self, _quickack: bool) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `TCP_QUICKACK` option on this socket.
+    ///
+    /// For more information about this option, see [`set_quickack`][link].
+    ///
+    /// [link]: #method.set_quickack
+    fn quickack(&self) -> io::Result<bool> {
+        Err(not_overridden())
+    }
+
+    /// Sets the value of the `TCP_MAXSEG` option on this socket.
+    ///
+    /// This caps the maximum segment size of outgoing segments. Most
+    /// platforms only take it into account before the connection is
+    /// established, see [`TcpBuilder::maxseg`][link]. This is not supported
+    /// on Windows.
+    ///
+    /// [link]: struct.TcpBuilder.html#method.maxseg
+    fn set_maxseg(&self, _mss: u32) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `TCP_MAXSEG` option on this socket.
+    ///
+    /// Once connected this is the maximum segment size in use.
+    fn maxseg(&self) -> io::Result<u32> {
+        Err(not_overridden())
+    }
+
+    /// Sets the value of the `SO_MARK` option on this socket.
+    ///
+    /// The mark is attached to every packet sent, for use by routing rules
+    /// and netfilter. It requires the `CAP_NET_ADMIN` capability and is only
+    /// supported on Linux and Android.
+    fn set_mark(&self, _mark: u32) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `SO_MARK` option on this socket.
+    fn mark(&self) -> io::Result<u32> {
+        Err(not_overridden())
+    }
+
+    /// Sets the value of the `SO_PRIORITY` option on this socket.
+    ///
+    /// This selects the queue packets are sent on by the queueing
+    /// discipline of the interface. Priorities above 6 require the
+    /// `CAP_NET_ADMIN` capability. This is only supported on Linux and
+    /// Android.
+    fn set_priority(&self, _priority: u32) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `SO_PRIORITY` option on this socket.
+    fn priority(&self) -> io::Result<u32> {
+        Err(not_overridden())
+    }
+
+    /// Sets the DSCP bits of the `IP_TOS` option, or `IPV6_TCLASS` for IPv6
+    /// sockets, on this socket, keeping its ECN bits.
+    ///
+    /// This is only supported on Linux and Android.
+    fn set_dscp(&self, _dscp: Dscp) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the DSCP bits of the `IP_TOS` or `IPV6_TCLASS` option on this
+    /// socket.
+    fn dscp(&self) -> io::Result<Dscp> {
+        Err(not_overridden())
+    }
+
+    /// Returns the original destination of a connection redirected to this
+    /// host by a netfilter `REDIRECT` or `DNAT` rule, read from the
+    /// `SO_ORIGINAL_DST` or `IP6T_SO_ORIGINAL_DST` option.
+    ///
+    /// Connections intercepted with `TPROXY` keep their original destination
+    /// as their local address instead. This is only supported on Linux and
+    /// Android, and fails with `ENOENT` if the connection was not redirected.
+    ///
+    /// An IPv4 connection accepted on an IPv6 socket, whose peer is an
+    /// IPv4-mapped address, returns its original IPv4 destination.
+    fn original_dst(&self) -> io::Result<SocketAddr> {
+        Err(not_overridden())
+    }
 }
 
 /// Extension methods for the standard [`TcpListener` type][link] in `std::net`.
 ///
+/// Methods added after the first release of this trait have default
+/// implementations returning an error of kind `Unsupported`, so that
+/// implementations outside this crate keep compiling.
+///
 /// [link]: https://doc.rust-lang.org/std/net/struct.TcpListener.html
 pub trait TcpListenerExt {
     /// Sets the value for the `IP_TTL` option on this socket.
@@ -359,10 +777,58 @@ pub trait TcpListenerExt {
 
     /// reads the linger duration for this socket by getting the SO_LINGER option
     fn linger(&self) -> io::Result<Option<Duration>>;
+
+    /// Sets the value of the `IP_TRANSPARENT` option, or `IPV6_TRANSPARENT`
+    /// for IPv6 sockets, on this socket.
+    ///
+    /// This allows a listener to accept connections to addresses which are
+    /// not local to this host, as redirected to it by a netfilter `TPROXY`
+    /// rule. It requires the `CAP_NET_ADMIN` capability and is only supported
+    /// on Linux and Android.
+    fn set_transparent(&self, _transparent: bool) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `IP_TRANSPARENT` or `IPV6_TRANSPARENT` option on
+    /// this socket.
+    ///
+    /// For more information about this option, see [`set_transparent`][link].
+    ///
+    /// [link]: #method.set_transparent
+    fn transparent(&self) -> io::Result<bool> {
+        Err(not_overridden())
+    }
+
+    /// Sets the keys used to generate and check TCP Fast Open cookies with
+    /// the `TCP_FASTOPEN_KEY` option.
+    ///
+    /// Cookies are generated with `primary` and cookies generated with
+    /// `backup` are still accepted, so a key can be rotated without
+    /// invalidating the cookies already handed out by installing a new
+    /// primary key with the old one as backup. Passing a backup key requires
+    /// Linux 5.8 or later. This is only supported on Linux and Android.
+    fn set_fastopen_keys(&self, _primary: [u8; 16], _backup: Option<[u8; 16]>)
+                         -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the TCP Fast Open cookie keys of this socket.
+    ///
+    /// For more information about the keys, see
+    /// [`set_fastopen_keys`][link].
+    ///
+    /// [link]: #method.set_fastopen_keys
+    fn fastopen_keys(&self) -> io::Result<([u8; 16], Option<[u8; 16]>)> {
+        Err(not_overridden())
+    }
 }
 
 /// Extension methods for the standard [`UdpSocket` type][link] in `std::net`.
 ///
+/// Methods added after the first release of this trait have default
+/// implementations returning an error of kind `Unsupported`, so that
+/// implementations outside this crate keep compiling.
+///
 /// [link]: https://doc.rust-lang.org/std/net/struct.UdpSocket.html
 pub trait UdpSocketExt {
     /// Sets the value of the `SO_RCVBUF` option on this socket.
@@ -644,6 +1110,165 @@ pub trait UdpSocketExt {
     ///
     /// [link]: trait.TcpStreamExt.html#tymethod.set_nonblocking
     fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+
+    /// Sets the value of the `IP_TRANSPARENT` option, or `IPV6_TRANSPARENT`
+    /// for IPv6 sockets, on this socket.
+    ///
+    /// This is the same as [`TcpListenerExt::set_transparent`][other], and
+    /// allows receiving datagrams sent to addresses which are not local to
+    /// this host.
+    ///
+    /// [other]: trait.TcpListenerExt.html#method.set_transparent
+    fn set_transparent(&self, _transparent: bool) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `IP_TRANSPARENT` or `IPV6_TRANSPARENT` option on
+    /// this socket.
+    fn transparent(&self) -> io::Result<bool> {
+        Err(not_overridden())
+    }
+
+    /// Sets the value of the `IP_RECVORIGDSTADDR` option, or
+    /// `IPV6_RECVORIGDSTADDR` for IPv6 sockets, on this socket.
+    ///
+    /// If set, the original destination of every datagram is made available
+    /// to [`recv_from_orig_dst`][link]. This is only supported on Linux and
+    /// Android.
+    ///
+    /// [link]: #method.recv_from_orig_dst
+    fn set_recv_orig_dst_addr(&self, _recv: bool) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `IP_RECVORIGDSTADDR` or `IPV6_RECVORIGDSTADDR`
+    /// option on this socket.
+    fn recv_orig_dst_addr(&self) -> io::Result<bool> {
+        Err(not_overridden())
+    }
+
+    /// Receives a single datagram, returning the number of bytes read, the
+    /// address it came from, the address it was originally sent to and
+    /// whether the datagram was truncated.
+    ///
+    /// The original destination is only known if the `IP_RECVORIGDSTADDR`
+    /// option was set with [`set_recv_orig_dst_addr`][link], and is `None`
+    /// otherwise. A datagram which did not fit in `buf` is truncated, with
+    /// the rest of it discarded, and the last element is `true`.
+    ///
+    /// [link]: #method.set_recv_orig_dst_addr
+    fn recv_from_orig_dst(&self, _buf: &mut [u8])
+                          -> io::Result<(usize, SocketAddr, Option<SocketAddr>,
+                                         bool)> {
+        Err(not_overridden())
+    }
+
+    /// Sets the value of the `IP_PKTINFO` option, or `IPV6_RECVPKTINFO` for
+    /// IPv6 sockets, on this socket.
+    ///
+    /// If set, the destination address and incoming interface of every
+    /// datagram is made available to [`recv_msg`][link]. This is only
+    /// supported on Linux and Android.
+    ///
+    /// [link]: #method.recv_msg
+    fn set_recv_pktinfo(&self, _recv: bool) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `IP_PKTINFO` or `IPV6_RECVPKTINFO` option on
+    /// this socket.
+    fn recv_pktinfo(&self) -> io::Result<bool> {
+        Err(not_overridden())
+    }
+
+    /// Receives a single datagram, returning the number of bytes read, the
+    /// address it came from, the address and interface it arrived on and
+    /// whether the datagram was truncated.
+    ///
+    /// The destination is only known if the `IP_PKTINFO` option was set
+    /// with [`set_recv_pktinfo`][link], and is `None` otherwise. For IPv4
+    /// this is the local address a reply should be sent from, `ipi_spec_dst`,
+    /// which is the address of the receiving interface rather than the
+    /// broadcast address for broadcasts. On IPv6 sockets IPv4 destinations
+    /// are returned as IPv4-mapped addresses.
+    ///
+    /// A datagram which did not fit in `buf` is truncated, with the rest of
+    /// it discarded, and the last element is `true`.
+    ///
+    /// [link]: #method.set_recv_pktinfo
+    fn recv_msg(&self, _buf: &mut [u8])
+                -> io::Result<(usize, SocketAddr, Option<PacketInfo>, bool)> {
+        Err(not_overridden())
+    }
+
+    /// Sends a datagram to `addr`, returning the number of bytes written.
+    ///
+    /// If `info` is given its address and interface are passed in an
+    /// `IP_PKTINFO` or `IPV6_PKTINFO` control message, which selects the
+    /// source address and outgoing interface of this datagram only. On IPv6
+    /// sockets an IPv4 address is sent as an IPv4-mapped address. This is
+    /// only supported on Linux and Android.
+    fn send_msg(&self, _buf: &[u8], _addr: &SocketAddr,
+                _info: Option<&PacketInfo>) -> io::Result<usize> {
+        Err(not_overridden())
+    }
+
+    /// Sets the value of the `SO_MARK` option on this socket.
+    ///
+    /// The mark is attached to every packet sent, for use by routing rules
+    /// and netfilter. It requires the `CAP_NET_ADMIN` capability and is only
+    /// supported on Linux and Android.
+    fn set_mark(&self, _mark: u32) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `SO_MARK` option on this socket.
+    fn mark(&self) -> io::Result<u32> {
+        Err(not_overridden())
+    }
+
+    /// Sets the value of the `SO_PRIORITY` option on this socket.
+    ///
+    /// This selects the queue packets are sent on by the queueing
+    /// discipline of the interface. Priorities above 6 require the
+    /// `CAP_NET_ADMIN` capability. This is only supported on Linux and
+    /// Android.
+    fn set_priority(&self, _priority: u32) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the value of the `SO_PRIORITY` option on this socket.
+    fn priority(&self) -> io::Result<u32> {
+        Err(not_overridden())
+    }
+
+    /// Sets the DSCP bits of the `IP_TOS` option, or `IPV6_TCLASS` for IPv6
+    /// sockets, on this socket, keeping its ECN bits.
+    ///
+    /// This is only supported on Linux and Android.
+    fn set_dscp(&self, _dscp: Dscp) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the DSCP bits of the `IP_TOS` or `IPV6_TCLASS` option on this
+    /// socket.
+    fn dscp(&self) -> io::Result<Dscp> {
+        Err(not_overridden())
+    }
+
+    /// Sets the ECN bits of the `IP_TOS` option, or `IPV6_TCLASS` for IPv6
+    /// sockets, on this socket, keeping its DSCP bits.
+    ///
+    /// This is only supported on Linux and Android.
+    fn set_ecn(&self, _ecn: Ecn) -> io::Result<()> {
+        Err(not_overridden())
+    }
+
+    /// Gets the ECN bits of the `IP_TOS` or `IPV6_TCLASS` option on this
+    /// socket.
+    fn ecn(&self) -> io::Result<Ecn> {
+        Err(not_overridden())
+    }
 }
 
 #[doc(hidden)]
@@ -651,6 +1276,17 @@ pub trait AsSock {
     fn as_sock(&self) -> Socket;
 }
 
+/// Calls `f` with `TcpStream` and `UdpSocket` views of `sock`, through
+/// which the extension traits can be used for any kind of socket. The
+/// views never close `sock`.
+pub(crate) fn with_views<F, R>(sock: Socket, f: F) -> R
+    where F: FnOnce(&TcpStream, &UdpSocket) -> R
+{
+    let tcp = ManuallyDrop::new(sys::Socket::from_inner(sock).into_tcp_stream());
+    let udp = ManuallyDrop::new(sys::Socket::from_inner(sock).into_udp_socket());
+    f(&tcp, &udp)
+}
+
 #[cfg(any(unix, target_os = "wasi"))]
 impl<T: AsRawFd> AsSock for T {
     fn as_sock(&self) -> Socket { self.as_raw_fd() }
@@ -662,11 +1298,11 @@ impl<T: AsRawSocket> AsSock for T {
 
 cfg_if! {
     if #[cfg(any(target_os = "macos", target_os = "ios", target_os = "nto"))] {
-        use libc::TCP_KEEPALIVE as KEEPALIVE_OPTION;
+        pub(crate) use libc::TCP_KEEPALIVE as KEEPALIVE_OPTION;
     } else if #[cfg(any(target_os = "haiku", target_os = "netbsd", target_os = "openbsd"))] {
-        use libc::SO_KEEPALIVE as KEEPALIVE_OPTION;
+        pub(crate) use libc::SO_KEEPALIVE as KEEPALIVE_OPTION;
     } else if #[cfg(unix)] {
-        use libc::TCP_KEEPIDLE as KEEPALIVE_OPTION;
+        pub(crate) use libc::TCP_KEEPIDLE as KEEPALIVE_OPTION;
     } else {
         // ...
     }
@@ -843,7 +1479,7 @@ impl TcpStreamExt for TcpStream {
     }
 
     fn connect<T: ToSocketAddrs>(&self, addr: T) -> io::Result<()> {
-        do_connect(self.as_sock(), addr)
+        do_connect_stream(self.as_sock(), addr)
     }
 
     fn take_error(&self) -> io::Result<Option<io::Error>> {
@@ -861,10 +1497,121 @@ impl TcpStreamExt for TcpStream {
     fn linger(&self) -> io::Result<Option<Duration>> {
         get_opt(self.as_sock(), SOL_SOCKET, SO_LINGER).map(linger2dur)
     }
+
+    fn set_tcp_keepalive(&self, keepalive: Option<TcpKeepalive>)
+                         -> io::Result<()> {
+        keepalive::set(keepalive, |level, name, val| {
+            set_opt(self.as_sock(), level, name, val)
+        })
+    }
+
+    fn tcp_keepalive(&self) -> io::Result<Option<TcpKeepalive>> {
+        keepalive::get(self.as_sock())
+    }
+
+    fn set_user_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let (level, name) = try!(keepalive::user_timeout_opt());
+        set_opt(self.as_sock(), level, name,
+                keepalive::dur2user_timeout(timeout))
+    }
+
+    fn user_timeout(&self) -> io::Result<Option<Duration>> {
+        let (level, name) = try!(keepalive::user_timeout_opt());
+        get_opt(self.as_sock(), level, name).map(keepalive::user_timeout2dur)
+    }
+
+    fn tcp_info(&self) -> io::Result<TcpInfo> {
+        tcp_info::get(self.as_sock())
+    }
+
+    fn set_congestion_control(&self, algorithm: &str) -> io::Result<()> {
+        let (level, name, payload) = try!(congestion_opt(algorithm));
+        set_opt(self.as_sock(), level, name, payload)
+    }
+
+    fn congestion_control(&self) -> io::Result<String> {
+        congestion_control(self.as_sock())
+    }
+
+    fn set_cork(&self, cork: bool) -> io::Result<()> {
+        let (level, name) = try!(cork_opt());
+        set_opt(self.as_sock(), level, name, cork as c_int)
+    }
+
+    fn cork(&self) -> io::Result<bool> {
+        let (level, name) = try!(cork_opt());
+        get_opt(self.as_sock(), level, name).map(int2bool)
+    }
+
+    fn set_notsent_lowat(&self, bytes: u32) -> io::Result<()> {
+        let (level, name) = try!(notsent_lowat_opt());
+        set_opt(self.as_sock(), level, name, bytes as c_int)
+            .map_err(|e| too_old(e, "3.12"))
+    }
+
+    fn notsent_lowat(&self) -> io::Result<u32> {
+        let (level, name) = try!(notsent_lowat_opt());
+        get_opt::<c_int>(self.as_sock(), level, name)
+            .map(|n| n as u32)
+            .map_err(|e| too_old(e, "3.12"))
+    }
+
+    fn set_quickack(&self, quickack: bool) -> io::Result<()> {
+        let (level, name) = try!(quickack_opt());
+        set_opt(self.as_sock(), level, name, quickack as c_int)
+    }
+
+    fn quickack(&self) -> io::Result<bool> {
+        let (level, name) = try!(quickack_opt());
+        get_opt(self.as_sock(), level, name).map(int2bool)
+    }
+
+    fn set_maxseg(&self, mss: u32) -> io::Result<()> {
+        let (level, name) = try!(maxseg_opt());
+        set_opt(self.as_sock(), level, name, mss as c_int)
+    }
+
+    fn maxseg(&self) -> io::Result<u32> {
+        let (level, name) = try!(maxseg_opt());
+        get_opt::<c_int>(self.as_sock(), level, name).map(|n| n as u32)
+    }
+
+    fn set_mark(&self, mark: u32) -> io::Result<()> {
+        let (level, name) = try!(mark_opt());
+        set_opt(self.as_sock(), level, name, mark as c_int)
+    }
+
+    fn mark(&self) -> io::Result<u32> {
+        let (level, name) = try!(mark_opt());
+        get_opt::<c_int>(self.as_sock(), level, name).map(|n| n as u32)
+    }
+
+    fn set_priority(&self, priority: u32) -> io::Result<()> {
+        let (level, name) = try!(priority_opt());
+        set_opt(self.as_sock(), level, name, priority as c_int)
+    }
+
+    fn priority(&self) -> io::Result<u32> {
+        let (level, name) = try!(priority_opt());
+        get_opt::<c_int>(self.as_sock(), level, name).map(|n| n as u32)
+    }
+
+    fn set_dscp(&self, dscp: Dscp) -> io::Result<()> {
+        let (level, name, payload) = try!(dscp_opt(self.as_sock(), dscp));
+        set_opt(self.as_sock(), level, name, payload)
+    }
+
+    fn dscp(&self) -> io::Result<Dscp> {
+        tos(self.as_sock()).map(qos::dscp)
+    }
+
+    fn original_dst(&self) -> io::Result<SocketAddr> {
+        original_dst(self.as_sock())
+    }
 }
 
 #[cfg(any(unix, target_os = "wasi"))]
-fn ms2timeout(dur: Option<u32>) -> timeval {
+pub fn ms2timeout(dur: Option<u32>) -> timeval {
     // TODO: be more rigorous
     match dur {
         Some(d) => timeval {
@@ -876,7 +1623,7 @@ fn ms2timeout(dur: Option<u32>) -> timeval {
 }
 
 #[cfg(any(unix, target_os = "wasi"))]
-fn timeout2ms(dur: timeval) -> Option<u32> {
+pub fn timeout2ms(dur: timeval) -> Option<u32> {
     if dur.tv_sec == 0 && dur.tv_usec == 0 {
         None
     } else {
@@ -885,12 +1632,12 @@ fn timeout2ms(dur: timeval) -> Option<u32> {
 }
 
 #[cfg(windows)]
-fn ms2timeout(dur: Option<u32>) -> DWORD {
+pub fn ms2timeout(dur: Option<u32>) -> DWORD {
     dur.unwrap_or(0)
 }
 
 #[cfg(windows)]
-fn timeout2ms(dur: DWORD) -> Option<u32> {
+pub fn timeout2ms(dur: DWORD) -> Option<u32> {
     if dur == 0 {
         None
     } else {
@@ -908,7 +1655,7 @@ fn linger2dur(linger_opt: linger) -> Option<Duration> {
 }
 
 #[cfg(windows)]
-fn dur2linger(dur: Option<Duration>) -> linger {
+pub(crate) fn dur2linger(dur: Option<Duration>) -> linger {
     match dur {
         Some(d) => {
             linger {
@@ -921,7 +1668,7 @@ fn dur2linger(dur: Option<Duration>) -> linger {
 }
 
 #[cfg(any(unix, target_os = "wasi"))]
-fn dur2linger(dur: Option<Duration>) -> linger {
+pub(crate) fn dur2linger(dur: Option<Duration>) -> linger {
     match dur {
         Some(d) => {
             linger {
@@ -933,16 +1680,16 @@ fn dur2linger(dur: Option<Duration>) -> linger {
     }
 }
 
-fn ms2dur(ms: u32) -> Duration {
-    Duration::new((ms as u64) / 1000, (ms as u32) % 1000 * 1_000_000)
+pub fn ms2dur(ms: u32) -> Duration {
+    Duration::new((ms as u64) / 1000, ms % 1000 * 1_000_000)
 }
 
-fn dur2ms(dur: Duration) -> u32 {
-    (dur.as_secs() as u32 * 1000) + (dur.subsec_nanos() / 1_000_000)
+pub fn dur2ms(dur: Duration) -> u32 {
+    (dur.as_secs() as u32 * 1000) + dur.subsec_millis()
 }
 
 pub fn int2bool(n: c_int) -> bool {
-    if n == 0 {false} else {true}
+    n != 0
 }
 
 pub fn int2usize(n: c_int) -> usize {
@@ -954,7 +1701,7 @@ pub fn int2err(n: c_int) -> Option<io::Error> {
     if n == 0 {
         None
     } else {
-        Some(io::Error::from_raw_os_error(n as i32))
+        Some(io::Error::from_raw_os_error(n))
     }
 }
 
@@ -1027,7 +1774,8 @@ impl UdpSocketExt for UdpSocket {
     }
 
     fn multicast_if_v4(&self) -> io::Result<Ipv4Addr> {
-        get_opt(self.as_sock(), IPPROTO_IP, IP_MULTICAST_IF).map(in_addr2ip)
+        get_opt::<in_addr>(self.as_sock(), IPPROTO_IP, IP_MULTICAST_IF)
+            .map(|a| in_addr2ip(&a))
     }
 
     fn set_multicast_if_v6(&self, _interface: u32) -> io::Result<()> {
@@ -1232,64 +1980,318 @@ impl UdpSocketExt for UdpSocket {
     fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
         set_nonblocking(self.as_sock(), nonblocking)
     }
-}
 
-fn do_connect<A: ToSocketAddrs>(sock: Socket, addr: A) -> io::Result<()> {
-    let err = io::Error::new(io::ErrorKind::Other,
-                             "no socket addresses resolved");
-    let addrs = try!(addr.to_socket_addrs());
-    let sys = sys::Socket::from_inner(sock);
-    let sock = socket::Socket::from_inner(sys);
-    let ret = addrs.fold(Err(err), |prev, addr| {
-        prev.or_else(|_| sock.connect(&addr))
-    });
-    mem::forget(sock);
-    return ret
-}
+    fn set_transparent(&self, transparent: bool) -> io::Result<()> {
+        let (level, name) = try!(transparent_opt(self.as_sock()));
+        set_opt(self.as_sock(), level, name, transparent as c_int)
+    }
 
-#[cfg(unix)]
-fn set_nonblocking(sock: Socket, nonblocking: bool) -> io::Result<()> {
-    let mut nonblocking = nonblocking as c_ulong;
-    ::cvt(unsafe {
-        ioctl(sock, FIONBIO, &mut nonblocking)
-    }).map(|_| ())
-}
+    fn transparent(&self) -> io::Result<bool> {
+        let (level, name) = try!(transparent_opt(self.as_sock()));
+        get_opt(self.as_sock(), level, name).map(int2bool)
+    }
 
-#[cfg(target_os = "wasi")]
-fn set_nonblocking(_sock: Socket, _nonblocking: bool) -> io::Result<()> {
-    Ok(())
-}
+    fn set_recv_orig_dst_addr(&self, recv: bool) -> io::Result<()> {
+        let (level, name) = try!(recv_orig_dst_opt(self.as_sock()));
+        set_opt(self.as_sock(), level, name, recv as c_int)
+    }
 
-#[cfg(windows)]
-fn set_nonblocking(sock: Socket, nonblocking: bool) -> io::Result<()> {
-    let mut nonblocking = nonblocking as c_ulong;
-    ::cvt(unsafe {
-        ioctlsocket(sock, FIONBIO as c_int, &mut nonblocking)
-    }).map(|_| ())
-}
+    fn recv_orig_dst_addr(&self) -> io::Result<bool> {
+        let (level, name) = try!(recv_orig_dst_opt(self.as_sock()));
+        get_opt(self.as_sock(), level, name).map(int2bool)
+    }
 
-#[cfg(any(unix, target_os = "wasi"))]
-fn ip2in_addr(ip: &Ipv4Addr) -> in_addr {
-    let oct = ip.octets();
-    in_addr {
-        s_addr: ::hton(((oct[0] as u32) << 24) |
-                       ((oct[1] as u32) << 16) |
+    fn recv_from_orig_dst(&self, buf: &mut [u8])
+                          -> io::Result<(usize, SocketAddr, Option<SocketAddr>,
+                                         bool)> {
+        recv_from_orig_dst(self.as_sock(), buf)
+    }
+
+    fn set_recv_pktinfo(&self, recv: bool) -> io::Result<()> {
+        let (level, name) = try!(recv_pktinfo_opt(self.as_sock()));
+        set_opt(self.as_sock(), level, name, recv as c_int)
+    }
+
+    fn recv_pktinfo(&self) -> io::Result<bool> {
+        let (level, name) = try!(recv_pktinfo_opt(self.as_sock()));
+        get_opt(self.as_sock(), level, name).map(int2bool)
+    }
+
+    fn recv_msg(&self, buf: &mut [u8])
+                -> io::Result<(usize, SocketAddr, Option<PacketInfo>, bool)> {
+        recv_msg(self.as_sock(), buf)
+    }
+
+    fn send_msg(&self, buf: &[u8], addr: &SocketAddr,
+                info: Option<&PacketInfo>) -> io::Result<usize> {
+        send_msg(self.as_sock(), buf, addr, info)
+    }
+
+    fn set_mark(&self, mark: u32) -> io::Result<()> {
+        let (level, name) = try!(mark_opt());
+        set_opt(self.as_sock(), level, name, mark as c_int)
+    }
+
+    fn mark(&self) -> io::Result<u32> {
+        let (level, name) = try!(mark_opt());
+        get_opt::<c_int>(self.as_sock(), level, name).map(|n| n as u32)
+    }
+
+    fn set_priority(&self, priority: u32) -> io::Result<()> {
+        let (level, name) = try!(priority_opt());
+        set_opt(self.as_sock(), level, name, priority as c_int)
+    }
+
+    fn priority(&self) -> io::Result<u32> {
+        let (level, name) = try!(priority_opt());
+        get_opt::<c_int>(self.as_sock(), level, name).map(|n| n as u32)
+    }
+
+    fn set_dscp(&self, dscp: Dscp) -> io::Result<()> {
+        let (level, name, payload) = try!(dscp_opt(self.as_sock(), dscp));
+        set_opt(self.as_sock(), level, name, payload)
+    }
+
+    fn dscp(&self) -> io::Result<Dscp> {
+        tos(self.as_sock()).map(qos::dscp)
+    }
+
+    fn set_ecn(&self, ecn: Ecn) -> io::Result<()> {
+        let (level, name, payload) = try!(ecn_opt(self.as_sock(), ecn));
+        set_opt(self.as_sock(), level, name, payload)
+    }
+
+    fn ecn(&self) -> io::Result<Ecn> {
+        tos(self.as_sock()).map(qos::ecn)
+    }
+}
+
+fn do_connect<A: ToSocketAddrs>(sock: Socket, addr: A) -> io::Result<()> {
+    let sys = sys::Socket::from_inner(sock);
+    let sock = socket::Socket::from_inner(sys);
+    let ret = error::each_addr(addr, |_, addr| sock.connect(addr));
+    mem::forget(sock);
+    ret
+}
+
+/// Like `do_connect`, but every attempt after the first is made on a fresh
+/// socket as the state of a stream socket is unspecified after a failed
+/// connect. The fresh socket takes over the original descriptor, so the
+/// caller's `TcpStream` stays valid.
+#[cfg(unix)]
+fn do_connect_stream<A: ToSocketAddrs>(sock: Socket, addr: A) -> io::Result<()> {
+    let sys = sys::Socket::from_inner(sock);
+    let sock = socket::Socket::from_inner(sys);
+    let ret = error::each_addr(addr, |i, addr| {
+        if i > 0 {
+            try!(renew_stream(&sock));
+        }
+        sock.connect(addr)
+    });
+    mem::forget(sock);
+    ret
+}
+
+/// Like `do_connect`, but as a socket can't be replaced behind its handle on
+/// Windows further attempts are only made after the errors which Winsock
+/// documents as leaving the socket usable for another connect.
+#[cfg(windows)]
+fn do_connect_stream<A: ToSocketAddrs>(sock: Socket, addr: A) -> io::Result<()> {
+    use std::time::Instant;
+
+    let addrs = try!(addr.to_socket_addrs());
+    let sys = sys::Socket::from_inner(sock);
+    let sock = socket::Socket::from_inner(sys);
+    let mut err = error::ConnectError::new();
+    for addr in addrs {
+        let start = Instant::now();
+        match sock.connect(&addr) {
+            Ok(()) => {
+                mem::forget(sock);
+                return Ok(())
+            }
+            Err(e) => {
+                let reusable = match e.kind() {
+                    io::ErrorKind::ConnectionRefused |
+                    io::ErrorKind::NetworkUnreachable |
+                    io::ErrorKind::TimedOut => true,
+                    _ => false,
+                };
+                err.push(addr, e, start.elapsed());
+                if !reusable {
+                    break
+                }
+            }
+        }
+    }
+    mem::forget(sock);
+    Err(err.into())
+}
+
+#[cfg(target_os = "wasi")]
+fn do_connect_stream<A: ToSocketAddrs>(sock: Socket, addr: A) -> io::Result<()> {
+    do_connect(sock, addr)
+}
+
+/// Replaces the socket behind `sock`'s descriptor with a fresh socket of the
+/// same family, carrying over the options this crate knows about as well as
+/// the local address and nonblocking mode.
+#[cfg(unix)]
+fn renew_stream(sock: &socket::Socket) -> io::Result<()> {
+    let old = sock.as_inner().raw();
+    let family = try!(sock.family());
+    let local = sock.getsockname().ok().and_then(|addr| {
+        if addr.port() != 0 || !addr.ip().is_unspecified() {
+            Some(addr)
+        } else {
+            None
+        }
+    });
+    let fresh = try!(socket::Socket::new(family, SOCK_STREAM));
+    let new = fresh.as_inner().raw();
+
+    let mut int_opts = vec![
+        (SOL_SOCKET, SO_REUSEADDR),
+        (SOL_SOCKET, SO_KEEPALIVE),
+        (v(IPPROTO_TCP), TCP_NODELAY),
+        (v(IPPROTO_TCP), KEEPALIVE_OPTION),
+        (IPPROTO_IP, IP_TTL),
+        (v(IPPROTO_IPV6), IPV6_V6ONLY),
+        (v(IPPROTO_IPV6), IPV6_UNICAST_HOPS),
+    ];
+    #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+    int_opts.push((SOL_SOCKET, SO_REUSEPORT));
+    // Options which aren't supported by the platform are skipped
+    let platform_opts = [
+        mark_opt(),
+        priority_opt(),
+        tos_opt(old),
+        notsent_lowat_opt(),
+        keepalive::user_timeout_opt(),
+        keepalive::interval_opt().map(|name| (v(IPPROTO_TCP), name)),
+        keepalive::retries_opt().map(|name| (v(IPPROTO_TCP), name)),
+    ];
+    int_opts.extend(platform_opts.iter().filter_map(|opt| opt.as_ref().ok()));
+    // Options which don't apply to this family simply fail to be read
+    for &(level, name) in int_opts.iter() {
+        try!(renew_opt::<c_int>(old, new, level, name, |val| val));
+    }
+    for &name in [SO_RCVBUF, SO_SNDBUF].iter() {
+        // Linux reports double the size that was requested
+        try!(renew_opt::<c_int>(old, new, SOL_SOCKET, name, |val| {
+            if cfg!(any(target_os = "linux", target_os = "android")) {
+                val / 2
+            } else {
+                val
+            }
+        }));
+    }
+    for &name in [SO_RCVTIMEO, SO_SNDTIMEO].iter() {
+        try!(renew_opt::<timeval>(old, new, SOL_SOCKET, name, |val| val));
+    }
+    try!(renew_opt::<linger>(old, new, SOL_SOCKET, SO_LINGER, |val| val));
+    for &(level, name, size) in renewed_bytes_opts().iter() {
+        let mut buf = vec![0; size];
+        let len = try!(get_opt_bytes(old, level, name, &mut buf));
+        let mut fresh_buf = vec![0; size];
+        let fresh_len = try!(get_opt_bytes(new, level, name, &mut fresh_buf));
+        if buf[..len] != fresh_buf[..fresh_len] {
+            try!(set_opt_bytes(new, level, name, &buf[..len]));
+        }
+    }
+    let flags = try!(::cvt(unsafe { fcntl(old, F_GETFL) }));
+    if flags & O_NONBLOCK != 0 {
+        try!(set_nonblocking(new, true));
+    }
+
+    try!(::cvt(unsafe { dup2(new, old) }));
+    drop(fresh);
+    if let Some(local) = local {
+        try!(sock.bind(&local));
+    }
+    Ok(())
+}
+
+/// Copies the option `name` at `level` from `old` onto `new`, passing it
+/// through `adjust`, unless it is left at the value a fresh socket starts
+/// out with. Setting an option can have side effects, such as turning off
+/// buffer autotuning, or need privileges.
+#[cfg(unix)]
+fn renew_opt<T: Copy>(old: Socket, new: Socket, level: c_int, name: c_int,
+                      adjust: fn(T) -> T) -> io::Result<()> {
+    let val = match get_opt::<T>(old, level, name) {
+        Ok(val) => val,
+        Err(_) => return Ok(()),
+    };
+    if let Ok(fresh) = get_opt::<T>(new, level, name) {
+        let as_bytes = |val: &T| unsafe {
+            ::std::slice::from_raw_parts(val as *const T as *const u8,
+                                         mem::size_of::<T>())
+        };
+        if as_bytes(&val) == as_bytes(&fresh) {
+            return Ok(())
+        }
+    }
+    set_opt(new, level, name, adjust(val))
+}
+
+/// Returns the options of variable length which `renew_stream` carries over
+/// along with their maximum size, the device a socket is bound to and its
+/// congestion control algorithm.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn renewed_bytes_opts() -> [(c_int, c_int, usize); 2] {
+    [(SOL_SOCKET, SO_BINDTODEVICE, IFNAMSIZ),
+     (IPPROTO_TCP, TCP_CONGESTION, TCP_CA_NAME_MAX)]
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn renewed_bytes_opts() -> [(c_int, c_int, usize); 0] {
+    []
+}
+
+#[cfg(unix)]
+pub fn set_nonblocking(sock: Socket, nonblocking: bool) -> io::Result<()> {
+    let mut nonblocking = nonblocking as c_ulong;
+    ::cvt(unsafe {
+        ioctl(sock, FIONBIO, &mut nonblocking)
+    }).map(|_| ())
+}
+
+#[cfg(target_os = "wasi")]
+pub fn set_nonblocking(_sock: Socket, _nonblocking: bool) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn set_nonblocking(sock: Socket, nonblocking: bool) -> io::Result<()> {
+    let mut nonblocking = nonblocking as c_ulong;
+    ::cvt(unsafe {
+        ioctlsocket(sock, FIONBIO as c_int, &mut nonblocking)
+    }).map(|_| ())
+}
+
+#[cfg(any(unix, target_os = "wasi"))]
+pub(crate) fn ip2in_addr(ip: &Ipv4Addr) -> in_addr {
+    let oct = ip.octets();
+    in_addr {
+        s_addr: ::hton(((oct[0] as u32) << 24) |
+                       ((oct[1] as u32) << 16) |
                        ((oct[2] as u32) <<  8) |
-                       ((oct[3] as u32) <<  0)),
+                       (oct[3] as u32)),
     }
 }
 
 #[cfg(windows)]
-fn ip2in_addr(ip: &Ipv4Addr) -> in_addr {
+pub(crate) fn ip2in_addr(ip: &Ipv4Addr) -> in_addr {
     let oct = ip.octets();
     unsafe {
         let mut S_un: in_addr_S_un = mem::zeroed();
         *S_un.S_addr_mut() = ::hton(((oct[0] as u32) << 24) |
                                 ((oct[1] as u32) << 16) |
                                 ((oct[2] as u32) <<  8) |
-                                ((oct[3] as u32) <<  0));
+                                (oct[3] as u32));
         in_addr {
-            S_un: S_un,
+            S_un,
         }
     }
 }
@@ -1300,18 +2302,18 @@ fn in_addr2ip(ip: &in_addr) -> Ipv4Addr {
     let a: u8 = (h_addr >> 24) as u8;
     let b: u8 = (h_addr >> 16) as u8;
     let c: u8 = (h_addr >> 8) as u8;
-    let d: u8 = (h_addr >> 0) as u8;
+    let d: u8 = h_addr as u8;
 
     Ipv4Addr::new(a,b,c,d)
 }
 
 #[cfg(target_os = "android")]
-fn to_ipv6mr_interface(value: u32) -> c_int {
+pub(crate) fn to_ipv6mr_interface(value: u32) -> c_int {
     value as c_int
 }
 
 #[cfg(not(target_os = "android"))]
-fn to_ipv6mr_interface(value: u32) -> c_uint {
+pub(crate) fn to_ipv6mr_interface(value: u32) -> c_uint {
     value as c_uint
 }
 
@@ -1320,26 +2322,26 @@ fn ip2in6_addr(ip: &Ipv6Addr) -> in6_addr {
     let seg = ip.segments();
     let bytes = [
         (seg[0] >> 8) as u8,
-        (seg[0] >> 0) as u8,
+        seg[0] as u8,
         (seg[1] >> 8) as u8,
-        (seg[1] >> 0) as u8,
+        seg[1] as u8,
         (seg[2] >> 8) as u8,
-        (seg[2] >> 0) as u8,
+        seg[2] as u8,
         (seg[3] >> 8) as u8,
-        (seg[3] >> 0) as u8,
+        seg[3] as u8,
         (seg[4] >> 8) as u8,
-        (seg[4] >> 0) as u8,
+        seg[4] as u8,
         (seg[5] >> 8) as u8,
-        (seg[5] >> 0) as u8,
+        seg[5] as u8,
         (seg[6] >> 8) as u8,
-        (seg[6] >> 0) as u8,
+        seg[6] as u8,
         (seg[7] >> 8) as u8,
-        (seg[7] >> 0) as u8,
+        seg[7] as u8,
     ];
     #[cfg(windows)] unsafe { *ret.u.Byte_mut() = bytes; }
     #[cfg(not(windows))]   { ret.s6_addr = bytes; }
 
-    return ret
+    ret
 }
 
 impl TcpListenerExt for TcpListener {
@@ -1375,106 +2377,1757 @@ impl TcpListenerExt for TcpListener {
     fn linger(&self) -> io::Result<Option<Duration>> {
         get_opt(self.as_sock(), SOL_SOCKET, SO_LINGER).map(linger2dur)
     }
+
+    fn set_transparent(&self, transparent: bool) -> io::Result<()> {
+        let (level, name) = try!(transparent_opt(self.as_sock()));
+        set_opt(self.as_sock(), level, name, transparent as c_int)
+    }
+
+    fn transparent(&self) -> io::Result<bool> {
+        let (level, name) = try!(transparent_opt(self.as_sock()));
+        get_opt(self.as_sock(), level, name).map(int2bool)
+    }
+
+    fn set_fastopen_keys(&self, primary: [u8; 16], backup: Option<[u8; 16]>)
+                         -> io::Result<()> {
+        set_fastopen_keys(self.as_sock(), primary, backup)
+    }
+
+    fn fastopen_keys(&self) -> io::Result<([u8; 16], Option<[u8; 16]>)> {
+        fastopen_keys(self.as_sock())
+    }
 }
 
-impl TcpBuilder {
-    /// Sets the value for the `IP_TTL` option on this socket.
-    ///
-    /// This is the same as [`TcpStreamExt::set_ttl`][other].
-    ///
-    /// [other]: trait.TcpStreamExt.html#tymethod.set_ttl
-    pub fn ttl(&self, ttl: u32) -> io::Result<&Self> {
-        set_opt(self.as_sock(), IPPROTO_IP, IP_TTL, ttl as c_int)
-            .map(|()| self)
+/// Returns the option binding a socket to the interface called `interface`,
+/// or unbinding it if `None`, as a level, name and payload.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn device_opt(interface: Option<&str>)
+              -> io::Result<(c_int, c_int, [c_char; IFNAMSIZ])> {
+    let interface = interface.unwrap_or("");
+    if interface.len() >= IFNAMSIZ || interface.as_bytes().contains(&0) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  format!("invalid interface name `{}`",
+                                          interface)))
+    }
+    let mut name = [0; IFNAMSIZ];
+    for (dst, &src) in name.iter_mut().zip(interface.as_bytes()) {
+        *dst = src as c_char;
     }
+    Ok((SOL_SOCKET, SO_BINDTODEVICE, name))
+}
 
-    /// Sets the value for the `IPV6_V6ONLY` option on this socket.
-    ///
-    /// This is the same as [`TcpStreamExt::set_only_v6`][other].
-    ///
-    /// [other]: trait.TcpStreamExt.html#tymethod.set_only_v6
-    pub fn only_v6(&self, only_v6: bool) -> io::Result<&Self> {
-        set_opt(self.as_sock(), v(IPPROTO_IPV6), IPV6_V6ONLY, only_v6 as c_int)
-            .map(|()| self)
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn device_opt(_interface: Option<&str>) -> io::Result<(c_int, c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "SO_BINDTODEVICE is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn device(sock: Socket) -> io::Result<Option<String>> {
+    let mut buf = [0; IFNAMSIZ];
+    let len = try!(get_opt_bytes(sock, SOL_SOCKET, SO_BINDTODEVICE, &mut buf));
+    let name = &buf[..len];
+    let name = match name.iter().position(|&b| b == 0) {
+        Some(end) => &name[..end],
+        None => name,
+    };
+    if name.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(String::from_utf8_lossy(name).into_owned()))
     }
+}
 
-    /// Set value for the `SO_REUSEADDR` option on this socket.
-    ///
-    /// This indicates that further calls to `bind` may allow reuse of local
-    /// addresses. For IPv4 sockets this means that a socket may bind even when
-    /// there's a socket already listening on this port.
-    pub fn reuse_address(&self, reuse: bool) -> io::Result<&Self> {
-        set_opt(self.as_sock(), SOL_SOCKET, SO_REUSEADDR,
-               reuse as c_int).map(|()| self)
+/// Adds the interface a socket was being bound to to `err`, along with a
+/// hint when the process lacks the privileges to bind it.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn device_err(err: io::Error, interface: String) -> io::Error {
+    let context = if error::raw_os_error(&err) == Some(EPERM) {
+        format!("{}, binding or rebinding a socket to an interface may \
+                 require CAP_NET_RAW", interface)
+    } else {
+        interface
+    };
+    error::with_context(err, context)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn device_err(err: io::Error, interface: String) -> io::Error {
+    error::with_context(err, interface)
+}
+
+/// Describes the argument of `bind_device` for `device_err`.
+pub(crate) fn device_name(interface: Option<&str>) -> String {
+    match interface {
+        Some(name) => format!("interface `{}`", name),
+        None => "removing the interface binding".to_string(),
     }
+}
 
-    /// Check the `SO_REUSEADDR` option on this socket.
-    pub fn get_reuse_address(&self) -> io::Result<bool> {
-        get_opt(self.as_sock(), SOL_SOCKET, SO_REUSEADDR).map(int2bool)
+/// Describes the argument of `bind_device_by_index` for `device_err`.
+fn device_index_name(index: u32) -> String {
+    match index {
+        0 => "removing the interface binding".to_string(),
+        index => format!("interface index {}", index),
     }
+}
 
-    /// Get the value of the `SO_ERROR` option on this socket.
-    ///
-    /// This will retrieve the stored error in the underlying socket, clearing
-    /// the field in the process. This can be useful for checking errors between
-    /// calls.
-    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
-        get_opt(self.as_sock(), SOL_SOCKET, SO_ERROR).map(int2err)
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn device(_sock: Socket) -> io::Result<Option<String>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "SO_BINDTODEVICE is not supported by platform"))
+}
+
+/// Returns the option binding `sock` to the interface with index `index`,
+/// or unbinding it if 0, as a level, name and payload.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn device_index_opt(_sock: Socket, index: u32)
+                    -> io::Result<(c_int, c_int, c_int)> {
+    Ok((SOL_SOCKET, c::SO_BINDTOIFINDEX, index as c_int))
+}
+
+#[cfg(windows)]
+fn device_index_opt(sock: Socket, index: u32)
+                    -> io::Result<(c_int, c_int, c_int)> {
+    unicast_if_opt(sock, index)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+fn device_index_opt(_sock: Socket, _index: u32)
+                    -> io::Result<(c_int, c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "binding to an interface index is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn device_index(sock: Socket) -> io::Result<u32> {
+    get_opt::<c_int>(sock, SOL_SOCKET, c::SO_BINDTOIFINDEX).map(|i| i as u32)
+}
+
+#[cfg(windows)]
+fn device_index(sock: Socket) -> io::Result<u32> {
+    unicast_if(sock)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+fn device_index(_sock: Socket) -> io::Result<u32> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "binding to an interface index is not supported by platform"))
+}
+
+/// Returns the option selecting the interface outgoing unicast packets of
+/// `sock` leave through as a level, name and payload.
+///
+/// Linux takes the index in network byte order for both families.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn unicast_if_opt(sock: Socket, index: u32)
+                  -> io::Result<(c_int, c_int, c_int)> {
+    if try!(is_v6(sock)) {
+        Ok((IPPROTO_IPV6, c::IPV6_UNICAST_IF, index.to_be() as c_int))
+    } else {
+        Ok((IPPROTO_IP, c::IP_UNICAST_IF, index.to_be() as c_int))
     }
+}
 
-    /// Sets the linger option for this socket
-    fn set_linger(&self, dur: Option<Duration>) -> io::Result<()> {
-        set_opt(self.as_sock(), SOL_SOCKET, SO_LINGER, dur2linger(dur))
+// IP_UNICAST_IF takes the index in network byte order, IPV6_UNICAST_IF in
+// host byte order. Only the one matching the family of the socket can be
+// read, which tells the two apart even before the socket is bound.
+#[cfg(windows)]
+fn unicast_if_opt(sock: Socket, index: u32)
+                  -> io::Result<(c_int, c_int, c_int)> {
+    if get_opt::<c_int>(sock, v(IPPROTO_IPV6), c::IPV6_UNICAST_IF).is_ok() {
+        Ok((v(IPPROTO_IPV6), c::IPV6_UNICAST_IF, index as c_int))
+    } else {
+        Ok((IPPROTO_IP, c::IP_UNICAST_IF, index.to_be() as c_int))
     }
+}
 
-    /// Gets the linger option for this socket
-    fn linger(&self) -> io::Result<Option<Duration>> {
-        get_opt(self.as_sock(), SOL_SOCKET, SO_LINGER).map(linger2dur)
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+fn unicast_if_opt(_sock: Socket, _index: u32)
+                  -> io::Result<(c_int, c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_UNICAST_IF is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn unicast_if(sock: Socket) -> io::Result<u32> {
+    let (level, name, _) = try!(unicast_if_opt(sock, 0));
+    get_opt::<c_int>(sock, level, name).map(|i| u32::from_be(i as u32))
+}
+
+#[cfg(windows)]
+fn unicast_if(sock: Socket) -> io::Result<u32> {
+    match get_opt::<c_int>(sock, v(IPPROTO_IPV6), c::IPV6_UNICAST_IF) {
+        Ok(index) => Ok(index as u32),
+        Err(_) => get_opt::<c_int>(sock, IPPROTO_IP, c::IP_UNICAST_IF)
+            .map(|i| u32::from_be(i as u32)),
     }
 }
 
-impl UdpBuilder {
-    /// Sets the value for the `IP_TTL` option on this socket.
-    ///
-    /// This is the same as [`TcpStreamExt::set_ttl`][other].
-    ///
-    /// [other]: trait.TcpStreamExt.html#tymethod.set_ttl
-    pub fn ttl(&self, ttl: u32) -> io::Result<&Self> {
-        set_opt(self.as_sock(), IPPROTO_IP, IP_TTL, ttl as c_int)
-            .map(|()| self)
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+fn unicast_if(_sock: Socket) -> io::Result<u32> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_UNICAST_IF is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_v6(sock: Socket) -> io::Result<bool> {
+    get_opt::<c_int>(sock, SOL_SOCKET, SO_DOMAIN).map(|d| d == AF_INET6)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn transparent_opt(sock: Socket) -> io::Result<(c_int, c_int)> {
+    if try!(is_v6(sock)) {
+        Ok((IPPROTO_IPV6, IPV6_TRANSPARENT))
+    } else {
+        Ok((IPPROTO_IP, IP_TRANSPARENT))
     }
+}
 
-    /// Sets the value for the `IPV6_V6ONLY` option on this socket.
-    ///
-    /// This is the same as [`TcpStream::only_v6`][other].
-    ///
-    /// [other]: struct.TcpBuilder.html#method.only_v6
-    pub fn only_v6(&self, only_v6: bool) -> io::Result<&Self> {
-        set_opt(self.as_sock(), v(IPPROTO_IPV6), IPV6_V6ONLY, only_v6 as c_int)
-            .map(|()| self)
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn transparent_opt(_sock: Socket) -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_TRANSPARENT is not supported by platform"))
+}
+
+// IP_FREEBIND applies to IPv6 sockets too, unlike IPV6_FREEBIND which needs
+// Linux 4.15
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn freebind_opt() -> io::Result<(c_int, c_int)> {
+    Ok((IPPROTO_IP, IP_FREEBIND))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn freebind_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_FREEBIND is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn recv_orig_dst_opt(sock: Socket) -> io::Result<(c_int, c_int)> {
+    if try!(is_v6(sock)) {
+        Ok((IPPROTO_IPV6, IPV6_RECVORIGDSTADDR))
+    } else {
+        Ok((IPPROTO_IP, IP_RECVORIGDSTADDR))
     }
+}
 
-    /// Set value for the `SO_REUSEADDR` option on this socket.
-    ///
-    /// This is the same as [`TcpBuilder::reuse_address`][other].
-    ///
-    /// [other]: struct.TcpBuilder.html#method.reuse_address
-    pub fn reuse_address(&self, reuse: bool) -> io::Result<&Self> {
-        set_opt(self.as_sock(), SOL_SOCKET, SO_REUSEADDR,
-               reuse as c_int).map(|()| self)
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn recv_orig_dst_opt(_sock: Socket) -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_RECVORIGDSTADDR is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn original_dst(sock: Socket) -> io::Result<SocketAddr> {
+    // An IPv4 connection accepted on an IPv6 socket is tracked as IPv4
+    let v6 = try!(is_v6(sock)) && {
+        let sock = ManuallyDrop::new(
+            socket::Socket::from_inner(sys::Socket::from_inner(sock)));
+        match try!(sock.getpeername()) {
+            SocketAddr::V6(addr) => addr.ip().to_ipv4_mapped().is_none(),
+            SocketAddr::V4(..) => false,
+        }
+    };
+    let (level, name) = if v6 {
+        (IPPROTO_IPV6, IP6T_SO_ORIGINAL_DST)
+    } else {
+        (IPPROTO_IP, SO_ORIGINAL_DST)
+    };
+    unsafe {
+        let mut storage: sockaddr_storage = mem::zeroed();
+        let len = {
+            let buf = ::std::slice::from_raw_parts_mut(
+                &mut storage as *mut _ as *mut u8, mem::size_of_val(&storage));
+            try!(get_opt_bytes(sock, level, name, buf))
+        };
+        socket::raw2addr(&storage, len as socklen_t)
     }
+}
 
-    /// Check the `SO_REUSEADDR` option on this socket.
-    pub fn get_reuse_address(&self) -> io::Result<bool> {
-        get_opt(self.as_sock(), SOL_SOCKET, SO_REUSEADDR).map(int2bool)
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn original_dst(_sock: Socket) -> io::Result<SocketAddr> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "SO_ORIGINAL_DST is not supported by platform"))
+}
+
+/// Receives a single datagram with `recvmsg`, passing the level, type and
+/// data of every control message received along with it to `f`.
+///
+/// Returns the number of bytes read, the source address and whether the
+/// datagram was truncated to fit in `buf`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn recv_with_cmsgs<F>(sock: Socket, buf: &mut [u8], mut f: F)
+                      -> io::Result<(usize, SocketAddr, bool)>
+    where F: FnMut(c_int, c_int, &[u8]) -> io::Result<()>
+{
+    unsafe {
+        let mut from: sockaddr_storage = mem::zeroed();
+        // Room for a few address sized control messages, aligned for cmsghdr
+        let mut control = [0u64; 16];
+        let mut iov = iovec {
+            iov_base: buf.as_mut_ptr() as *mut _,
+            iov_len: buf.len(),
+        };
+        let mut msg: msghdr = mem::zeroed();
+        msg.msg_name = &mut from as *mut _ as *mut _;
+        msg.msg_namelen = mem::size_of_val(&from) as socklen_t;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut _;
+        msg.msg_controllen = mem::size_of_val(&control) as _;
+        let n = try!(::cvt(recvmsg(sock, &mut msg, 0)));
+        let from = try!(socket::raw2addr(&from, msg.msg_namelen));
+
+        let mut cmsg = CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let len = (*cmsg).cmsg_len as usize - CMSG_LEN(0) as usize;
+            let data = ::std::slice::from_raw_parts(CMSG_DATA(cmsg), len);
+            try!(f((*cmsg).cmsg_level, (*cmsg).cmsg_type, data));
+            cmsg = CMSG_NXTHDR(&msg, cmsg);
+        }
+        if msg.msg_flags & MSG_CTRUNC != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "control messages of the datagram \
+                                       were truncated"))
+        }
+        Ok((n as usize, from, msg.msg_flags & MSG_TRUNC != 0))
     }
+}
 
-    /// Get the value of the `SO_ERROR` option on this socket.
-    ///
+/// Copies the data of a control message into a `T`, which must be no
+/// larger than the data.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn cmsg_data<T: Copy>(data: &[u8]) -> T {
+    let mut ret: T = mem::zeroed();
+    let len = ::std::cmp::min(data.len(), mem::size_of::<T>());
+    ::std::ptr::copy_nonoverlapping(data.as_ptr(),
+                                    &mut ret as *mut T as *mut u8, len);
+    ret
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn recv_from_orig_dst(sock: Socket, buf: &mut [u8])
+                      -> io::Result<(usize, SocketAddr, Option<SocketAddr>,
+                                     bool)> {
+    let mut dst = None;
+    let (n, from, trunc) = try!(recv_with_cmsgs(sock, buf, |level, ty, data| {
+        if (level == IPPROTO_IP && ty == IP_ORIGDSTADDR) ||
+           (level == IPPROTO_IPV6 && ty == IPV6_ORIGDSTADDR) {
+            let storage: sockaddr_storage = unsafe { cmsg_data(data) };
+            let len = ::std::cmp::min(data.len(), mem::size_of_val(&storage));
+            dst = Some(try!(socket::raw2addr(&storage, len as socklen_t)));
+        }
+        Ok(())
+    }));
+    Ok((n, from, dst, trunc))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn recv_from_orig_dst(_sock: Socket, _buf: &mut [u8])
+                      -> io::Result<(usize, SocketAddr, Option<SocketAddr>,
+                                     bool)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_RECVORIGDSTADDR is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn recv_pktinfo_opt(sock: Socket) -> io::Result<(c_int, c_int)> {
+    if try!(is_v6(sock)) {
+        Ok((IPPROTO_IPV6, IPV6_RECVPKTINFO))
+    } else {
+        Ok((IPPROTO_IP, IP_PKTINFO))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn recv_pktinfo_opt(_sock: Socket) -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_PKTINFO is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn recv_msg(sock: Socket, buf: &mut [u8])
+            -> io::Result<(usize, SocketAddr, Option<PacketInfo>, bool)> {
+    use std::net::IpAddr;
+
+    let mut info = None;
+    let (n, from, trunc) = try!(recv_with_cmsgs(sock, buf, |level, ty, data| {
+        if level == IPPROTO_IP && ty == IP_PKTINFO {
+            let pi: in_pktinfo = unsafe { cmsg_data(data) };
+            // The local address to reply from, which differs from the
+            // destination in the header for broadcasts
+            info = Some(PacketInfo {
+                addr: IpAddr::V4(in_addr2ip(&pi.ipi_spec_dst)),
+                interface: pi.ipi_ifindex as u32,
+            });
+        } else if level == IPPROTO_IPV6 && ty == IPV6_PKTINFO {
+            let pi: in6_pktinfo = unsafe { cmsg_data(data) };
+            let ip = Ipv6Addr::from(pi.ipi6_addr.s6_addr);
+            // A c_int on Android
+            #[allow(clippy::unnecessary_cast)]
+            let interface = pi.ipi6_ifindex as u32;
+            info = Some(PacketInfo {
+                addr: IpAddr::V6(ip),
+                interface,
+            });
+        }
+        Ok(())
+    }));
+    Ok((n, from, info, trunc))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn recv_msg(_sock: Socket, _buf: &mut [u8])
+            -> io::Result<(usize, SocketAddr, Option<PacketInfo>, bool)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_PKTINFO is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn send_msg(sock: Socket, buf: &[u8], addr: &SocketAddr,
+            info: Option<&PacketInfo>) -> io::Result<usize> {
+    use std::net::IpAddr;
+
+    unsafe {
+        // Room for one in6_pktinfo control message, aligned for cmsghdr
+        let mut control = [0u64; 8];
+        let mut controllen = 0;
+        if let Some(info) = info {
+            let v6 = try!(is_v6(sock));
+            let (level, ty, len) = match (info.addr, v6) {
+                (IpAddr::V4(..), false) => {
+                    (IPPROTO_IP, IP_PKTINFO, mem::size_of::<in_pktinfo>())
+                }
+                (_, true) => {
+                    (IPPROTO_IPV6, IPV6_PKTINFO, mem::size_of::<in6_pktinfo>())
+                }
+                (IpAddr::V6(..), false) => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                              "IPv6 source address on an \
+                                               IPv4 socket"))
+                }
+            };
+            controllen = CMSG_SPACE(len as u32) as usize;
+            let mut msg: msghdr = mem::zeroed();
+            msg.msg_control = control.as_mut_ptr() as *mut _;
+            msg.msg_controllen = controllen as _;
+            let cmsg = CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = level;
+            (*cmsg).cmsg_type = ty;
+            (*cmsg).cmsg_len = CMSG_LEN(len as u32) as _;
+            let data = CMSG_DATA(cmsg);
+            match info.addr {
+                IpAddr::V4(ref ip) if !v6 => {
+                    let mut pi: in_pktinfo = mem::zeroed();
+                    pi.ipi_ifindex = info.interface as c_int;
+                    pi.ipi_spec_dst = ip2in_addr(ip);
+                    ::std::ptr::write_unaligned(data as *mut in_pktinfo, pi);
+                }
+                ref ip => {
+                    let ip = match *ip {
+                        IpAddr::V4(ref ip) => ip.to_ipv6_mapped(),
+                        IpAddr::V6(ip) => ip,
+                    };
+                    let mut pi: in6_pktinfo = mem::zeroed();
+                    pi.ipi6_ifindex = info.interface as _;
+                    pi.ipi6_addr = ip2in6_addr(&ip);
+                    ::std::ptr::write_unaligned(data as *mut in6_pktinfo, pi);
+                }
+            }
+        }
+
+        let (to, tolen) = socket::addr2raw(addr);
+        let mut iov = iovec {
+            iov_base: buf.as_ptr() as *mut _,
+            iov_len: buf.len(),
+        };
+        let mut msg: msghdr = mem::zeroed();
+        msg.msg_name = to.as_ptr() as *mut _;
+        msg.msg_namelen = tolen;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        if controllen > 0 {
+            msg.msg_control = control.as_mut_ptr() as *mut _;
+            msg.msg_controllen = controllen as _;
+        }
+        let n = try!(::cvt(sendmsg(sock, &msg, MSG_NOSIGNAL)));
+        Ok(n as usize)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn send_msg(_sock: Socket, _buf: &[u8], _addr: &SocketAddr,
+            _info: Option<&PacketInfo>) -> io::Result<usize> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_PKTINFO is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn bind_address_no_port_opt() -> io::Result<(c_int, c_int)> {
+    Ok((IPPROTO_IP, IP_BIND_ADDRESS_NO_PORT))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn bind_address_no_port_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_BIND_ADDRESS_NO_PORT is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn local_port_range_opt() -> io::Result<(c_int, c_int)> {
+    Ok((IPPROTO_IP, c::IP_LOCAL_PORT_RANGE))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn local_port_range_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_LOCAL_PORT_RANGE is not supported by platform"))
+}
+
+// The range is packed into a single integer, the lower bound in the low 16
+// bits and the upper bound in the high 16 bits.
+fn range2int(range: (u16, u16)) -> u32 {
+    (range.0 as u32) | ((range.1 as u32) << 16)
+}
+
+pub(crate) fn int2range(n: u32) -> (u16, u16) {
+    (n as u16, (n >> 16) as u16)
+}
+
+/// Turns the `ENOPROTOOPT` a kernel fails with for an option it predates
+/// into an `Unsupported` error naming the release which introduced it.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn too_old(err: io::Error, release: &str) -> io::Error {
+    if error::raw_os_error(&err) != Some(ENOPROTOOPT) {
+        return err
+    }
+    let op = match error::Error::from_io(&err) {
+        Some(e) => e.operation(),
+        None => return err,
+    };
+    let msg = format!("not supported by this kernel, Linux {} or later is \
+                       required", release);
+    error::wrap(op, io::Error::new(io::ErrorKind::Unsupported, msg))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn too_old(err: io::Error, _release: &str) -> io::Error {
+    err
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn fastopen_opt() -> io::Result<(c_int, c_int)> {
+    Ok((IPPROTO_TCP, TCP_FASTOPEN))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn fastopen_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_FASTOPEN is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn fastopen_connect_opt() -> io::Result<(c_int, c_int)> {
+    Ok((IPPROTO_TCP, TCP_FASTOPEN_CONNECT))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn fastopen_connect_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_FASTOPEN_CONNECT is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_fastopen_keys(sock: Socket, primary: [u8; 16],
+                     backup: Option<[u8; 16]>) -> io::Result<()> {
+    match backup {
+        Some(backup) => {
+            let mut keys = [0; 32];
+            keys[..16].copy_from_slice(&primary);
+            keys[16..].copy_from_slice(&backup);
+            set_opt(sock, IPPROTO_TCP, TCP_FASTOPEN_KEY, keys)
+                .map_err(|e| too_old(e, "5.8"))
+        }
+        None => set_opt(sock, IPPROTO_TCP, TCP_FASTOPEN_KEY, primary)
+            .map_err(|e| too_old(e, "4.19")),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_fastopen_keys(_sock: Socket, _primary: [u8; 16],
+                     _backup: Option<[u8; 16]>) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_FASTOPEN_KEY is not supported by platform"))
+}
+
+/// Returns the option setting the TCP Fast Open cookie keys as a level,
+/// name and payload.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn fastopen_keys_opt(primary: [u8; 16], backup: Option<[u8; 16]>)
+                                -> io::Result<(c_int, c_int, Vec<u8>)> {
+    let mut keys = primary.to_vec();
+    if let Some(backup) = backup {
+        keys.extend_from_slice(&backup);
+    }
+    Ok((IPPROTO_TCP, TCP_FASTOPEN_KEY, keys))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn fastopen_keys_opt(_primary: [u8; 16], _backup: Option<[u8; 16]>)
+                                -> io::Result<(c_int, c_int, Vec<u8>)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_FASTOPEN_KEY is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn fastopen_keys(sock: Socket) -> io::Result<([u8; 16], Option<[u8; 16]>)> {
+    let mut keys = [0; 32];
+    let len = try!(get_opt_bytes(sock, IPPROTO_TCP, TCP_FASTOPEN_KEY, &mut keys)
+                   .map_err(|e| too_old(e, "4.19")));
+    let mut primary = [0; 16];
+    primary.copy_from_slice(&keys[..16]);
+    if len < 32 {
+        return Ok((primary, None))
+    }
+    let mut backup = [0; 16];
+    backup.copy_from_slice(&keys[16..]);
+    Ok((primary, Some(backup)))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn fastopen_keys(_sock: Socket) -> io::Result<([u8; 16], Option<[u8; 16]>)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_FASTOPEN_KEY is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const TCP_CA_NAME_MAX: usize = 16;
+
+/// Returns the option selecting the congestion control algorithm called
+/// `algorithm` as a level, name and payload.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn congestion_opt(algorithm: &str)
+                  -> io::Result<(c_int, c_int, [c_char; TCP_CA_NAME_MAX])> {
+    if algorithm.is_empty() || algorithm.len() >= TCP_CA_NAME_MAX ||
+       algorithm.as_bytes().contains(&0) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  format!("invalid congestion control \
+                                           algorithm `{}`", algorithm)))
+    }
+    let mut name = [0; TCP_CA_NAME_MAX];
+    for (dst, &src) in name.iter_mut().zip(algorithm.as_bytes()) {
+        *dst = src as c_char;
+    }
+    Ok((IPPROTO_TCP, TCP_CONGESTION, name))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn congestion_opt(_algorithm: &str) -> io::Result<(c_int, c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_CONGESTION is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn congestion_control(sock: Socket) -> io::Result<String> {
+    let mut buf = [0; TCP_CA_NAME_MAX];
+    let len = try!(get_opt_bytes(sock, IPPROTO_TCP, TCP_CONGESTION, &mut buf));
+    let name = &buf[..len];
+    let name = match name.iter().position(|&b| b == 0) {
+        Some(end) => &name[..end],
+        None => name,
+    };
+    Ok(String::from_utf8_lossy(name).into_owned())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn congestion_control(_sock: Socket) -> io::Result<String> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_CONGESTION is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn cork_opt() -> io::Result<(c_int, c_int)> {
+    Ok((IPPROTO_TCP, TCP_CORK))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn cork_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_CORK is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn notsent_lowat_opt() -> io::Result<(c_int, c_int)> {
+    Ok((IPPROTO_TCP, TCP_NOTSENT_LOWAT))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn notsent_lowat_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_NOTSENT_LOWAT is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn quickack_opt() -> io::Result<(c_int, c_int)> {
+    Ok((IPPROTO_TCP, TCP_QUICKACK))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn quickack_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_QUICKACK is not supported by platform"))
+}
+
+#[cfg(unix)]
+pub(crate) fn maxseg_opt() -> io::Result<(c_int, c_int)> {
+    Ok((IPPROTO_TCP, TCP_MAXSEG))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn maxseg_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_MAXSEG is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn mark_opt() -> io::Result<(c_int, c_int)> {
+    Ok((SOL_SOCKET, SO_MARK))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn mark_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "SO_MARK is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn priority_opt() -> io::Result<(c_int, c_int)> {
+    Ok((SOL_SOCKET, SO_PRIORITY))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn priority_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "SO_PRIORITY is not supported by platform"))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn tos_opt(sock: Socket) -> io::Result<(c_int, c_int)> {
+    if try!(is_v6(sock)) {
+        Ok((IPPROTO_IPV6, IPV6_TCLASS))
+    } else {
+        Ok((IPPROTO_IP, IP_TOS))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn tos_opt(_sock: Socket) -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "IP_TOS is not supported by platform"))
+}
+
+fn tos(sock: Socket) -> io::Result<u8> {
+    let (level, name) = try!(tos_opt(sock));
+    get_opt::<c_int>(sock, level, name).map(|n| n as u8)
+}
+
+/// Returns the option replacing the DSCP bits of the TOS byte or traffic
+/// class of `sock` as a level, name and payload.
+pub(crate) fn dscp_opt(sock: Socket, dscp: Dscp) -> io::Result<(c_int, c_int, c_int)> {
+    let (level, name) = try!(tos_opt(sock));
+    let tos = try!(tos(sock));
+    Ok((level, name, qos::with_dscp(tos, dscp) as c_int))
+}
+
+/// Returns the option replacing the ECN bits of the TOS byte or traffic
+/// class of `sock` as a level, name and payload.
+fn ecn_opt(sock: Socket, ecn: Ecn) -> io::Result<(c_int, c_int, c_int)> {
+    let (level, name) = try!(tos_opt(sock));
+    let tos = try!(tos(sock));
+    Ok((level, name, qos::with_ecn(tos, ecn) as c_int))
+}
+
+impl TcpBuilder {
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This is the same as [`TcpStreamExt::set_ttl`][other].
+    ///
+    /// [other]: trait.TcpStreamExt.html#tymethod.set_ttl
+    pub fn ttl(&self, ttl: u32) -> io::Result<&Self> {
+        self.set_opt(IPPROTO_IP, IP_TTL, ttl as c_int).map(|()| self)
+    }
+
+    /// Sets the value for the `IPV6_V6ONLY` option on this socket.
+    ///
+    /// This is the same as [`TcpStreamExt::set_only_v6`][other].
+    ///
+    /// [other]: trait.TcpStreamExt.html#tymethod.set_only_v6
+    pub fn only_v6(&self, only_v6: bool) -> io::Result<&Self> {
+        self.set_opt(v(IPPROTO_IPV6), IPV6_V6ONLY, only_v6 as c_int)
+            .map(|()| self)
+    }
+
+    /// Set value for the `SO_REUSEADDR` option on this socket.
+    ///
+    /// This indicates that further calls to `bind` may allow reuse of local
+    /// addresses. For IPv4 sockets this means that a socket may bind even when
+    /// there's a socket already listening on this port.
+    pub fn reuse_address(&self, reuse: bool) -> io::Result<&Self> {
+        self.set_opt(SOL_SOCKET, SO_REUSEADDR, reuse as c_int).map(|()| self)
+    }
+
+    /// Check the `SO_REUSEADDR` option on this socket.
+    pub fn get_reuse_address(&self) -> io::Result<bool> {
+        get_opt(self.as_sock(), SOL_SOCKET, SO_REUSEADDR).map(int2bool)
+    }
+
+    /// Get the value of the `SO_ERROR` option on this socket.
+    ///
+    /// This will retrieve the stored error in the underlying socket, clearing
+    /// the field in the process. This can be useful for checking errors between
+    /// calls.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        get_opt(self.as_sock(), SOL_SOCKET, SO_ERROR).map(int2err)
+    }
+
+    /// Sets the linger option for this socket
+    fn set_linger(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.set_opt(SOL_SOCKET, SO_LINGER, dur2linger(dur))
+    }
+
+    /// Gets the linger option for this socket
+    fn linger(&self) -> io::Result<Option<Duration>> {
+        get_opt(self.as_sock(), SOL_SOCKET, SO_LINGER).map(linger2dur)
+    }
+
+    /// Binds this socket to the network interface or VRF called `interface`
+    /// with the `SO_BINDTODEVICE` option, or removes the binding if `None`.
+    ///
+    /// Only packets received on the interface are processed and packets are
+    /// sent out of it regardless of the routing table. This is only
+    /// supported on Linux and Android, and on Linux before 5.7 requires the
+    /// `CAP_NET_RAW` capability. An unknown interface fails with `ENODEV`.
+    pub fn bind_device(&self, interface: Option<&str>) -> io::Result<&Self> {
+        let (level, name, payload) = try!(device_opt(interface));
+        self.set_opt(level, name, payload)
+            .map_err(|e| device_err(e, device_name(interface)))
+            .map(|()| self)
+    }
+
+    /// Returns the name of the interface this socket is bound to with
+    /// `SO_BINDTODEVICE`, if any.
+    pub fn device(&self) -> io::Result<Option<String>> {
+        device(self.as_sock())
+    }
+
+    /// Binds this socket to the network interface with index `index`, or
+    /// removes the binding if `index` is 0.
+    ///
+    /// This uses the `SO_BINDTOIFINDEX` option on Linux 5.0 and later and on
+    /// Android, and `IP_UNICAST_IF` or `IPV6_UNICAST_IF`, depending on the
+    /// family of the socket, on Windows. On Windows only outgoing packets
+    /// are affected.
+    ///
+    /// On Linux this also filters incoming packets, like `bind_device`, and
+    /// changing an existing binding requires `CAP_NET_RAW`. To only select
+    /// the interface outgoing packets leave through, which any process may
+    /// do, use [`unicast_if`][unicast] instead.
+    ///
+    /// [unicast]: #method.unicast_if
+    pub fn bind_device_by_index(&self, index: u32) -> io::Result<&Self> {
+        let (level, name, payload) =
+            try!(device_index_opt(self.as_sock(), index));
+        self.set_opt(level, name, payload)
+            .map_err(|e| device_err(e, device_index_name(index)))
+            .map(|()| self)
+    }
+
+    /// Returns the index of the interface this socket is bound to, or 0 if
+    /// it is not bound to an interface.
+    ///
+    /// For more information see [`bind_device_by_index`][link].
+    ///
+    /// [link]: #method.bind_device_by_index
+    pub fn device_index(&self) -> io::Result<u32> {
+        device_index(self.as_sock())
+    }
+
+    /// Sets the interface outgoing unicast packets of this socket leave
+    /// through to the one with index `index`, or clears it if `index` is 0.
+    ///
+    /// This uses the `IP_UNICAST_IF` option, or `IPV6_UNICAST_IF` for IPv6
+    /// sockets, and is supported on Linux and Windows. Unlike
+    /// [`bind_device_by_index`][bind] incoming packets are not filtered and
+    /// no privileges are needed.
+    ///
+    /// [bind]: #method.bind_device_by_index
+    pub fn unicast_if(&self, index: u32) -> io::Result<&Self> {
+        let (level, name, payload) =
+            try!(unicast_if_opt(self.as_sock(), index));
+        self.set_opt(level, name, payload).map(|()| self)
+    }
+
+    /// Returns the index of the interface set with [`unicast_if`][link], or
+    /// 0 if none is set.
+    ///
+    /// [link]: #method.unicast_if
+    pub fn get_unicast_if(&self) -> io::Result<u32> {
+        unicast_if(self.as_sock())
+    }
+
+    /// Sets the value of the `IP_TRANSPARENT` option, or `IPV6_TRANSPARENT`
+    /// for IPv6 sockets, on this socket.
+    ///
+    /// This allows binding to addresses which are not local to this host,
+    /// such as the client's address when connecting upstream from a
+    /// transparent proxy. It requires the `CAP_NET_ADMIN` capability and is
+    /// only supported on Linux and Android.
+    pub fn transparent(&self, transparent: bool) -> io::Result<&Self> {
+        let (level, name) = try!(transparent_opt(self.as_sock()));
+        self.set_opt(level, name, transparent as c_int).map(|()| self)
+    }
+
+    /// Gets the value of the `IP_TRANSPARENT` or `IPV6_TRANSPARENT` option on
+    /// this socket.
+    pub fn get_transparent(&self) -> io::Result<bool> {
+        let (level, name) = try!(transparent_opt(self.as_sock()));
+        get_opt(self.as_sock(), level, name).map(int2bool)
+    }
+
+    /// Sets the value of the `IP_FREEBIND` option on this socket.
+    ///
+    /// This allows binding to addresses which are not, or not yet, assigned
+    /// to an interface of this host. Unlike `IP_TRANSPARENT` it needs no
+    /// privileges. This is only supported on Linux and Android.
+    pub fn freebind(&self, freebind: bool) -> io::Result<&Self> {
+        let (level, name) = try!(freebind_opt());
+        self.set_opt(level, name, freebind as c_int).map(|()| self)
+    }
+
+    /// Gets the value of the `IP_FREEBIND` option on this socket.
+    pub fn get_freebind(&self) -> io::Result<bool> {
+        let (level, name) = try!(freebind_opt());
+        get_opt(self.as_sock(), level, name).map(int2bool)
+    }
+
+    /// Sets the value of the `IP_BIND_ADDRESS_NO_PORT` option on this
+    /// socket.
+    ///
+    /// If set, binding to an address with port 0 does not reserve a port
+    /// straight away. The port is picked by `connect` instead, once the
+    /// whole 4-tuple is known, so the same port can be shared by connections
+    /// to different destinations. This is only supported on Linux 4.2 and
+    /// later and on Android.
+    pub fn bind_address_no_port(&self, no_port: bool) -> io::Result<&Self> {
+        let (level, name) = try!(bind_address_no_port_opt());
+        self.set_opt(level, name, no_port as c_int)
+            .map_err(|e| too_old(e, "4.2"))
+            .map(|()| self)
+    }
+
+    /// Gets the value of the `IP_BIND_ADDRESS_NO_PORT` option on this socket.
+    pub fn get_bind_address_no_port(&self) -> io::Result<bool> {
+        let (level, name) = try!(bind_address_no_port_opt());
+        get_opt(self.as_sock(), level, name)
+            .map_err(|e| too_old(e, "4.2"))
+            .map(int2bool)
+    }
+
+    /// Sets the value of the `IP_LOCAL_PORT_RANGE` option on this socket.
+    ///
+    /// This restricts the ephemeral ports picked for this socket to the
+    /// inclusive range `(low, high)`, within the system wide
+    /// `ip_local_port_range`. A bound of 0 leaves that end of the system
+    /// range in place. This is only supported on Linux 6.3 and later.
+    pub fn local_port_range(&self, range: (u16, u16)) -> io::Result<&Self> {
+        let (level, name) = try!(local_port_range_opt());
+        self.set_opt(level, name, range2int(range))
+            .map_err(|e| too_old(e, "6.3"))
+            .map(|()| self)
+    }
+
+    /// Gets the value of the `IP_LOCAL_PORT_RANGE` option on this socket.
+    pub fn get_local_port_range(&self) -> io::Result<(u16, u16)> {
+        let (level, name) = try!(local_port_range_opt());
+        get_opt(self.as_sock(), level, name)
+            .map_err(|e| too_old(e, "6.3"))
+            .map(int2range)
+    }
+
+    /// Enables `SO_KEEPALIVE` and sets the parameters of keepalive probes,
+    /// or disables keepalive probes if `None` is specified.
+    ///
+    /// This is the same as [`TcpStreamExt::set_tcp_keepalive`][other].
+    ///
+    /// [other]: trait.TcpStreamExt.html#method.set_tcp_keepalive
+    pub fn tcp_keepalive(&self, keepalive: Option<TcpKeepalive>)
+                         -> io::Result<&Self> {
+        keepalive::set(keepalive, |level, name, val| {
+            self.set_opt(level, name, val)
+        }).map(|()| self)
+    }
+
+    /// Returns the parameters of keepalive probes if they are enabled on
+    /// this socket.
+    pub fn get_tcp_keepalive(&self) -> io::Result<Option<TcpKeepalive>> {
+        keepalive::get(self.as_sock())
+    }
+
+    /// Sets the value of the `TCP_USER_TIMEOUT` option on this socket.
+    ///
+    /// This is the same as [`TcpStreamExt::set_user_timeout`][other].
+    ///
+    /// [other]: trait.TcpStreamExt.html#method.set_user_timeout
+    pub fn user_timeout(&self, timeout: Option<Duration>) -> io::Result<&Self> {
+        let (level, name) = try!(keepalive::user_timeout_opt());
+        self.set_opt(level, name, keepalive::dur2user_timeout(timeout))
+            .map(|()| self)
+    }
+
+    /// Gets the value of the `TCP_USER_TIMEOUT` option on this socket.
+    pub fn get_user_timeout(&self) -> io::Result<Option<Duration>> {
+        let (level, name) = try!(keepalive::user_timeout_opt());
+        get_opt(self.as_sock(), level, name).map(keepalive::user_timeout2dur)
+    }
+
+    /// Selects the congestion control algorithm used by this socket, such as
+    /// `"cubic"` or `"bbr"`, with the `TCP_CONGESTION` option.
+    ///
+    /// This is the same as [`TcpStreamExt::set_congestion_control`][other].
+    ///
+    /// [other]: trait.TcpStreamExt.html#method.set_congestion_control
+    pub fn congestion_control(&self, algorithm: &str) -> io::Result<&Self> {
+        let (level, name, payload) = try!(congestion_opt(algorithm));
+        self.set_opt(level, name, payload).map(|()| self)
+    }
+
+    /// Returns the name of the congestion control algorithm used by this
+    /// socket.
+    pub fn get_congestion_control(&self) -> io::Result<String> {
+        congestion_control(self.as_sock())
+    }
+
+    /// Sets the value of the `TCP_MAXSEG` option on this socket.
+    ///
+    /// Set before connecting or listening, this caps the maximum segment size
+    /// advertised to and used with the peer. This is not supported on
+    /// Windows.
+    pub fn maxseg(&self, mss: u32) -> io::Result<&Self> {
+        let (level, name) = try!(maxseg_opt());
+        self.set_opt(level, name, mss as c_int).map(|()| self)
+    }
+
+    /// Gets the value of the `TCP_MAXSEG` option on this socket.
+    pub fn get_maxseg(&self) -> io::Result<u32> {
+        let (level, name) = try!(maxseg_opt());
+        get_opt::<c_int>(self.as_sock(), level, name).map(|n| n as u32)
+    }
+
+    /// Sets the value of the `SO_MARK` option on this socket.
+    ///
+    /// This is the same as [`TcpStreamExt::set_mark`][mark].
+    ///
+    /// [mark]: trait.TcpStreamExt.html#method.set_mark
+    pub fn mark(&self, mark: u32) -> io::Result<&Self> {
+        let (level, name) = try!(mark_opt());
+        self.set_opt(level, name, mark as c_int).map(|()| self)
+    }
+
+    /// Gets the value of the `SO_MARK` option on this socket.
+    pub fn get_mark(&self) -> io::Result<u32> {
+        let (level, name) = try!(mark_opt());
+        get_opt::<c_int>(self.as_sock(), level, name).map(|n| n as u32)
+    }
+
+    /// Sets the value of the `SO_PRIORITY` option on this socket.
+    ///
+    /// This is the same as [`TcpStreamExt::set_priority`][priority].
+    ///
+    /// [priority]: trait.TcpStreamExt.html#method.set_priority
+    pub fn priority(&self, priority: u32) -> io::Result<&Self> {
+        let (level, name) = try!(priority_opt());
+        self.set_opt(level, name, priority as c_int).map(|()| self)
+    }
+
+    /// Gets the value of the `SO_PRIORITY` option on this socket.
+    pub fn get_priority(&self) -> io::Result<u32> {
+        let (level, name) = try!(priority_opt());
+        get_opt::<c_int>(self.as_sock(), level, name).map(|n| n as u32)
+    }
+
+    /// Sets the DSCP bits of the `IP_TOS` option, or `IPV6_TCLASS` for IPv6
+    /// sockets, on this socket.
+    ///
+    /// This is the same as [`TcpStreamExt::set_dscp`][dscp].
+    ///
+    /// [dscp]: trait.TcpStreamExt.html#method.set_dscp
+    pub fn dscp(&self, dscp: Dscp) -> io::Result<&Self> {
+        let (level, name, payload) = try!(dscp_opt(self.as_sock(), dscp));
+        self.set_opt(level, name, payload).map(|()| self)
+    }
+
+    /// Gets the DSCP bits of the `IP_TOS` or `IPV6_TCLASS` option on this
+    /// socket.
+    pub fn get_dscp(&self) -> io::Result<Dscp> {
+        tos(self.as_sock()).map(qos::dscp)
+    }
+
+    /// Sets the value of the `TCP_FASTOPEN` option on this socket.
+    ///
+    /// This enables TCP Fast Open on a socket about to `listen`, allowing
+    /// clients holding a valid cookie to send data in their SYN. The value
+    /// is the maximum number of such connections which have not completed
+    /// their handshake yet, 0 disabling Fast Open. This is only supported on
+    /// Linux and Android, and needs bit 1 of the `net.ipv4.tcp_fastopen`
+    /// sysctl set.
+    pub fn fastopen(&self, queue_len: u32) -> io::Result<&Self> {
+        let (level, name) = try!(fastopen_opt());
+        self.set_opt(level, name, queue_len as c_int).map(|()| self)
+    }
+
+    /// Gets the value of the `TCP_FASTOPEN` option on this socket.
+    pub fn get_fastopen(&self) -> io::Result<u32> {
+        let (level, name) = try!(fastopen_opt());
+        get_opt::<c_int>(self.as_sock(), level, name).map(|n| n as u32)
+    }
+
+    /// Sets the value of the `TCP_FASTOPEN_CONNECT` option on this socket.
+    ///
+    /// If set, `connect` returns straight away without sending a SYN and the
+    /// first write to the stream is sent in the SYN with TCP Fast Open. This
+    /// is an alternative to [`connect_with_data`][link] for code which only
+    /// writes through the returned stream. This is only supported on Linux
+    /// 4.11 and later and on Android.
+    ///
+    /// Unlike `connect_with_data` this has no fallback: it fails with
+    /// `EOPNOTSUPP` unless bit 0 of the `net.ipv4.tcp_fastopen` sysctl is
+    /// set.
+    ///
+    /// [link]: #method.connect_with_data
+    pub fn fastopen_connect(&self, enable: bool) -> io::Result<&Self> {
+        let (level, name) = try!(fastopen_connect_opt());
+        self.set_opt(level, name, enable as c_int)
+            .map_err(|e| too_old(e, "4.11"))
+            .map(|()| self)
+    }
+
+    /// Gets the value of the `TCP_FASTOPEN_CONNECT` option on this socket.
+    pub fn get_fastopen_connect(&self) -> io::Result<bool> {
+        let (level, name) = try!(fastopen_connect_opt());
+        get_opt(self.as_sock(), level, name)
+            .map_err(|e| too_old(e, "4.11"))
+            .map(int2bool)
+    }
+}
+
+impl UdpBuilder {
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This is the same as [`TcpStreamExt::set_ttl`][other].
+    ///
+    /// [other]: trait.TcpStreamExt.html#tymethod.set_ttl
+    pub fn ttl(&self, ttl: u32) -> io::Result<&Self> {
+        set_opt(self.as_sock(), IPPROTO_IP, IP_TTL, ttl as c_int)
+            .map(|()| self)
+    }
+
+    /// Sets the value for the `IPV6_V6ONLY` option on this socket.
+    ///
+    /// This is the same as [`TcpStream::only_v6`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.only_v6
+    pub fn only_v6(&self, only_v6: bool) -> io::Result<&Self> {
+        set_opt(self.as_sock(), v(IPPROTO_IPV6), IPV6_V6ONLY, only_v6 as c_int)
+            .map(|()| self)
+    }
+
+    /// Set value for the `SO_REUSEADDR` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::reuse_address`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.reuse_address
+    pub fn reuse_address(&self, reuse: bool) -> io::Result<&Self> {
+        set_opt(self.as_sock(), SOL_SOCKET, SO_REUSEADDR,
+               reuse as c_int).map(|()| self)
+    }
+
+    /// Check the `SO_REUSEADDR` option on this socket.
+    pub fn get_reuse_address(&self) -> io::Result<bool> {
+        get_opt(self.as_sock(), SOL_SOCKET, SO_REUSEADDR).map(int2bool)
+    }
+
+    /// Get the value of the `SO_ERROR` option on this socket.
+    ///
+    /// This will retrieve the stored error in the underlying socket, clearing
+    /// the field in the process. This can be useful for checking errors between
+    /// calls.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        get_opt(self.as_sock(), SOL_SOCKET, SO_ERROR).map(int2err)
+    }
+
+    /// Binds this socket to the network interface or VRF called `interface`,
+    /// or removes the binding if `None`.
+    ///
+    /// This is the same as [`TcpBuilder::bind_device`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.bind_device
+    pub fn bind_device(&self, interface: Option<&str>) -> io::Result<&Self> {
+        let (level, name, payload) = try!(device_opt(interface));
+        set_opt(self.as_sock(), level, name, payload)
+            .map_err(|e| device_err(e, device_name(interface)))
+            .map(|()| self)
+    }
+
+    /// Returns the name of the interface this socket is bound to with
+    /// `SO_BINDTODEVICE`, if any.
+    pub fn device(&self) -> io::Result<Option<String>> {
+        device(self.as_sock())
+    }
+
+    /// Binds this socket to the network interface with index `index`, or
+    /// removes the binding if `index` is 0.
+    ///
+    /// This is the same as [`TcpBuilder::bind_device_by_index`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.bind_device_by_index
+    pub fn bind_device_by_index(&self, index: u32) -> io::Result<&Self> {
+        let (level, name, payload) =
+            try!(device_index_opt(self.as_sock(), index));
+        set_opt(self.as_sock(), level, name, payload)
+            .map_err(|e| device_err(e, device_index_name(index)))
+            .map(|()| self)
+    }
+
+    /// Returns the index of the interface this socket is bound to, or 0 if
+    /// it is not bound to an interface.
+    pub fn device_index(&self) -> io::Result<u32> {
+        device_index(self.as_sock())
+    }
+
+    /// Sets the interface outgoing unicast packets of this socket leave
+    /// through to the one with index `index`, or clears it if `index` is 0.
+    ///
+    /// This is the same as [`TcpBuilder::unicast_if`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.unicast_if
+    pub fn unicast_if(&self, index: u32) -> io::Result<&Self> {
+        let (level, name, payload) =
+            try!(unicast_if_opt(self.as_sock(), index));
+        set_opt(self.as_sock(), level, name, payload).map(|()| self)
+    }
+
+    /// Returns the index of the interface set with [`unicast_if`][link], or
+    /// 0 if none is set.
+    ///
+    /// [link]: #method.unicast_if
+    pub fn get_unicast_if(&self) -> io::Result<u32> {
+        unicast_if(self.as_sock())
+    }
+
+    /// Sets the value of the `IP_TRANSPARENT` option, or `IPV6_TRANSPARENT`
+    /// for IPv6 sockets, on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::transparent`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.transparent
+    pub fn transparent(&self, transparent: bool) -> io::Result<&Self> {
+        let (level, name) = try!(transparent_opt(self.as_sock()));
+        set_opt(self.as_sock(), level, name, transparent as c_int)
+            .map(|()| self)
+    }
+
+    /// Gets the value of the `IP_TRANSPARENT` or `IPV6_TRANSPARENT` option on
+    /// this socket.
+    pub fn get_transparent(&self) -> io::Result<bool> {
+        let (level, name) = try!(transparent_opt(self.as_sock()));
+        get_opt(self.as_sock(), level, name).map(int2bool)
+    }
+
+    /// Sets the value of the `IP_FREEBIND` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::freebind`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.freebind
+    pub fn freebind(&self, freebind: bool) -> io::Result<&Self> {
+        let (level, name) = try!(freebind_opt());
+        set_opt(self.as_sock(), level, name, freebind as c_int).map(|()| self)
+    }
+
+    /// Gets the value of the `IP_FREEBIND` option on this socket.
+    pub fn get_freebind(&self) -> io::Result<bool> {
+        let (level, name) = try!(freebind_opt());
+        get_opt(self.as_sock(), level, name).map(int2bool)
+    }
+
+    /// Sets the value of the `IP_RECVORIGDSTADDR` option, or
+    /// `IPV6_RECVORIGDSTADDR` for IPv6 sockets, on this socket.
+    ///
+    /// This is the same as
+    /// [`UdpSocketExt::set_recv_orig_dst_addr`][other].
+    ///
+    /// [other]: trait.UdpSocketExt.html#method.set_recv_orig_dst_addr
+    pub fn recv_orig_dst_addr(&self, recv: bool) -> io::Result<&Self> {
+        let (level, name) = try!(recv_orig_dst_opt(self.as_sock()));
+        set_opt(self.as_sock(), level, name, recv as c_int).map(|()| self)
+    }
+
+    /// Sets the value of the `IP_PKTINFO` option, or `IPV6_RECVPKTINFO` for
+    /// IPv6 sockets, on this socket.
+    ///
+    /// This is the same as [`UdpSocketExt::set_recv_pktinfo`][other].
+    ///
+    /// [other]: trait.UdpSocketExt.html#method.set_recv_pktinfo
+    pub fn recv_pktinfo(&self, recv: bool) -> io::Result<&Self> {
+        let (level, name) = try!(recv_pktinfo_opt(self.as_sock()));
+        set_opt(self.as_sock(), level, name, recv as c_int).map(|()| self)
+    }
+
+    /// Sets the value of the `IP_LOCAL_PORT_RANGE` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::local_port_range`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.local_port_range
+    pub fn local_port_range(&self, range: (u16, u16)) -> io::Result<&Self> {
+        let (level, name) = try!(local_port_range_opt());
+        set_opt(self.as_sock(), level, name, range2int(range))
+            .map_err(|e| too_old(e, "6.3"))
+            .map(|()| self)
+    }
+
+    /// Gets the value of the `IP_LOCAL_PORT_RANGE` option on this socket.
+    pub fn get_local_port_range(&self) -> io::Result<(u16, u16)> {
+        let (level, name) = try!(local_port_range_opt());
+        get_opt(self.as_sock(), level, name)
+            .map_err(|e| too_old(e, "6.3"))
+            .map(int2range)
+    }
+
+    /// Sets the value of the `SO_MARK` option on this socket.
+    ///
+    /// This is the same as [`UdpSocketExt::set_mark`][mark].
+    ///
+    /// [mark]: trait.UdpSocketExt.html#method.set_mark
+    pub fn mark(&self, mark: u32) -> io::Result<&Self> {
+        let (level, name) = try!(mark_opt());
+        set_opt(self.as_sock(), level, name, mark as c_int).map(|()| self)
+    }
+
+    /// Gets the value of the `SO_MARK` option on this socket.
+    pub fn get_mark(&self) -> io::Result<u32> {
+        let (level, name) = try!(mark_opt());
+        get_opt::<c_int>(self.as_sock(), level, name).map(|n| n as u32)
+    }
+
+    /// Sets the value of the `SO_PRIORITY` option on this socket.
+    ///
+    /// This is the same as [`UdpSocketExt::set_priority`][priority].
+    ///
+    /// [priority]: trait.UdpSocketExt.html#method.set_priority
+    pub fn priority(&self, priority: u32) -> io::Result<&Self> {
+        let (level, name) = try!(priority_opt());
+        set_opt(self.as_sock(), level, name, priority as c_int).map(|()| self)
+    }
+
+    /// Gets the value of the `SO_PRIORITY` option on this socket.
+    pub fn get_priority(&self) -> io::Result<u32> {
+        let (level, name) = try!(priority_opt());
+        get_opt::<c_int>(self.as_sock(), level, name).map(|n| n as u32)
+    }
+
+    /// Sets the DSCP bits of the `IP_TOS` option, or `IPV6_TCLASS` for IPv6
+    /// sockets, on this socket.
+    ///
+    /// This is the same as [`UdpSocketExt::set_dscp`][dscp].
+    ///
+    /// [dscp]: trait.UdpSocketExt.html#method.set_dscp
+    pub fn dscp(&self, dscp: Dscp) -> io::Result<&Self> {
+        let (level, name, payload) = try!(dscp_opt(self.as_sock(), dscp));
+        set_opt(self.as_sock(), level, name, payload).map(|()| self)
+    }
+
+    /// Gets the DSCP bits of the `IP_TOS` or `IPV6_TCLASS` option on this
+    /// socket.
+    pub fn get_dscp(&self) -> io::Result<Dscp> {
+        tos(self.as_sock()).map(qos::dscp)
+    }
+
+    /// Sets the ECN bits of the `IP_TOS` option, or `IPV6_TCLASS` for IPv6
+    /// sockets, on this socket.
+    ///
+    /// This is the same as [`UdpSocketExt::set_ecn`][ecn].
+    ///
+    /// [ecn]: trait.UdpSocketExt.html#method.set_ecn
+    pub fn ecn(&self, ecn: Ecn) -> io::Result<&Self> {
+        let (level, name, payload) = try!(ecn_opt(self.as_sock(), ecn));
+        set_opt(self.as_sock(), level, name, payload).map(|()| self)
+    }
+
+    /// Gets the ECN bits of the `IP_TOS` or `IPV6_TCLASS` option on this
+    /// socket.
+    pub fn get_ecn(&self) -> io::Result<Ecn> {
+        tos(self.as_sock()).map(qos::ecn)
+    }
+}
+
+impl SocketBuilder {
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This is the same as [`TcpStreamExt::set_ttl`][other].
+    ///
+    /// [other]: trait.TcpStreamExt.html#tymethod.set_ttl
+    pub fn ttl(&self, ttl: u32) -> io::Result<&Self> {
+        set_opt(try!(self.sock()), IPPROTO_IP, IP_TTL, ttl as c_int)
+            .map(|()| self)
+    }
+
+    /// Sets the value for the `IPV6_V6ONLY` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::only_v6`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.only_v6
+    pub fn only_v6(&self, only_v6: bool) -> io::Result<&Self> {
+        set_opt(try!(self.sock()), v(IPPROTO_IPV6), IPV6_V6ONLY, only_v6 as c_int)
+            .map(|()| self)
+    }
+
+    /// Set value for the `SO_REUSEADDR` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::reuse_address`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.reuse_address
+    pub fn reuse_address(&self, reuse: bool) -> io::Result<&Self> {
+        set_opt(try!(self.sock()), SOL_SOCKET, SO_REUSEADDR,
+               reuse as c_int).map(|()| self)
+    }
+
+    /// Check the `SO_REUSEADDR` option on this socket.
+    pub fn get_reuse_address(&self) -> io::Result<bool> {
+        get_opt(try!(self.sock()), SOL_SOCKET, SO_REUSEADDR).map(int2bool)
+    }
+
+    /// Get the value of the `SO_ERROR` option on this socket.
+    ///
+    /// This will retrieve the stored error in the underlying socket, clearing
+    /// the field in the process. This can be useful for checking errors between
+    /// calls.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        get_opt(try!(self.sock()), SOL_SOCKET, SO_ERROR).map(int2err)
+    }
+}
+
+impl<S> OwnedTcpBuilder<S> {
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::ttl`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.ttl
+    pub fn ttl(self, ttl: u32) -> io::Result<Self> {
+        self.set_opt(IPPROTO_IP, IP_TTL, ttl as c_int)
+    }
+
+    /// Sets the value for the `IPV6_V6ONLY` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::only_v6`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.only_v6
+    pub fn only_v6(self, only_v6: bool) -> io::Result<Self> {
+        self.set_opt(v(IPPROTO_IPV6), IPV6_V6ONLY, only_v6 as c_int)
+    }
+
+    /// Set value for the `SO_REUSEADDR` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::reuse_address`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.reuse_address
+    pub fn reuse_address(self, reuse: bool) -> io::Result<Self> {
+        self.set_opt(SOL_SOCKET, SO_REUSEADDR, reuse as c_int)
+    }
+
+    /// Check the `SO_REUSEADDR` option on this socket.
+    pub fn get_reuse_address(&self) -> io::Result<bool> {
+        get_opt(self.as_sock(), SOL_SOCKET, SO_REUSEADDR).map(int2bool)
+    }
+
+    /// Get the value of the `SO_ERROR` option on this socket.
+    ///
+    /// This will retrieve the stored error in the underlying socket, clearing
+    /// the field in the process. This can be useful for checking errors between
+    /// calls.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        get_opt(self.as_sock(), SOL_SOCKET, SO_ERROR).map(int2err)
+    }
+
+    /// Binds this socket to the network interface or VRF called `interface`,
+    /// or removes the binding if `None`.
+    ///
+    /// This is the same as [`TcpBuilder::bind_device`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.bind_device
+    pub fn bind_device(self, interface: Option<&str>) -> io::Result<Self> {
+        let (level, name, payload) = try!(device_opt(interface));
+        self.set_opt(level, name, payload)
+            .map_err(|e| device_err(e, device_name(interface)))
+    }
+
+    /// Binds this socket to the network interface with index `index`, or
+    /// removes the binding if `index` is 0.
+    ///
+    /// This is the same as [`TcpBuilder::bind_device_by_index`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.bind_device_by_index
+    pub fn bind_device_by_index(self, index: u32) -> io::Result<Self> {
+        let (level, name, payload) =
+            try!(device_index_opt(self.as_sock(), index));
+        self.set_opt(level, name, payload)
+            .map_err(|e| device_err(e, device_index_name(index)))
+    }
+
+    /// Sets the value of the `IP_TRANSPARENT` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::transparent`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.transparent
+    pub fn transparent(self, transparent: bool) -> io::Result<Self> {
+        let (level, name) = try!(transparent_opt(self.as_sock()));
+        self.set_opt(level, name, transparent as c_int)
+    }
+
+    /// Sets the value of the `IP_FREEBIND` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::freebind`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.freebind
+    pub fn freebind(self, freebind: bool) -> io::Result<Self> {
+        let (level, name) = try!(freebind_opt());
+        self.set_opt(level, name, freebind as c_int)
+    }
+
+    /// Enables `SO_KEEPALIVE` and sets the parameters of keepalive probes,
+    /// or disables keepalive probes if `None` is specified.
+    ///
+    /// This is the same as [`TcpBuilder::tcp_keepalive`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.tcp_keepalive
+    pub fn tcp_keepalive(mut self, keepalive: Option<TcpKeepalive>)
+                         -> io::Result<Self> {
+        try!(keepalive::set(keepalive, |level, name, val| {
+            self.record_opt(level, name, val)
+        }));
+        Ok(self)
+    }
+
+    /// Sets the value of the `TCP_USER_TIMEOUT` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::user_timeout`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.user_timeout
+    pub fn user_timeout(self, timeout: Option<Duration>) -> io::Result<Self> {
+        let (level, name) = try!(keepalive::user_timeout_opt());
+        self.set_opt(level, name, keepalive::dur2user_timeout(timeout))
+    }
+
+    /// Sets the congestion control algorithm used by this socket.
+    ///
+    /// This is the same as [`TcpBuilder::congestion_control`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.congestion_control
+    pub fn congestion_control(self, algorithm: &str) -> io::Result<Self> {
+        let (level, name, payload) = try!(congestion_opt(algorithm));
+        self.set_opt(level, name, payload)
+    }
+
+    /// Sets the value of the `TCP_MAXSEG` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::maxseg`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.maxseg
+    pub fn maxseg(self, mss: u32) -> io::Result<Self> {
+        let (level, name) = try!(maxseg_opt());
+        self.set_opt(level, name, mss as c_int)
+    }
+
+    /// Sets the value of the `SO_MARK` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::mark`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.mark
+    pub fn mark(self, mark: u32) -> io::Result<Self> {
+        let (level, name) = try!(mark_opt());
+        self.set_opt(level, name, mark as c_int)
+    }
+
+    /// Sets the value of the `SO_PRIORITY` option on this socket.
+    ///
+    /// This is the same as [`TcpBuilder::priority`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.priority
+    pub fn priority(self, priority: u32) -> io::Result<Self> {
+        let (level, name) = try!(priority_opt());
+        self.set_opt(level, name, priority as c_int)
+    }
+
+    /// Sets the DSCP value of packets sent from this socket.
+    ///
+    /// This is the same as [`TcpBuilder::dscp`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.dscp
+    pub fn dscp(self, dscp: Dscp) -> io::Result<Self> {
+        let (level, name, payload) = try!(dscp_opt(self.as_sock(), dscp));
+        self.set_opt(level, name, payload)
+    }
+
+    /// Sets the length of the queue of pending TCP Fast Open connections.
+    ///
+    /// This is the same as [`TcpBuilder::fastopen`][other].
+    ///
+    /// [other]: struct.TcpBuilder.html#method.fastopen
+    pub fn fastopen(self, queue_len: u32) -> io::Result<Self> {
+        let (level, name) = try!(fastopen_opt());
+        self.set_opt(level, name, queue_len as c_int)
+    }
+
+    /// Set value for the `SO_REUSEPORT` option on this socket.
+    ///
+    /// This is the same as [`UnixTcpBuilderExt::reuse_port`][other].
+    ///
+    /// [other]: unix/trait.UnixTcpBuilderExt.html#tymethod.reuse_port
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    pub fn reuse_port(self, reuse: bool) -> io::Result<Self> {
+        self.set_opt(SOL_SOCKET, SO_REUSEPORT, reuse as c_int)
+    }
+}
+
+impl OwnedUdpBuilder {
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This is the same as [`UdpBuilder::ttl`][other].
+    ///
+    /// [other]: struct.UdpBuilder.html#method.ttl
+    pub fn ttl(self, ttl: u32) -> io::Result<Self> {
+        set_opt(self.as_sock(), IPPROTO_IP, IP_TTL, ttl as c_int).map(|()| self)
+    }
+
+    /// Sets the value for the `IPV6_V6ONLY` option on this socket.
+    ///
+    /// This is the same as [`UdpBuilder::only_v6`][other].
+    ///
+    /// [other]: struct.UdpBuilder.html#method.only_v6
+    pub fn only_v6(self, only_v6: bool) -> io::Result<Self> {
+        set_opt(self.as_sock(), v(IPPROTO_IPV6), IPV6_V6ONLY, only_v6 as c_int)
+            .map(|()| self)
+    }
+
+    /// Set value for the `SO_REUSEADDR` option on this socket.
+    ///
+    /// This is the same as [`UdpBuilder::reuse_address`][other].
+    ///
+    /// [other]: struct.UdpBuilder.html#method.reuse_address
+    pub fn reuse_address(self, reuse: bool) -> io::Result<Self> {
+        set_opt(self.as_sock(), SOL_SOCKET, SO_REUSEADDR,
+               reuse as c_int).map(|()| self)
+    }
+
+    /// Check the `SO_REUSEADDR` option on this socket.
+    pub fn get_reuse_address(&self) -> io::Result<bool> {
+        get_opt(self.as_sock(), SOL_SOCKET, SO_REUSEADDR).map(int2bool)
+    }
+
+    /// Get the value of the `SO_ERROR` option on this socket.
+    ///
     /// This will retrieve the stored error in the underlying socket, clearing
     /// the field in the process. This can be useful for checking errors between
     /// calls.
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
         get_opt(self.as_sock(), SOL_SOCKET, SO_ERROR).map(int2err)
     }
+
+    /// Binds this socket to the network interface or VRF called `interface`,
+    /// or removes the binding if `None`.
+    ///
+    /// This is the same as [`UdpBuilder::bind_device`][other].
+    ///
+    /// [other]: struct.UdpBuilder.html#method.bind_device
+    pub fn bind_device(self, interface: Option<&str>) -> io::Result<Self> {
+        let (level, name, payload) = try!(device_opt(interface));
+        set_opt(self.as_sock(), level, name, payload)
+            .map_err(|e| device_err(e, device_name(interface)))
+            .map(|()| self)
+    }
+
+    /// Binds this socket to the network interface with index `index`, or
+    /// removes the binding if `index` is 0.
+    ///
+    /// This is the same as [`UdpBuilder::bind_device_by_index`][other].
+    ///
+    /// [other]: struct.UdpBuilder.html#method.bind_device_by_index
+    pub fn bind_device_by_index(self, index: u32) -> io::Result<Self> {
+        let (level, name, payload) =
+            try!(device_index_opt(self.as_sock(), index));
+        set_opt(self.as_sock(), level, name, payload)
+            .map_err(|e| device_err(e, device_index_name(index)))
+            .map(|()| self)
+    }
+
+    /// Sets the value of the `IP_TRANSPARENT` option on this socket.
+    ///
+    /// This is the same as [`UdpBuilder::transparent`][other].
+    ///
+    /// [other]: struct.UdpBuilder.html#method.transparent
+    pub fn transparent(self, transparent: bool) -> io::Result<Self> {
+        let (level, name) = try!(transparent_opt(self.as_sock()));
+        set_opt(self.as_sock(), level, name, transparent as c_int)
+            .map(|()| self)
+    }
+
+    /// Sets the value of the `IP_FREEBIND` option on this socket.
+    ///
+    /// This is the same as [`UdpBuilder::freebind`][other].
+    ///
+    /// [other]: struct.UdpBuilder.html#method.freebind
+    pub fn freebind(self, freebind: bool) -> io::Result<Self> {
+        let (level, name) = try!(freebind_opt());
+        set_opt(self.as_sock(), level, name, freebind as c_int).map(|()| self)
+    }
+
+    /// Sets the value of the `SO_MARK` option on this socket.
+    ///
+    /// This is the same as [`UdpBuilder::mark`][other].
+    ///
+    /// [other]: struct.UdpBuilder.html#method.mark
+    pub fn mark(self, mark: u32) -> io::Result<Self> {
+        let (level, name) = try!(mark_opt());
+        set_opt(self.as_sock(), level, name, mark as c_int).map(|()| self)
+    }
+
+    /// Sets the value of the `SO_PRIORITY` option on this socket.
+    ///
+    /// This is the same as [`UdpBuilder::priority`][other].
+    ///
+    /// [other]: struct.UdpBuilder.html#method.priority
+    pub fn priority(self, priority: u32) -> io::Result<Self> {
+        let (level, name) = try!(priority_opt());
+        set_opt(self.as_sock(), level, name, priority as c_int).map(|()| self)
+    }
+
+    /// Sets the DSCP value of packets sent from this socket.
+    ///
+    /// This is the same as [`UdpBuilder::dscp`][other].
+    ///
+    /// [other]: struct.UdpBuilder.html#method.dscp
+    pub fn dscp(self, dscp: Dscp) -> io::Result<Self> {
+        let (level, name, payload) = try!(dscp_opt(self.as_sock(), dscp));
+        set_opt(self.as_sock(), level, name, payload).map(|()| self)
+    }
+
+    /// Set value for the `SO_REUSEPORT` option on this socket.
+    ///
+    /// This is the same as [`UnixUdpBuilderExt::reuse_port`][other].
+    ///
+    /// [other]: unix/trait.UnixUdpBuilderExt.html#tymethod.reuse_port
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    pub fn reuse_port(self, reuse: bool) -> io::Result<Self> {
+        set_opt(self.as_sock(), SOL_SOCKET, SO_REUSEPORT, reuse as c_int)
+            .map(|()| self)
+    }
 }
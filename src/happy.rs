@@ -0,0 +1,236 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cmp;
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use {AsInner, IntoInner, TcpBuilder};
+use error::{self, ConnectError};
+use ext;
+use socket::Socket;
+use sys;
+
+/// A dual-stack TCP connector implementing "Happy Eyeballs" as described in
+/// [RFC 8305][rfc].
+///
+/// Every resolved address is tried on its own `AF_INET` or `AF_INET6` socket.
+/// Addresses are interleaved by family, starting with the family of the
+/// first resolved address, and a new attempt is started every
+/// `attempt_delay` (or as soon as the previous attempt fails) while earlier
+/// attempts are still in flight. The first connection to be established wins
+/// and all other attempts are abandoned.
+///
+/// [rfc]: https://tools.ietf.org/html/rfc8305
+///
+/// # Examples
+///
+/// ```no_run
+/// use net2::HappyEyeballs;
+///
+/// let stream = HappyEyeballs::new()
+///     .configure(|b| b.ttl(64).map(|_| ()))
+///     .connect("example.com:80")
+///     .unwrap();
+/// ```
+pub struct HappyEyeballs {
+    attempt_delay: Duration,
+    timeout: Option<Duration>,
+    configure: Option<Configure>,
+}
+
+type Configure = Box<dyn Fn(&TcpBuilder) -> io::Result<()> + Send + Sync>;
+
+impl HappyEyeballs {
+    /// Creates a new connector with the 250ms connection attempt delay
+    /// recommended by RFC 8305 and no overall timeout.
+    pub fn new() -> HappyEyeballs {
+        HappyEyeballs {
+            attempt_delay: Duration::from_millis(250),
+            timeout: None,
+            configure: None,
+        }
+    }
+
+    /// Sets how long to wait for an attempt before starting the next one.
+    pub fn attempt_delay(&mut self, delay: Duration) -> &mut HappyEyeballs {
+        self.attempt_delay = delay;
+        self
+    }
+
+    /// Sets an overall deadline for establishing a connection.
+    ///
+    /// If no attempt succeeds in time an error of kind `TimedOut` is
+    /// returned.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut HappyEyeballs {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Registers a function used to configure the builder of every attempt
+    /// before it connects.
+    ///
+    /// The function is called once per candidate address with a fresh
+    /// `TcpBuilder` of the matching family, so any options set through it
+    /// are applied identically to every socket. The function must be
+    /// `Send` and `Sync` so that the connector can be shared between
+    /// threads.
+    pub fn configure<F>(&mut self, f: F) -> &mut HappyEyeballs
+        where F: Fn(&TcpBuilder) -> io::Result<()> + Send + Sync + 'static
+    {
+        self.configure = Some(Box::new(f));
+        self
+    }
+
+    /// Connects to `addr`, returning the first stream to be established.
+    ///
+    /// If every attempt fails the returned error wraps a `ConnectError`
+    /// describing each attempt, its kind being that of the last failure.
+    /// Attempts still in flight when the overall timeout passes are recorded
+    /// as having timed out.
+    pub fn connect<T: ToSocketAddrs>(&self, addr: T) -> io::Result<TcpStream> {
+        let addrs = try!(addr.to_socket_addrs()).collect::<Vec<_>>();
+        let deadline = self.timeout.map(|t| Instant::now() + t);
+        let mut pending = interleave(addrs).into_iter().peekable();
+        let mut in_flight: Vec<(Socket, SocketAddr, Instant)> = Vec::new();
+        let mut next_attempt = Instant::now();
+        let mut err = ConnectError::new();
+
+        loop {
+            let now = Instant::now();
+            if let Some(deadline) = deadline {
+                if now >= deadline {
+                    for (_, addr, start) in in_flight {
+                        err.push(addr, io::Error::new(io::ErrorKind::TimedOut,
+                                                      "connection timed out"),
+                                 now - start);
+                    }
+                    if err.attempts().is_empty() {
+                        return Err(io::Error::new(io::ErrorKind::TimedOut,
+                                                  "connection timed out"))
+                    }
+                    return Err(err.into())
+                }
+            }
+
+            if pending.peek().is_some() &&
+               (in_flight.is_empty() || now >= next_attempt) {
+                let addr = pending.next().unwrap();
+                match self.start(&addr) {
+                    Ok(Attempt::Connected(sock)) => return finish(sock),
+                    Ok(Attempt::InProgress(sock)) => {
+                        in_flight.push((sock, addr, now));
+                        next_attempt = Instant::now() + self.attempt_delay;
+                    }
+                    Err(e) => err.push(addr, e, now.elapsed()),
+                }
+                continue
+            }
+
+            if in_flight.is_empty() {
+                return Err(err.into())
+            }
+
+            let mut wait = if pending.peek().is_some() {
+                Some(next_attempt - now)
+            } else {
+                None
+            };
+            if let Some(deadline) = deadline {
+                let left = deadline - now;
+                wait = Some(wait.map_or(left, |w| cmp::min(w, left)));
+            }
+            let ready = {
+                let socks = in_flight.iter().map(|s| s.0.as_inner())
+                    .collect::<Vec<_>>();
+                try!(sys::wait_writable(&socks, wait))
+            };
+            if let Some(i) = ready {
+                let (sock, addr, start) = in_flight.remove(i);
+                match sock.take_error() {
+                    Ok(None) => return finish(sock),
+                    Ok(Some(e)) | Err(e) => {
+                        err.push(addr, e, start.elapsed());
+                        // A failed attempt lets the next one start right away
+                        next_attempt = Instant::now();
+                    }
+                }
+            }
+        }
+    }
+
+    fn start(&self, addr: &SocketAddr) -> io::Result<Attempt> {
+        let builder = match *addr {
+            SocketAddr::V4(..) => try!(TcpBuilder::new_v4()),
+            SocketAddr::V6(..) => try!(TcpBuilder::new_v6()),
+        };
+        if let Some(ref configure) = self.configure {
+            try!(configure(&builder));
+        }
+        let sock = try!(builder.as_inner().borrow_mut().take().ok_or_else(|| {
+            error::consumed()
+        }));
+        try!(ext::set_nonblocking(sock.as_inner().raw(), true));
+        match sock.connect(addr) {
+            Ok(()) => Ok(Attempt::Connected(sock)),
+            Err(ref e) if sys::is_in_progress(e) => Ok(Attempt::InProgress(sock)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Default for HappyEyeballs {
+    fn default() -> HappyEyeballs {
+        HappyEyeballs::new()
+    }
+}
+
+impl fmt::Debug for HappyEyeballs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HappyEyeballs")
+            .field("attempt_delay", &self.attempt_delay)
+            .field("timeout", &self.timeout)
+            .field("configure", &self.configure.is_some())
+            .finish()
+    }
+}
+
+enum Attempt {
+    Connected(Socket),
+    InProgress(Socket),
+}
+
+fn finish(sock: Socket) -> io::Result<TcpStream> {
+    try!(ext::set_nonblocking(sock.as_inner().raw(), false));
+    Ok(sock.into_inner().into_tcp_stream())
+}
+
+/// Orders addresses so that the two families alternate, starting with the
+/// family of the first address.
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = addrs.first().map(|a| a.is_ipv6()).unwrap_or(false);
+    let len = addrs.len();
+    let (first, second): (Vec<_>, Vec<_>) = addrs.into_iter()
+        .partition(|a| a.is_ipv6() == first_v6);
+    let mut first = first.into_iter();
+    let mut second = second.into_iter();
+    let mut ret = Vec::with_capacity(len);
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => return ret,
+            (a, b) => {
+                ret.extend(a);
+                ret.extend(b);
+            }
+        }
+    }
+}
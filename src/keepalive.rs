@@ -0,0 +1,212 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::io;
+use std::time::Duration;
+
+use ext::{self, Socket};
+use sys::c::{self, c_int};
+
+/// The parameters of TCP keepalive probes.
+///
+/// Every field is optional: `None` leaves the corresponding system default
+/// in place when the parameters are set, and means that the parameter could
+/// not be read on this platform when they are returned by a getter.
+/// Durations are rounded up to whole seconds, with a minimum of one second.
+///
+/// These are set through [`TcpStreamExt::set_tcp_keepalive`][stream] and
+/// [`TcpBuilder::tcp_keepalive`][builder].
+///
+/// [stream]: trait.TcpStreamExt.html#method.set_tcp_keepalive
+/// [builder]: struct.TcpBuilder.html#method.tcp_keepalive
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::TcpStream;
+/// use std::time::Duration;
+/// use net2::{TcpKeepalive, TcpStreamExt};
+///
+/// let stream = TcpStream::connect("127.0.0.1:80").unwrap();
+/// stream.set_tcp_keepalive(Some(TcpKeepalive {
+///     idle: Some(Duration::from_secs(60)),
+///     interval: Some(Duration::from_secs(10)),
+///     retries: Some(3),
+/// })).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TcpKeepalive {
+    /// How long the connection has to be idle before the first probe is
+    /// sent, the `TCP_KEEPIDLE` option or its equivalent.
+    pub idle: Option<Duration>,
+    /// How long to wait between unanswered probes, the `TCP_KEEPINTVL`
+    /// option.
+    pub interval: Option<Duration>,
+    /// How many unanswered probes are sent before the connection is dropped,
+    /// the `TCP_KEEPCNT` option.
+    pub retries: Option<u32>,
+}
+
+impl TcpKeepalive {
+    /// Creates a new set of parameters leaving every system default in
+    /// place.
+    pub fn new() -> TcpKeepalive {
+        TcpKeepalive::default()
+    }
+}
+
+/// Enables `SO_KEEPALIVE` along with the given parameters, or disables it
+/// if `keepalive` is `None`, setting each option through `set`.
+pub(crate) fn set<F>(keepalive: Option<TcpKeepalive>, mut set: F)
+                     -> io::Result<()>
+    where F: FnMut(c_int, c_int, c_int) -> io::Result<()>
+{
+    try!(set(c::SOL_SOCKET, c::SO_KEEPALIVE, keepalive.is_some() as c_int));
+    let keepalive = match keepalive {
+        Some(keepalive) => keepalive,
+        None => return Ok(()),
+    };
+    if let Some(idle) = keepalive.idle {
+        try!(set(tcp(), try!(idle_opt()), dur2secs(idle)));
+    }
+    if let Some(interval) = keepalive.interval {
+        try!(set(tcp(), try!(interval_opt()), dur2secs(interval)));
+    }
+    if let Some(retries) = keepalive.retries {
+        try!(set(tcp(), try!(retries_opt()), retries as c_int));
+    }
+    Ok(())
+}
+
+/// Reads `SO_KEEPALIVE` and, if it is enabled, every parameter this
+/// platform supports.
+pub(crate) fn get(sock: Socket) -> io::Result<Option<TcpKeepalive>> {
+    let on = try!(ext::get_opt::<c_int>(sock, c::SOL_SOCKET, c::SO_KEEPALIVE));
+    if on == 0 {
+        return Ok(None)
+    }
+    let read = |name: io::Result<c_int>| -> io::Result<Option<c_int>> {
+        match name {
+            Ok(name) => ext::get_opt(sock, tcp(), name).map(Some),
+            Err(_) => Ok(None),
+        }
+    };
+    let secs = |n: c_int| Duration::from_secs(n as u64);
+    Ok(Some(TcpKeepalive {
+        idle: try!(read(idle_opt())).map(secs),
+        interval: try!(read(interval_opt())).map(secs),
+        retries: try!(read(retries_opt())).map(|n| n as u32),
+    }))
+}
+
+/// Returns the level and name of the `TCP_USER_TIMEOUT` option.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn user_timeout_opt() -> io::Result<(c_int, c_int)> {
+    Ok((c::IPPROTO_TCP, c::TCP_USER_TIMEOUT))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn user_timeout_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_USER_TIMEOUT is not supported by platform"))
+}
+
+/// Converts a `TCP_USER_TIMEOUT` value, 0 meaning the system default.
+pub(crate) fn dur2user_timeout(dur: Option<Duration>) -> c_int {
+    match dur {
+        Some(dur) => {
+            let ms = dur.as_secs().saturating_mul(1000) +
+                     dur.subsec_millis() as u64;
+            ::std::cmp::max(1, ::std::cmp::min(ms, c_int::MAX as u64))
+                as c_int
+        }
+        None => 0,
+    }
+}
+
+pub(crate) fn user_timeout2dur(ms: c_int) -> Option<Duration> {
+    if ms == 0 {
+        None
+    } else {
+        Some(Duration::from_millis(ms as u64))
+    }
+}
+
+fn dur2secs(dur: Duration) -> c_int {
+    let secs = dur.as_secs().saturating_add((dur.subsec_nanos() > 0) as u64);
+    let secs = ::std::cmp::min(secs, c_int::MAX as u64);
+    ::std::cmp::max(1, secs as c_int)
+}
+
+#[cfg(unix)]
+pub(crate) fn tcp() -> c_int {
+    c::IPPROTO_TCP
+}
+
+#[cfg(not(unix))]
+pub(crate) fn tcp() -> c_int {
+    c::IPPROTO_TCP as c_int
+}
+
+#[cfg(unix)]
+fn idle_opt() -> io::Result<c_int> {
+    Ok(ext::KEEPALIVE_OPTION)
+}
+
+#[cfg(windows)]
+fn idle_opt() -> io::Result<c_int> {
+    Ok(c::TCP_KEEPIDLE)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos",
+          target_os = "ios", target_os = "freebsd", target_os = "netbsd",
+          target_os = "dragonfly"))]
+pub(crate) fn interval_opt() -> io::Result<c_int> {
+    Ok(c::TCP_KEEPINTVL)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos",
+          target_os = "ios", target_os = "freebsd", target_os = "netbsd",
+          target_os = "dragonfly"))]
+pub(crate) fn retries_opt() -> io::Result<c_int> {
+    Ok(c::TCP_KEEPCNT)
+}
+
+#[cfg(windows)]
+pub(crate) fn interval_opt() -> io::Result<c_int> {
+    Ok(c::TCP_KEEPINTVL)
+}
+
+#[cfg(windows)]
+pub(crate) fn retries_opt() -> io::Result<c_int> {
+    Ok(c::TCP_KEEPCNT)
+}
+
+#[cfg(target_os = "wasi")]
+fn idle_opt() -> io::Result<c_int> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_KEEPIDLE is not supported by platform"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos",
+              target_os = "ios", target_os = "freebsd", target_os = "netbsd",
+              target_os = "dragonfly", windows)))]
+pub(crate) fn interval_opt() -> io::Result<c_int> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_KEEPINTVL is not supported by platform"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos",
+              target_os = "ios", target_os = "freebsd", target_os = "netbsd",
+              target_os = "dragonfly", windows)))]
+pub(crate) fn retries_opt() -> io::Result<c_int> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_KEEPCNT is not supported by platform"))
+}
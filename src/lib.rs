@@ -52,6 +52,8 @@
 
 #[macro_use] extern crate cfg_if;
 
+#[cfg(feature = "serde")] #[macro_use] extern crate serde;
+
 use std::io;
 use std::ops::Neg;
 use std::net::{ToSocketAddrs, SocketAddr};
@@ -60,9 +62,21 @@ use utils::{One, NetInt};
 
 mod tcp;
 mod udp;
+mod builder;
+mod cork;
+mod error;
+mod happy;
+mod keepalive;
+mod owned;
+mod options;
+mod pktinfo;
+mod qos;
+mod snapshot;
 mod socket;
+mod tcp_info;
 mod ext;
 mod utils;
+#[cfg(unix)] mod uds;
 
 #[cfg(unix)] #[path = "sys/unix/mod.rs"] mod sys;
 #[cfg(windows)] #[path = "sys/windows/mod.rs"] mod sys;
@@ -71,20 +85,31 @@ mod utils;
 
 pub use tcp::TcpBuilder;
 pub use udp::UdpBuilder;
+pub use builder::{SocketBuilder, SocketType};
+pub use happy::HappyEyeballs;
+pub use cork::CorkGuard;
+pub use keepalive::TcpKeepalive;
+pub use owned::{OwnedTcpBuilder, OwnedUdpBuilder, Created, Bound};
+pub use options::{SocketOptions, SocketOptionsTarget};
+pub use pktinfo::PacketInfo;
+pub use qos::{Dscp, Ecn};
+pub use tcp_info::{TcpInfo, TcpState};
+pub use snapshot::{SocketSnapshot, SnapshotEntry, OptionValue, OptionDiff};
+pub use error::{Error, Operation, ConnectError, ConnectAttempt};
 pub use ext::{TcpStreamExt, TcpListenerExt, UdpSocketExt};
+#[cfg(unix)]
+pub use uds::{UnixStreamBuilder, UnixDatagramBuilder, UnixSocketAddr};
 
 fn one_addr<T: ToSocketAddrs>(tsa: T) -> io::Result<SocketAddr> {
     let mut addrs = try!(tsa.to_socket_addrs());
     let addr = match addrs.next() {
         Some(addr) => addr,
-        None => return Err(io::Error::new(io::ErrorKind::Other,
-                                          "no socket addresses could be resolved"))
+        None => return Err(io::Error::other("no socket addresses could be resolved"))
     };
     if addrs.next().is_none() {
         Ok(addr)
     } else {
-        Err(io::Error::new(io::ErrorKind::Other,
-                           "more than one address resolved"))
+        Err(io::Error::other("more than one address resolved"))
     }
 }
 
@@ -0,0 +1,505 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::io;
+use std::mem;
+use std::net::{Ipv4Addr, TcpListener, TcpStream, UdpSocket};
+use std::slice;
+use std::time::Duration;
+
+use {AsInner, SocketBuilder, TcpBuilder, UdpBuilder};
+use {Dscp, OwnedTcpBuilder, OwnedUdpBuilder, TcpKeepalive};
+use error;
+use ext::{self, AsSock};
+use keepalive;
+use sys::c::{self, c_int};
+
+/// A declarative set of socket options.
+///
+/// Every field is optional: `None` leaves the corresponding option of the
+/// socket untouched. The options can be applied to any of the builders in
+/// this crate as well as to the standard library's `TcpStream`,
+/// `TcpListener` and `UdpSocket` through [`apply_to`][link].
+///
+/// Options that only make sense for some kinds of sockets, such as
+/// `nodelay` on a UDP socket, are still passed to the system and fail as
+/// they would when set individually. Options which are not supported on the
+/// current platform at all return an error of kind `Unsupported`.
+///
+/// With the `serde` feature enabled this type implements `Serialize` and
+/// `Deserialize`, so it can be loaded from a configuration file. Durations
+/// are written as a number of milliseconds, or `false` to disable the
+/// option.
+///
+/// [link]: #method.apply_to
+///
+/// # Examples
+///
+/// ```no_run
+/// use net2::{SocketOptions, TcpBuilder};
+///
+/// let opts = SocketOptions {
+///     reuse_address: Some(true),
+///     nodelay: Some(true),
+///     ..SocketOptions::default()
+/// };
+///
+/// let tcp = TcpBuilder::new_v4().unwrap();
+/// opts.apply_to(&tcp).unwrap();
+/// let listener = tcp.bind("0.0.0.0:8080").unwrap().listen(128).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct SocketOptions {
+    /// The `SO_REUSEADDR` option.
+    pub reuse_address: Option<bool>,
+    /// The `SO_REUSEPORT` option, only available on Unix.
+    pub reuse_port: Option<bool>,
+    /// The `IPV6_V6ONLY` option.
+    pub only_v6: Option<bool>,
+    /// The `IP_TTL` option.
+    pub ttl: Option<u32>,
+    /// The `IPV6_UNICAST_HOPS` option.
+    pub unicast_hops_v6: Option<u32>,
+    /// The `TCP_NODELAY` option.
+    pub nodelay: Option<bool>,
+    /// The `SO_RCVBUF` option.
+    pub recv_buffer_size: Option<usize>,
+    /// The `SO_SNDBUF` option.
+    pub send_buffer_size: Option<usize>,
+    /// The `SO_KEEPALIVE` option along with the keepalive idle time, see
+    /// the `idle` field of [`TcpKeepalive`][link].
+    ///
+    /// [link]: struct.TcpKeepalive.html
+    #[cfg_attr(feature = "serde", serde(with = "millis",
+                                        skip_serializing_if = "Option::is_none"))]
+    pub keepalive: Option<Option<Duration>>,
+    /// The `SO_RCVTIMEO` option.
+    #[cfg_attr(feature = "serde", serde(with = "millis",
+                                        skip_serializing_if = "Option::is_none"))]
+    pub read_timeout: Option<Option<Duration>>,
+    /// The `SO_SNDTIMEO` option.
+    #[cfg_attr(feature = "serde", serde(with = "millis",
+                                        skip_serializing_if = "Option::is_none"))]
+    pub write_timeout: Option<Option<Duration>>,
+    /// The `SO_LINGER` option.
+    #[cfg_attr(feature = "serde", serde(with = "millis",
+                                        skip_serializing_if = "Option::is_none"))]
+    pub linger: Option<Option<Duration>>,
+    /// The `SO_BROADCAST` option.
+    pub broadcast: Option<bool>,
+    /// The `IP_MULTICAST_LOOP` option.
+    pub multicast_loop_v4: Option<bool>,
+    /// The `IP_MULTICAST_TTL` option.
+    pub multicast_ttl_v4: Option<u32>,
+    /// The `IP_MULTICAST_IF` option.
+    pub multicast_if_v4: Option<Ipv4Addr>,
+    /// The `IPV6_MULTICAST_LOOP` option.
+    pub multicast_loop_v6: Option<bool>,
+    /// The `IPV6_MULTICAST_HOPS` option.
+    pub multicast_hops_v6: Option<u32>,
+    /// The `IPV6_MULTICAST_IF` option.
+    pub multicast_if_v6: Option<u32>,
+    /// The `SO_BINDTODEVICE` option, only available on Linux. An empty name
+    /// removes the binding.
+    pub bind_device: Option<String>,
+    /// The `IP_FREEBIND` option, only available on Linux.
+    pub freebind: Option<bool>,
+    /// The `IP_TRANSPARENT` option, or `IPV6_TRANSPARENT` for IPv6 sockets,
+    /// only available on Linux.
+    pub transparent: Option<bool>,
+    /// The interval between keepalive probes, see the `interval` field of
+    /// [`TcpKeepalive`][link]. Only applied when `keepalive` enables
+    /// keepalive probes.
+    ///
+    /// [link]: struct.TcpKeepalive.html
+    #[cfg_attr(feature = "serde", serde(with = "plain_millis",
+                                        skip_serializing_if = "Option::is_none"))]
+    pub keepalive_interval: Option<Duration>,
+    /// The number of unanswered keepalive probes before the connection is
+    /// dropped, see the `retries` field of [`TcpKeepalive`][link]. Only
+    /// applied when `keepalive` enables keepalive probes.
+    ///
+    /// [link]: struct.TcpKeepalive.html
+    pub keepalive_retries: Option<u32>,
+    /// The `TCP_USER_TIMEOUT` option, only available on Linux.
+    #[cfg_attr(feature = "serde", serde(with = "millis",
+                                        skip_serializing_if = "Option::is_none"))]
+    pub user_timeout: Option<Option<Duration>>,
+    /// The `TCP_CONGESTION` option, only available on Linux.
+    pub congestion_control: Option<String>,
+    /// The `TCP_CORK` option, only available on Linux.
+    pub cork: Option<bool>,
+    /// The `TCP_NOTSENT_LOWAT` option, only available on Linux.
+    pub notsent_lowat: Option<u32>,
+    /// The `TCP_MAXSEG` option, only available on Unix.
+    pub maxseg: Option<u32>,
+    /// The `TCP_FASTOPEN` option, only available on Linux.
+    pub fastopen: Option<u32>,
+    /// The `TCP_FASTOPEN_KEY` option, the primary key followed by an
+    /// optional backup key, only available on Linux.
+    pub fastopen_keys: Option<([u8; 16], Option<[u8; 16]>)>,
+    /// The `SO_MARK` option, only available on Linux.
+    pub mark: Option<u32>,
+    /// The `SO_PRIORITY` option, only available on Linux.
+    pub priority: Option<u32>,
+    /// The DSCP bits of the `IP_TOS` option, or `IPV6_TCLASS` for IPv6
+    /// sockets, only available on Linux.
+    pub dscp: Option<Dscp>,
+    /// Whether the socket is in nonblocking mode, applied last.
+    pub nonblocking: Option<bool>,
+}
+
+/// A socket that [`SocketOptions`][link] can be applied to.
+///
+/// This is implemented for the builders in this crate and for the standard
+/// library's `TcpStream`, `TcpListener` and `UdpSocket`.
+///
+/// [link]: struct.SocketOptions.html
+pub trait SocketOptionsTarget {
+    #[doc(hidden)]
+    fn apply_options(&self, opts: &SocketOptions) -> io::Result<()>;
+}
+
+impl SocketOptions {
+    /// Creates a set of options which leaves every option untouched.
+    pub fn new() -> SocketOptions {
+        SocketOptions::default()
+    }
+
+    /// Applies every option which is set to `target`.
+    ///
+    /// Options are applied one at a time, starting with the ones which
+    /// affect binding such as `reuse_address`, and the first error is
+    /// returned. Every option applied to a `TcpBuilder`, `nonblocking`
+    /// included, is recorded just like when it is set through the builder's
+    /// methods, so that it is replayed onto fresh sockets, see
+    /// [`TcpBuilder::connect`][link].
+    ///
+    /// [link]: struct.TcpBuilder.html#method.connect
+    pub fn apply_to<T>(&self, target: &T) -> io::Result<()>
+        where T: SocketOptionsTarget + ?Sized
+    {
+        target.apply_options(self)
+    }
+
+    fn apply_raw(&self, sock: ext::Socket) -> io::Result<()> {
+        try!(self.set_all(sock, |level, name, payload| {
+            ext::set_opt_bytes(sock, level, name, payload)
+        }));
+        if let Some(nonblocking) = self.nonblocking {
+            try!(ext::set_nonblocking(sock, nonblocking));
+        }
+        Ok(())
+    }
+
+    /// Calls `set` with the level, name and payload of every option which is
+    /// set other than `nonblocking`, in the order `apply_to` documents.
+    ///
+    /// `sock` is only read, for options which depend on the family of the
+    /// socket or its current value.
+    fn set_all<F>(&self, sock: ext::Socket, mut set: F) -> io::Result<()>
+        where F: FnMut(c_int, c_int, &[u8]) -> io::Result<()>
+    {
+        let ip = c::IPPROTO_IP;
+        let ipv6 = ext::v(c::IPPROTO_IPV6);
+        if let Some(reuse) = self.reuse_address {
+            try!(set(c::SOL_SOCKET, c::SO_REUSEADDR, bytes(&(reuse as c_int))));
+        }
+        if let Some(reuse) = self.reuse_port {
+            let (level, name) = try!(reuse_port_opt());
+            try!(set(level, name, bytes(&(reuse as c_int))));
+        }
+        if let Some(only_v6) = self.only_v6 {
+            try!(set(ipv6, c::IPV6_V6ONLY, bytes(&(only_v6 as c_int))));
+        }
+        if let Some(ref interface) = self.bind_device {
+            let interface = if interface.is_empty() {
+                None
+            } else {
+                Some(&interface[..])
+            };
+            let (level, name, payload) = try!(ext::device_opt(interface));
+            try!(set(level, name, bytes(&payload)).map_err(|e| {
+                ext::device_err(e, ext::device_name(interface))
+            }));
+        }
+        if let Some(freebind) = self.freebind {
+            let (level, name) = try!(ext::freebind_opt());
+            try!(set(level, name, bytes(&(freebind as c_int))));
+        }
+        if let Some(transparent) = self.transparent {
+            let (level, name) = try!(ext::transparent_opt(sock));
+            try!(set(level, name, bytes(&(transparent as c_int))));
+        }
+        if let Some(ttl) = self.ttl {
+            try!(set(ip, c::IP_TTL, bytes(&(ttl as c_int))));
+        }
+        if let Some(hops) = self.unicast_hops_v6 {
+            try!(set(ipv6, c::IPV6_UNICAST_HOPS, bytes(&(hops as c_int))));
+        }
+        if let Some(nodelay) = self.nodelay {
+            try!(set(ext::v(c::IPPROTO_TCP), c::TCP_NODELAY,
+                     bytes(&(nodelay as c_int))));
+        }
+        if let Some(size) = self.recv_buffer_size {
+            try!(set(c::SOL_SOCKET, c::SO_RCVBUF, bytes(&(size as c_int))));
+        }
+        if let Some(size) = self.send_buffer_size {
+            try!(set(c::SOL_SOCKET, c::SO_SNDBUF, bytes(&(size as c_int))));
+        }
+        if let Some(keepalive) = self.keepalive {
+            let keepalive = keepalive.map(|idle| {
+                TcpKeepalive {
+                    idle: Some(idle),
+                    interval: self.keepalive_interval,
+                    retries: self.keepalive_retries,
+                }
+            });
+            try!(keepalive::set(keepalive, |level, name, val| {
+                set(level, name, bytes(&val))
+            }));
+        }
+        if let Some(timeout) = self.read_timeout {
+            try!(set(c::SOL_SOCKET, c::SO_RCVTIMEO,
+                     bytes(&ext::ms2timeout(timeout.map(ext::dur2ms)))));
+        }
+        if let Some(timeout) = self.write_timeout {
+            try!(set(c::SOL_SOCKET, c::SO_SNDTIMEO,
+                     bytes(&ext::ms2timeout(timeout.map(ext::dur2ms)))));
+        }
+        if let Some(linger) = self.linger {
+            try!(set(c::SOL_SOCKET, c::SO_LINGER,
+                     bytes(&ext::dur2linger(linger))));
+        }
+        if let Some(broadcast) = self.broadcast {
+            try!(set(c::SOL_SOCKET, c::SO_BROADCAST,
+                     bytes(&(broadcast as c_int))));
+        }
+        if let Some(on) = self.multicast_loop_v4 {
+            try!(set(ip, c::IP_MULTICAST_LOOP, bytes(&(on as c_int))));
+        }
+        if let Some(ttl) = self.multicast_ttl_v4 {
+            try!(set(ip, c::IP_MULTICAST_TTL, bytes(&(ttl as c_int))));
+        }
+        if let Some(ref interface) = self.multicast_if_v4 {
+            try!(set(ip, c::IP_MULTICAST_IF,
+                     bytes(&ext::ip2in_addr(interface))));
+        }
+        if let Some(on) = self.multicast_loop_v6 {
+            try!(set(ipv6, c::IPV6_MULTICAST_LOOP, bytes(&(on as c_int))));
+        }
+        if let Some(hops) = self.multicast_hops_v6 {
+            try!(set(ipv6, c::IPV6_MULTICAST_HOPS, bytes(&(hops as c_int))));
+        }
+        if let Some(interface) = self.multicast_if_v6 {
+            try!(set(ipv6, c::IPV6_MULTICAST_IF,
+                     bytes(&ext::to_ipv6mr_interface(interface))));
+        }
+        if let Some(timeout) = self.user_timeout {
+            let (level, name) = try!(keepalive::user_timeout_opt());
+            try!(set(level, name,
+                     bytes(&keepalive::dur2user_timeout(timeout))));
+        }
+        if let Some(ref algorithm) = self.congestion_control {
+            let (level, name, payload) = try!(ext::congestion_opt(algorithm));
+            try!(set(level, name, bytes(&payload)));
+        }
+        if let Some(cork) = self.cork {
+            let (level, name) = try!(ext::cork_opt());
+            try!(set(level, name, bytes(&(cork as c_int))));
+        }
+        if let Some(lowat) = self.notsent_lowat {
+            let (level, name) = try!(ext::notsent_lowat_opt());
+            try!(set(level, name, bytes(&(lowat as c_int))));
+        }
+        if let Some(mss) = self.maxseg {
+            let (level, name) = try!(ext::maxseg_opt());
+            try!(set(level, name, bytes(&(mss as c_int))));
+        }
+        if let Some(queue_len) = self.fastopen {
+            let (level, name) = try!(ext::fastopen_opt());
+            try!(set(level, name, bytes(&(queue_len as c_int))));
+        }
+        if let Some((primary, backup)) = self.fastopen_keys {
+            let (level, name, keys) =
+                try!(ext::fastopen_keys_opt(primary, backup));
+            try!(set(level, name, &keys));
+        }
+        if let Some(mark) = self.mark {
+            let (level, name) = try!(ext::mark_opt());
+            try!(set(level, name, bytes(&(mark as c_int))));
+        }
+        if let Some(priority) = self.priority {
+            let (level, name) = try!(ext::priority_opt());
+            try!(set(level, name, bytes(&(priority as c_int))));
+        }
+        if let Some(dscp) = self.dscp {
+            let (level, name, payload) = try!(ext::dscp_opt(sock, dscp));
+            try!(set(level, name, bytes(&payload)));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the bytes of an option payload.
+fn bytes<T: Copy>(payload: &T) -> &[u8] {
+    unsafe {
+        slice::from_raw_parts(payload as *const T as *const u8,
+                              mem::size_of::<T>())
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+fn reuse_port_opt() -> io::Result<(c_int, c_int)> {
+    Ok((c::SOL_SOCKET, c::SO_REUSEPORT))
+}
+
+#[cfg(not(all(unix, not(any(target_os = "solaris", target_os = "illumos")))))]
+fn reuse_port_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "SO_REUSEPORT is not supported by platform"))
+}
+
+impl SocketOptionsTarget for TcpBuilder {
+    fn apply_options(&self, opts: &SocketOptions) -> io::Result<()> {
+        let sock = match *self.as_inner().borrow() {
+            Some(ref s) => s.as_inner().raw(),
+            None => return Err(error::finished()),
+        };
+        // Go through the builder so that every option is recorded
+        try!(opts.set_all(sock, |level, name, payload| {
+            self.set_opt_bytes(level, name, payload)
+        }));
+        if let Some(nonblocking) = opts.nonblocking {
+            try!(self.set_nonblocking(nonblocking));
+        }
+        Ok(())
+    }
+}
+
+impl SocketOptionsTarget for UdpBuilder {
+    fn apply_options(&self, opts: &SocketOptions) -> io::Result<()> {
+        match *self.as_inner().borrow() {
+            Some(ref s) => opts.apply_raw(s.as_inner().raw()),
+            None => Err(error::finished()),
+        }
+    }
+}
+
+impl SocketOptionsTarget for SocketBuilder {
+    fn apply_options(&self, opts: &SocketOptions) -> io::Result<()> {
+        match *self.as_inner().borrow() {
+            Some(ref s) => opts.apply_raw(s.as_inner().raw()),
+            None => Err(error::finished()),
+        }
+    }
+}
+
+impl SocketOptionsTarget for OwnedUdpBuilder {
+    fn apply_options(&self, opts: &SocketOptions) -> io::Result<()> {
+        opts.apply_raw(self.as_sock())
+    }
+}
+
+impl SocketOptionsTarget for TcpStream {
+    fn apply_options(&self, opts: &SocketOptions) -> io::Result<()> {
+        opts.apply_raw(self.as_sock())
+    }
+}
+
+impl SocketOptionsTarget for TcpListener {
+    fn apply_options(&self, opts: &SocketOptions) -> io::Result<()> {
+        opts.apply_raw(self.as_sock())
+    }
+}
+
+impl SocketOptionsTarget for UdpSocket {
+    fn apply_options(&self, opts: &SocketOptions) -> io::Result<()> {
+        opts.apply_raw(self.as_sock())
+    }
+}
+
+impl<S> OwnedTcpBuilder<S> {
+    /// Applies `opts` to this builder, recording the options a `TcpBuilder`
+    /// records.
+    ///
+    /// This is the by-value counterpart of
+    /// [`SocketOptions::apply_to`][link].
+    ///
+    /// [link]: struct.SocketOptions.html#method.apply_to
+    pub fn options(self, opts: &SocketOptions) -> io::Result<Self> {
+        let builder = TcpBuilder::from(self);
+        try!(opts.apply_to(&builder));
+        builder.into_parts().map(OwnedTcpBuilder::from_parts)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod plain_millis {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(val: &Option<Duration>, ser: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        val.map(|dur| dur.as_secs() * 1000 + dur.subsec_millis() as u64)
+           .serialize(ser)
+    }
+
+    pub fn deserialize<'de, D>(de: D) -> Result<Option<Duration>, D::Error>
+        where D: Deserializer<'de>
+    {
+        Option::<u64>::deserialize(de).map(|ms| ms.map(Duration::from_millis))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod millis {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde::de::Error;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Setting {
+        Millis(u64),
+        Enabled(bool),
+    }
+
+    pub fn serialize<S>(val: &Option<Option<Duration>>,
+                        ser: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        match *val {
+            Some(Some(dur)) => {
+                let ms = dur.as_secs() * 1000 + dur.subsec_millis() as u64;
+                Setting::Millis(ms).serialize(ser)
+            }
+            Some(None) => Setting::Enabled(false).serialize(ser),
+            None => ser.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(de: D) -> Result<Option<Option<Duration>>, D::Error>
+        where D: Deserializer<'de>
+    {
+        match try!(Setting::deserialize(de)) {
+            Setting::Millis(ms) => Ok(Some(Some(Duration::from_millis(ms)))),
+            Setting::Enabled(false) => Ok(Some(None)),
+            Setting::Enabled(true) => {
+                Err(D::Error::custom("expected a duration in milliseconds or `false`"))
+            }
+        }
+    }
+}
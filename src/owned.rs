@@ -0,0 +1,360 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::net::{SocketAddr, ToSocketAddrs, TcpListener, TcpStream, UdpSocket};
+use std::time::Duration;
+
+use {IntoInner, TcpBuilder, UdpBuilder};
+use socket::Socket;
+use sys::c::{self, c_int};
+use tcp::{self, Parts, SockOpt};
+
+/// The state of an [`OwnedTcpBuilder`][link] whose socket has not been bound.
+///
+/// [link]: struct.OwnedTcpBuilder.html
+#[derive(Debug)]
+pub enum Created {}
+
+/// The state of an [`OwnedTcpBuilder`][link] whose socket has been bound to a
+/// local address.
+///
+/// [link]: struct.OwnedTcpBuilder.html
+#[derive(Debug)]
+pub enum Bound {}
+
+/// An "in progress" TCP socket owned by value.
+///
+/// This is an alternative to [`TcpBuilder`][link] which is `Send` and `Sync`
+/// and encodes the state of the socket in its type. Every method that
+/// changes the state takes the builder by value: `bind` turns a
+/// `OwnedTcpBuilder<Created>` into a `OwnedTcpBuilder<Bound>`, while `listen`
+/// and `connect` finish the builder as a `TcpListener` or `TcpStream`. A
+/// builder can thus never be used after its socket was taken out of it.
+///
+/// Options are recorded and replayed onto fresh sockets by `connect` just as
+/// with `TcpBuilder`, and the two types convert into each other.
+///
+/// [link]: struct.TcpBuilder.html
+///
+/// # Examples
+///
+/// ```no_run
+/// use net2::OwnedTcpBuilder;
+///
+/// let listener = OwnedTcpBuilder::new_v4().unwrap()
+///     .reuse_address(true).unwrap()
+///     .bind("127.0.0.1:0").unwrap()
+///     .listen(128).unwrap();
+/// ```
+pub struct OwnedTcpBuilder<S = Created> {
+    socket: Socket,
+    options: Vec<SockOpt>,
+    local_addr: Option<SocketAddr>,
+    nonblocking: bool,
+    state: PhantomData<S>,
+}
+
+impl OwnedTcpBuilder<Created> {
+    /// Constructs a new builder with the `AF_INET` domain, the `SOCK_STREAM`
+    /// type, and with a protocol argument of 0.
+    pub fn new_v4() -> io::Result<OwnedTcpBuilder<Created>> {
+        Socket::new(c::AF_INET, c::SOCK_STREAM).map(OwnedTcpBuilder::from_socket)
+    }
+
+    /// Constructs a new builder with the `AF_INET6` domain, the `SOCK_STREAM`
+    /// type, and with a protocol argument of 0.
+    pub fn new_v6() -> io::Result<OwnedTcpBuilder<Created>> {
+        Socket::new(c::AF_INET6, c::SOCK_STREAM).map(OwnedTcpBuilder::from_socket)
+    }
+
+    /// Constructs a new builder like `new_v4`, except that the socket is in
+    /// nonblocking mode from the start.
+    ///
+    /// This is the same as [`TcpBuilder::new_v4_nonblocking`][link].
+    ///
+    /// [link]: struct.TcpBuilder.html#method.new_v4_nonblocking
+    pub fn new_v4_nonblocking() -> io::Result<OwnedTcpBuilder<Created>> {
+        OwnedTcpBuilder::nonblocking(c::AF_INET)
+    }
+
+    /// Constructs a new builder like `new_v6`, except that the socket is in
+    /// nonblocking mode from the start.
+    pub fn new_v6_nonblocking() -> io::Result<OwnedTcpBuilder<Created>> {
+        OwnedTcpBuilder::nonblocking(c::AF_INET6)
+    }
+
+    fn nonblocking(family: c_int) -> io::Result<OwnedTcpBuilder<Created>> {
+        let sock = try!(Socket::new_nonblocking(family, c::SOCK_STREAM, 0));
+        let mut owned = OwnedTcpBuilder::from_socket(sock);
+        owned.nonblocking = true;
+        Ok(owned)
+    }
+
+    /// Binds this socket to the specified address.
+    ///
+    /// This function directly corresponds to the bind(2) function on Windows
+    /// and Unix.
+    pub fn bind<T>(self, addr: T) -> io::Result<OwnedTcpBuilder<Bound>>
+        where T: ToSocketAddrs
+    {
+        let addr = try!(::one_addr(addr));
+        try!(self.socket.bind(&addr));
+        Ok(OwnedTcpBuilder {
+            socket: self.socket,
+            options: self.options,
+            local_addr: Some(addr),
+            nonblocking: self.nonblocking,
+            state: PhantomData,
+        })
+    }
+
+    pub(crate) fn from_socket(sock: Socket) -> OwnedTcpBuilder<Created> {
+        OwnedTcpBuilder {
+            socket: sock,
+            options: Vec::new(),
+            local_addr: None,
+            nonblocking: false,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<S> OwnedTcpBuilder<S> {
+    /// Mark a socket as ready to accept incoming connection requests using
+    /// accept(), returning it as a `TcpListener`.
+    ///
+    /// This function directly corresponds to the listen(2) function on Windows
+    /// and Unix.
+    pub fn listen(self, backlog: i32) -> io::Result<TcpListener> {
+        try!(self.socket.listen(backlog));
+        Ok(self.socket.into_inner().into_tcp_listener())
+    }
+
+    /// Initiate a connection on this socket to the specified address.
+    ///
+    /// This behaves like [`TcpBuilder::connect`][link], including retrying
+    /// every resolved address on a fresh socket.
+    ///
+    /// [link]: struct.TcpBuilder.html#method.connect
+    pub fn connect<T>(self, addr: T) -> io::Result<TcpStream>
+        where T: ToSocketAddrs
+    {
+        TcpBuilder::from(self).connect(addr)
+    }
+
+    /// Initiate a connection on this socket to the specified address, only
+    /// waiting up to `timeout` for it to be established.
+    ///
+    /// This behaves like [`TcpBuilder::connect_timeout`][link].
+    ///
+    /// [link]: struct.TcpBuilder.html#method.connect_timeout
+    pub fn connect_timeout<T>(self, addr: T, timeout: Duration)
+                              -> io::Result<TcpStream>
+        where T: ToSocketAddrs
+    {
+        TcpBuilder::from(self).connect_timeout(addr, timeout)
+    }
+
+    /// Returns the address of the local half of this TCP socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.getsockname()
+    }
+
+    /// Returns the address of the remote half of this TCP socket.
+    ///
+    /// This is mostly useful for connected sockets adopted through
+    /// `FromRaw{Fd,Socket}`.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.getpeername()
+    }
+
+    /// Sets a socket option and records it so that `connect` can replay it.
+    pub(crate) fn set_opt<T: Copy>(mut self, level: c_int, name: c_int,
+                                   payload: T) -> io::Result<Self> {
+        try!(self.record_opt(level, name, payload));
+        Ok(self)
+    }
+
+    /// Like `set_opt`, for callers that set several options in a row.
+    pub(crate) fn record_opt<T: Copy>(&mut self, level: c_int, name: c_int,
+                                      payload: T) -> io::Result<()> {
+        tcp::record_opt(&self.socket, &mut self.options, level, name, payload)
+    }
+
+    pub(crate) fn socket(&self) -> &Socket {
+        &self.socket
+    }
+
+    pub(crate) fn from_parts(parts: Parts) -> OwnedTcpBuilder<S> {
+        let (sock, options, local_addr, nonblocking) = parts;
+        OwnedTcpBuilder {
+            socket: sock,
+            options,
+            local_addr,
+            nonblocking,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<S> From<OwnedTcpBuilder<S>> for TcpBuilder {
+    fn from(b: OwnedTcpBuilder<S>) -> TcpBuilder {
+        TcpBuilder::from_parts(b.socket, b.options, b.local_addr, b.nonblocking)
+    }
+}
+
+impl<S> fmt::Debug for OwnedTcpBuilder<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OwnedTcpBuilder")
+            .field("socket", &self.socket)
+            .field("local_addr", &self.local_addr)
+            .finish()
+    }
+}
+
+impl TcpBuilder {
+    /// Converts this builder into an `OwnedTcpBuilder` in the `Created` state.
+    ///
+    /// An error is returned if the socket has already been consumed, or if
+    /// `bind` has been called, in which case `into_owned_bound` should be
+    /// used instead.
+    pub fn into_owned(self) -> io::Result<OwnedTcpBuilder<Created>> {
+        let (sock, options, local_addr, nonblocking) = try!(self.into_parts());
+        if local_addr.is_some() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "builder has already been bound"))
+        }
+        let mut owned = OwnedTcpBuilder::from_socket(sock);
+        owned.options = options;
+        owned.nonblocking = nonblocking;
+        Ok(owned)
+    }
+
+    /// Converts this builder into an `OwnedTcpBuilder` in the `Bound` state.
+    ///
+    /// An error is returned if the socket has already been consumed, or if
+    /// `bind` has not been called on this builder.
+    pub fn into_owned_bound(self) -> io::Result<OwnedTcpBuilder<Bound>> {
+        let (sock, options, local_addr, nonblocking) = try!(self.into_parts());
+        if local_addr.is_none() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "builder has not been bound"))
+        }
+        Ok(OwnedTcpBuilder {
+            socket: sock,
+            options,
+            local_addr,
+            nonblocking,
+            state: PhantomData,
+        })
+    }
+}
+
+/// An "in progress" UDP socket owned by value.
+///
+/// This is an alternative to [`UdpBuilder`][link] which is `Send` and `Sync`.
+/// Option setters take and return the builder by value, and `bind` and
+/// `connect` finish it as a `UdpSocket`, so a builder can never be used
+/// after its socket was taken out of it.
+///
+/// [link]: struct.UdpBuilder.html
+pub struct OwnedUdpBuilder {
+    socket: Socket,
+}
+
+impl OwnedUdpBuilder {
+    /// Constructs a new builder with the `AF_INET` domain, the `SOCK_DGRAM`
+    /// type, and with a protocol argument of 0.
+    pub fn new_v4() -> io::Result<OwnedUdpBuilder> {
+        Socket::new(c::AF_INET, c::SOCK_DGRAM).map(OwnedUdpBuilder::from_socket)
+    }
+
+    /// Constructs a new builder with the `AF_INET6` domain, the `SOCK_DGRAM`
+    /// type, and with a protocol argument of 0.
+    pub fn new_v6() -> io::Result<OwnedUdpBuilder> {
+        Socket::new(c::AF_INET6, c::SOCK_DGRAM).map(OwnedUdpBuilder::from_socket)
+    }
+
+    /// Binds this socket to the specified address, returning a `UdpSocket`.
+    ///
+    /// This function directly corresponds to the bind(2) function on Windows
+    /// and Unix.
+    pub fn bind<T>(self, addr: T) -> io::Result<UdpSocket>
+        where T: ToSocketAddrs
+    {
+        UdpBuilder::from(self).bind(addr)
+    }
+
+    /// Connects this socket to the specified remote address, returning a
+    /// connected `UdpSocket`.
+    ///
+    /// This behaves like [`UdpBuilder::connect`][link].
+    ///
+    /// [link]: struct.UdpBuilder.html#method.connect
+    pub fn connect<T>(self, addr: T) -> io::Result<UdpSocket>
+        where T: ToSocketAddrs
+    {
+        UdpBuilder::from(self).connect(addr)
+    }
+
+    /// Binds this socket to `local` and then connects it to `remote`,
+    /// returning a connected `UdpSocket`.
+    ///
+    /// This behaves like [`UdpBuilder::bind_and_connect`][link].
+    ///
+    /// [link]: struct.UdpBuilder.html#method.bind_and_connect
+    pub fn bind_and_connect<T, U>(self, local: T, remote: U) -> io::Result<UdpSocket>
+        where T: ToSocketAddrs, U: ToSocketAddrs
+    {
+        UdpBuilder::from(self).bind_and_connect(local, remote)
+    }
+
+    /// Returns the address of the remote half of this UDP socket.
+    ///
+    /// This is mostly useful for connected sockets adopted through
+    /// `FromRaw{Fd,Socket}`.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.getpeername()
+    }
+
+    pub(crate) fn from_socket(sock: Socket) -> OwnedUdpBuilder {
+        OwnedUdpBuilder { socket: sock }
+    }
+
+    pub(crate) fn socket(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl From<OwnedUdpBuilder> for UdpBuilder {
+    fn from(b: OwnedUdpBuilder) -> UdpBuilder {
+        ::FromInner::from_inner(b.socket)
+    }
+}
+
+impl fmt::Debug for OwnedUdpBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "OwnedUdpBuilder {{ socket: {:?} }}", self.socket)
+    }
+}
+
+impl UdpBuilder {
+    /// Converts this builder into an `OwnedUdpBuilder`.
+    ///
+    /// An error is returned if the socket has already been consumed.
+    pub fn into_owned(self) -> io::Result<OwnedUdpBuilder> {
+        ::AsInner::as_inner(&self).borrow_mut().take()
+            .map(OwnedUdpBuilder::from_socket)
+            .ok_or_else(::error::consumed)
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::net::IpAddr;
+
+/// The local address and interface of a datagram, carried in `IP_PKTINFO`
+/// or `IPV6_PKTINFO` control messages.
+///
+/// This is returned by [`UdpSocketExt::recv_msg`][recv] for the address a
+/// datagram was sent to, and passed to [`UdpSocketExt::send_msg`][send] to
+/// pick the source address and outgoing interface of a reply.
+///
+/// [recv]: trait.UdpSocketExt.html#method.recv_msg
+/// [send]: trait.UdpSocketExt.html#method.send_msg
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::UdpSocket;
+/// use net2::UdpSocketExt;
+///
+/// let socket = UdpSocket::bind("0.0.0.0:5353").unwrap();
+/// socket.set_recv_pktinfo(true).unwrap();
+///
+/// let mut buf = [0; 512];
+/// let (n, from, info, _) = socket.recv_msg(&mut buf).unwrap();
+/// // Reply from the address the query was sent to
+/// socket.send_msg(&buf[..n], &from, info.as_ref()).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketInfo {
+    /// The local address a received datagram should be answered from, or
+    /// the source address of a sent one. An unspecified address lets the
+    /// kernel pick the source address.
+    pub addr: IpAddr,
+    /// The index of the interface a datagram was received on, or is sent
+    /// on. 0 lets the kernel pick the interface.
+    pub interface: u32,
+}
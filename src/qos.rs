@@ -0,0 +1,179 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+
+/// A Differentiated Services Code Point, the upper six bits of the IPv4 TOS
+/// byte or IPv6 traffic class.
+///
+/// This is set through [`TcpStreamExt::set_dscp`][stream] and its
+/// counterparts, which pick `IP_TOS` or `IPV6_TCLASS` depending on the
+/// family of the socket.
+///
+/// [stream]: trait.TcpStreamExt.html#method.set_dscp
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::UdpSocket;
+/// use net2::{Dscp, UdpSocketExt};
+///
+/// let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+/// socket.set_dscp(Dscp::EF).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Dscp(u8);
+
+impl Dscp {
+    /// Class selector 0, best effort.
+    pub const CS0: Dscp = Dscp(0);
+    /// Class selector 1, low priority data.
+    pub const CS1: Dscp = Dscp(8);
+    /// Class selector 2, network operations.
+    pub const CS2: Dscp = Dscp(16);
+    /// Class selector 3, broadcast video.
+    pub const CS3: Dscp = Dscp(24);
+    /// Class selector 4, real-time interactive.
+    pub const CS4: Dscp = Dscp(32);
+    /// Class selector 5, signaling.
+    pub const CS5: Dscp = Dscp(40);
+    /// Class selector 6, network control.
+    pub const CS6: Dscp = Dscp(48);
+    /// Class selector 7.
+    pub const CS7: Dscp = Dscp(56);
+    /// Assured forwarding class 1, low drop probability.
+    pub const AF11: Dscp = Dscp(10);
+    /// Assured forwarding class 1, medium drop probability.
+    pub const AF12: Dscp = Dscp(12);
+    /// Assured forwarding class 1, high drop probability.
+    pub const AF13: Dscp = Dscp(14);
+    /// Assured forwarding class 2, low drop probability.
+    pub const AF21: Dscp = Dscp(18);
+    /// Assured forwarding class 2, medium drop probability.
+    pub const AF22: Dscp = Dscp(20);
+    /// Assured forwarding class 2, high drop probability.
+    pub const AF23: Dscp = Dscp(22);
+    /// Assured forwarding class 3, low drop probability.
+    pub const AF31: Dscp = Dscp(26);
+    /// Assured forwarding class 3, medium drop probability.
+    pub const AF32: Dscp = Dscp(28);
+    /// Assured forwarding class 3, high drop probability.
+    pub const AF33: Dscp = Dscp(30);
+    /// Assured forwarding class 4, low drop probability.
+    pub const AF41: Dscp = Dscp(34);
+    /// Assured forwarding class 4, medium drop probability.
+    pub const AF42: Dscp = Dscp(36);
+    /// Assured forwarding class 4, high drop probability.
+    pub const AF43: Dscp = Dscp(38);
+    /// Expedited forwarding, for low latency traffic such as voice.
+    pub const EF: Dscp = Dscp(46);
+
+    /// Creates a code point from its value, or returns `None` if it does not
+    /// fit in six bits.
+    pub fn new(value: u8) -> Option<Dscp> {
+        if value < 64 {
+            Some(Dscp(value))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value of this code point.
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Dscp {
+    fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+        where S: ::serde::Serializer
+    {
+        ser.serialize_u8(self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for Dscp {
+    fn deserialize<D>(de: D) -> Result<Dscp, D::Error>
+        where D: ::serde::Deserializer<'de>
+    {
+        use serde::de::Error;
+
+        let value = try!(u8::deserialize(de));
+        Dscp::new(value).ok_or_else(|| {
+            D::Error::custom(format!("invalid DSCP value {}, expected 0-63",
+                                     value))
+        })
+    }
+}
+
+impl fmt::Display for Dscp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// An Explicit Congestion Notification codepoint, the lower two bits of the
+/// IPv4 TOS byte or IPv6 traffic class.
+///
+/// This is set through [`UdpSocketExt::set_ecn`][link]. The kernel manages
+/// these bits itself on TCP sockets.
+///
+/// [link]: trait.UdpSocketExt.html#method.set_ecn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ecn {
+    /// Not ECN-capable transport.
+    NotEct,
+    /// ECN-capable transport, `ECT(1)`.
+    Ect1,
+    /// ECN-capable transport, `ECT(0)`.
+    Ect0,
+    /// Congestion experienced.
+    Ce,
+}
+
+impl Ecn {
+    fn bits(&self) -> u8 {
+        match *self {
+            Ecn::NotEct => 0,
+            Ecn::Ect1 => 1,
+            Ecn::Ect0 => 2,
+            Ecn::Ce => 3,
+        }
+    }
+
+    fn from_bits(bits: u8) -> Ecn {
+        match bits & 3 {
+            0 => Ecn::NotEct,
+            1 => Ecn::Ect1,
+            2 => Ecn::Ect0,
+            _ => Ecn::Ce,
+        }
+    }
+}
+
+/// Replaces the DSCP bits of a TOS byte, keeping its ECN bits.
+pub(crate) fn with_dscp(tos: u8, dscp: Dscp) -> u8 {
+    (dscp.0 << 2) | (tos & 3)
+}
+
+/// Replaces the ECN bits of a TOS byte, keeping its DSCP bits.
+pub(crate) fn with_ecn(tos: u8, ecn: Ecn) -> u8 {
+    (tos & !3) | ecn.bits()
+}
+
+pub(crate) fn dscp(tos: u8) -> Dscp {
+    Dscp(tos >> 2)
+}
+
+pub(crate) fn ecn(tos: u8) -> Ecn {
+    Ecn::from_bits(tos)
+}
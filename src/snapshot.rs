@@ -0,0 +1,324 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::io;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use error;
+use ext::{self, AsSock, Socket, TcpStreamExt, UdpSocketExt};
+use keepalive;
+use sys::c;
+
+/// The value of a socket option recorded in a [`SocketSnapshot`][link].
+///
+/// [link]: struct.SocketSnapshot.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionValue {
+    /// An option which is either on or off.
+    Bool(bool),
+    /// An integer option, such as a TTL or an interface index.
+    Int(u32),
+    /// A size in bytes, such as a buffer size.
+    Size(usize),
+    /// A duration, `None` meaning that the option is disabled.
+    Duration(Option<Duration>),
+    /// An IPv4 address, such as a multicast interface.
+    Addr(Ipv4Addr),
+    /// A name, such as an interface or a congestion control algorithm,
+    /// `None` meaning that the option is unset.
+    Name(Option<String>),
+    /// A range of local ports, `(0, 0)` meaning the system default.
+    PortRange(u16, u16),
+}
+
+/// A single option recorded in a [`SocketSnapshot`][link], along with its
+/// value or the error reading it.
+///
+/// [link]: struct.SocketSnapshot.html
+#[derive(Debug)]
+pub struct SnapshotEntry {
+    name: &'static str,
+    value: io::Result<OptionValue>,
+}
+
+/// The values of every socket option this crate knows about, read from a
+/// socket at one point in time.
+///
+/// Options are read one by one and an option which cannot be read, for
+/// example `IPV6_V6ONLY` on an IPv4 socket, is recorded along with the
+/// error. `SO_ERROR` is not read as reading it clears it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::TcpStream;
+/// use net2::SocketSnapshot;
+///
+/// let healthy = TcpStream::connect("127.0.0.1:80").unwrap();
+/// let broken = TcpStream::connect("127.0.0.1:80").unwrap();
+///
+/// let before = SocketSnapshot::capture(&healthy);
+/// println!("{}", before);
+/// for diff in before.diff(&SocketSnapshot::capture(&broken)) {
+///     println!("{}", diff);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SocketSnapshot {
+    entries: Vec<SnapshotEntry>,
+}
+
+/// An option whose value differs between two snapshots, as returned by
+/// [`SocketSnapshot::diff`][link].
+///
+/// [link]: struct.SocketSnapshot.html#method.diff
+#[derive(Debug)]
+pub struct OptionDiff<'a> {
+    name: &'static str,
+    before: Option<&'a io::Result<OptionValue>>,
+    after: Option<&'a io::Result<OptionValue>>,
+}
+
+impl SocketSnapshot {
+    /// Reads every option of `sock`.
+    pub fn capture<S: AsSock + ?Sized>(sock: &S) -> SocketSnapshot {
+        let sock = sock.as_sock();
+        let mut entries = Vec::new();
+        {
+            let mut add = |name, value| {
+                entries.push(SnapshotEntry { name, value })
+            };
+            add("SO_REUSEADDR",
+                ext::get_opt(sock, c::SOL_SOCKET, c::SO_REUSEADDR)
+                    .map(|v: c::c_int| OptionValue::Bool(v != 0)));
+            #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+            add("SO_REUSEPORT",
+                ext::get_opt(sock, c::SOL_SOCKET, c::SO_REUSEPORT)
+                    .map(|v: c::c_int| OptionValue::Bool(v != 0)));
+            add("SO_BINDTODEVICE", ext::device(sock).map(OptionValue::Name));
+            add("IP_FREEBIND",
+                read(sock, ext::freebind_opt()).map(|v| OptionValue::Bool(v != 0)));
+            add("IP_BIND_ADDRESS_NO_PORT",
+                read(sock, ext::bind_address_no_port_opt())
+                    .map(|v| OptionValue::Bool(v != 0)));
+            add("IP_LOCAL_PORT_RANGE",
+                read(sock, ext::local_port_range_opt()).map(|v| {
+                    let (lo, hi) = ext::int2range(v as u32);
+                    OptionValue::PortRange(lo, hi)
+                }));
+            add("TCP_KEEPINTVL",
+                read(sock, keepalive::interval_opt()
+                               .map(|name| (keepalive::tcp(), name)))
+                    .map(|v| OptionValue::Duration(Some(secs(v)))));
+            add("TCP_KEEPCNT",
+                read(sock, keepalive::retries_opt()
+                               .map(|name| (keepalive::tcp(), name)))
+                    .map(|v| OptionValue::Int(v as u32)));
+            let tos = ext::tos_opt(sock);
+            let tos_name = match tos {
+                Ok((level, _)) if level == ext::v(c::IPPROTO_IPV6) => {
+                    "IPV6_TCLASS"
+                }
+                _ => "IP_TOS",
+            };
+            add(tos_name, read(sock, tos).map(|v| OptionValue::Int(v as u32)));
+            ext::with_views(sock, |tcp, udp| {
+                add("SO_KEEPALIVE",
+                    TcpStreamExt::keepalive(tcp).map(OptionValue::Duration));
+                add("SO_RCVBUF",
+                    TcpStreamExt::recv_buffer_size(tcp).map(OptionValue::Size));
+                add("SO_SNDBUF",
+                    TcpStreamExt::send_buffer_size(tcp).map(OptionValue::Size));
+                add("SO_RCVTIMEO",
+                    TcpStreamExt::read_timeout(tcp).map(OptionValue::Duration));
+                add("SO_SNDTIMEO",
+                    TcpStreamExt::write_timeout(tcp).map(OptionValue::Duration));
+                add("SO_LINGER",
+                    TcpStreamExt::linger(tcp).map(OptionValue::Duration));
+                add("SO_BROADCAST",
+                    UdpSocketExt::broadcast(udp).map(OptionValue::Bool));
+                add("TCP_NODELAY",
+                    TcpStreamExt::nodelay(tcp).map(OptionValue::Bool));
+                add("TCP_USER_TIMEOUT",
+                    TcpStreamExt::user_timeout(tcp).map(OptionValue::Duration));
+                add("TCP_CONGESTION",
+                    TcpStreamExt::congestion_control(tcp)
+                        .map(|name| OptionValue::Name(Some(name))));
+                add("TCP_CORK", TcpStreamExt::cork(tcp).map(OptionValue::Bool));
+                add("TCP_NOTSENT_LOWAT",
+                    TcpStreamExt::notsent_lowat(tcp).map(OptionValue::Int));
+                add("TCP_MAXSEG",
+                    TcpStreamExt::maxseg(tcp).map(OptionValue::Int));
+                add("SO_MARK", TcpStreamExt::mark(tcp).map(OptionValue::Int));
+                add("SO_PRIORITY",
+                    TcpStreamExt::priority(tcp).map(OptionValue::Int));
+                add("IP_TRANSPARENT",
+                    UdpSocketExt::transparent(udp).map(OptionValue::Bool));
+                add("IP_TTL", TcpStreamExt::ttl(tcp).map(OptionValue::Int));
+                add("IP_MULTICAST_TTL",
+                    UdpSocketExt::multicast_ttl_v4(udp).map(OptionValue::Int));
+                add("IP_MULTICAST_LOOP",
+                    UdpSocketExt::multicast_loop_v4(udp).map(OptionValue::Bool));
+                add("IP_MULTICAST_IF",
+                    UdpSocketExt::multicast_if_v4(udp).map(OptionValue::Addr));
+                add("IPV6_V6ONLY",
+                    TcpStreamExt::only_v6(tcp).map(OptionValue::Bool));
+                add("IPV6_UNICAST_HOPS",
+                    UdpSocketExt::unicast_hops_v6(udp).map(OptionValue::Int));
+                add("IPV6_MULTICAST_HOPS",
+                    UdpSocketExt::multicast_hops_v6(udp).map(OptionValue::Int));
+                add("IPV6_MULTICAST_LOOP",
+                    UdpSocketExt::multicast_loop_v6(udp).map(OptionValue::Bool));
+                add("IPV6_MULTICAST_IF",
+                    UdpSocketExt::multicast_if_v6(udp).map(OptionValue::Int));
+            });
+        }
+        SocketSnapshot { entries }
+    }
+
+    /// Returns every recorded option in the order it was read.
+    pub fn entries(&self) -> &[SnapshotEntry] {
+        &self.entries
+    }
+
+    /// Returns the recorded value of the option called `name`, such as
+    /// `"TCP_NODELAY"`, if it was read.
+    pub fn get(&self, name: &str) -> Option<&io::Result<OptionValue>> {
+        self.entries.iter().find(|e| e.name == name).map(|e| &e.value)
+    }
+
+    /// Returns the options whose value, or error, differs between this
+    /// snapshot and `other`.
+    ///
+    /// Errors are considered equal if they have the same kind and OS error
+    /// code.
+    pub fn diff<'a>(&'a self, other: &'a SocketSnapshot) -> Vec<OptionDiff<'a>> {
+        let mut ret = Vec::new();
+        for entry in self.entries.iter() {
+            let theirs = other.get(entry.name);
+            if theirs.map(|v| !same(&entry.value, v)).unwrap_or(true) {
+                ret.push(OptionDiff {
+                    name: entry.name,
+                    before: Some(&entry.value),
+                    after: theirs,
+                });
+            }
+        }
+        for entry in other.entries.iter() {
+            if self.get(entry.name).is_none() {
+                ret.push(OptionDiff {
+                    name: entry.name,
+                    before: None,
+                    after: Some(&entry.value),
+                });
+            }
+        }
+        ret
+    }
+}
+
+impl SnapshotEntry {
+    /// Returns the name of the option, such as `"TCP_NODELAY"`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the value of the option, or the error reading it.
+    pub fn value(&self) -> &io::Result<OptionValue> {
+        &self.value
+    }
+}
+
+impl<'a> OptionDiff<'a> {
+    /// Returns the name of the option, such as `"TCP_NODELAY"`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the value in the first snapshot, or `None` if it was not
+    /// read.
+    pub fn before(&self) -> Option<&'a io::Result<OptionValue>> {
+        self.before
+    }
+
+    /// Returns the value in the second snapshot, or `None` if it was not
+    /// read.
+    pub fn after(&self) -> Option<&'a io::Result<OptionValue>> {
+        self.after
+    }
+}
+
+/// Reads an integer option whose level and name may not be supported.
+fn read(sock: Socket, opt: io::Result<(c::c_int, c::c_int)>)
+        -> io::Result<c::c_int> {
+    opt.and_then(|(level, name)| ext::get_opt(sock, level, name))
+}
+
+fn secs(n: c::c_int) -> Duration {
+    Duration::from_secs(n as u64)
+}
+
+fn same(a: &io::Result<OptionValue>, b: &io::Result<OptionValue>) -> bool {
+    match (a, b) {
+        (Ok(a), Ok(b)) => a == b,
+        (Err(a), Err(b)) => {
+            a.kind() == b.kind() &&
+                error::raw_os_error(a) == error::raw_os_error(b)
+        }
+        _ => false,
+    }
+}
+
+struct DisplayValue<'a>(Option<&'a io::Result<OptionValue>>);
+
+impl<'a> fmt::Display for DisplayValue<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(Ok(v)) => v.fmt(f),
+            Some(Err(e)) => write!(f, "error: {}", e),
+            None => write!(f, "(not read)"),
+        }
+    }
+}
+
+impl fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OptionValue::Bool(b) => b.fmt(f),
+            OptionValue::Int(n) => n.fmt(f),
+            OptionValue::Size(n) => n.fmt(f),
+            OptionValue::Duration(Some(ref d)) => write!(f, "{:?}", d),
+            OptionValue::Duration(None) => write!(f, "off"),
+            OptionValue::Addr(ref a) => a.fmt(f),
+            OptionValue::Name(Some(ref name)) => name.fmt(f),
+            OptionValue::Name(None) => write!(f, "(none)"),
+            OptionValue::PortRange(lo, hi) => write!(f, "{}-{}", lo, hi),
+        }
+    }
+}
+
+impl fmt::Display for SocketSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in self.entries.iter() {
+            try!(writeln!(f, "{}: {}", entry.name,
+                          DisplayValue(Some(&entry.value))));
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for OptionDiff<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.name, DisplayValue(self.before),
+               DisplayValue(self.after))
+    }
+}
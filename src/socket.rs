@@ -12,11 +12,14 @@ use std::fmt;
 use std::io;
 use std::mem;
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::time::Duration;
 #[cfg(any(unix, target_os = "wasi"))]
 use libc::c_int;
 #[cfg(windows)]
 use winapi::ctypes::c_int;
 
+use error::{self, Operation};
+use ext;
 use sys;
 use sys::c;
 
@@ -26,26 +29,137 @@ pub struct Socket {
 
 impl Socket {
     pub fn new(family: c_int, ty: c_int) -> io::Result<Socket> {
-        Ok(Socket { inner: try!(sys::Socket::new(family, ty)) })
+        Socket::with_protocol(family, ty, 0)
+    }
+
+    pub fn with_protocol(family: c_int, ty: c_int,
+                         protocol: c_int) -> io::Result<Socket> {
+        sys::Socket::new(family, ty, protocol)
+            .map(|inner| Socket { inner })
+            .map_err(|e| error::wrap(Operation::Socket, e))
+    }
+
+    pub fn new_nonblocking(family: c_int, ty: c_int,
+                           protocol: c_int) -> io::Result<Socket> {
+        sys::Socket::new_nonblocking(family, ty, protocol)
+            .map(|inner| Socket { inner })
+            .map_err(|e| error::wrap(Operation::Socket, e))
     }
 
     pub fn bind(&self, addr: &SocketAddr) -> io::Result<()> {
-        let (addr, len) = addr2raw(addr);
+        let (raw, len) = addr2raw(addr);
         unsafe {
-            ::cvt(c::bind(self.inner.raw(), addr.as_ptr(), len as c::socklen_t)).map(|_| ())
+            ::cvt(c::bind(self.inner.raw(), raw.as_ptr(), len as c::socklen_t))
+                .map(|_| ())
+                .map_err(|e| error::wrap(Operation::Bind(*addr), e))
         }
     }
 
     pub fn listen(&self, backlog: i32) -> io::Result<()> {
         unsafe {
-            ::cvt(c::listen(self.inner.raw(), backlog)).map(|_| ())
+            ::cvt(c::listen(self.inner.raw(), backlog))
+                .map(|_| ())
+                .map_err(|e| error::wrap(Operation::Listen, e))
         }
     }
 
     pub fn connect(&self, addr: &SocketAddr) -> io::Result<()> {
-        let (addr, len) = addr2raw(addr);
+        let (raw, len) = addr2raw(addr);
         unsafe {
-            ::cvt(c::connect(self.inner.raw(), addr.as_ptr(), len)).map(|_| ())
+            ::cvt(c::connect(self.inner.raw(), raw.as_ptr(), len))
+                .map(|_| ())
+                .map_err(|e| error::wrap(Operation::Connect(*addr), e))
+        }
+    }
+
+    /// Connects to `addr` with TCP Fast Open, sending as much of `buf` as
+    /// possible in the SYN and returning how much was sent.
+    ///
+    /// If the kernel does not allow Fast Open on the client side this
+    /// connects normally and then sends `buf`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn connect_fastopen(&self, addr: &SocketAddr,
+                            buf: &[u8]) -> io::Result<usize> {
+        let (raw, len) = addr2raw(addr);
+        let res = unsafe {
+            ::cvt(c::sendto(self.inner.raw(), buf.as_ptr() as *const _,
+                            buf.len(), c::MSG_FASTOPEN | c::MSG_NOSIGNAL,
+                            raw.as_ptr(), len))
+        };
+        let res = match res {
+            // Bit 0 of the `net.ipv4.tcp_fastopen` sysctl is unset
+            Err(ref e) if e.raw_os_error() == Some(c::EOPNOTSUPP) => {
+                try!(self.connect(addr));
+                unsafe {
+                    ::cvt(c::send(self.inner.raw(), buf.as_ptr() as *const _,
+                                  buf.len(), c::MSG_NOSIGNAL))
+                }
+            }
+            res => res,
+        };
+        res.map(|n| n as usize)
+            .map_err(|e| error::wrap(Operation::Connect(*addr), e))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn connect_fastopen(&self, _addr: &SocketAddr,
+                            _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::Unsupported,
+                           "MSG_FASTOPEN is not supported by platform"))
+    }
+
+    /// Connects to `addr`, failing with `TimedOut` if the connection is not
+    /// established within `timeout`.
+    ///
+    /// The socket is temporarily put into nonblocking mode and is always
+    /// returned to blocking mode afterwards. If the connection fails that
+    /// error is returned even if blocking mode could not be restored.
+    pub fn connect_timeout(&self, addr: &SocketAddr,
+                           timeout: Duration) -> io::Result<()> {
+        if timeout == Duration::new(0, 0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "cannot set a 0 duration timeout"))
+        }
+        try!(ext::set_nonblocking(self.inner.raw(), true));
+        let res = self.connect_nonblocking(addr, timeout);
+        // The error of the connection attempt matters more than a failure
+        // to restore blocking mode
+        let restored = ext::set_nonblocking(self.inner.raw(), false);
+        res.and(restored)
+    }
+
+    fn connect_nonblocking(&self, addr: &SocketAddr,
+                           timeout: Duration) -> io::Result<()> {
+        match self.connect(addr) {
+            Ok(()) => return Ok(()),
+            Err(ref e) if sys::is_in_progress(e) => {}
+            Err(e) => return Err(e),
+        }
+        if !try!(self.inner.wait_writable(timeout)) {
+            return Err(io::Error::new(io::ErrorKind::TimedOut,
+                                      "connection timed out"))
+        }
+        match try!(self.take_error()) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        ext::get_opt(self.inner.raw(), c::SOL_SOCKET, c::SO_ERROR)
+            .map(ext::int2err)
+    }
+
+    /// Returns the address family of this socket, as reported by
+    /// getsockname(2).
+    pub fn family(&self) -> io::Result<c_int> {
+        unsafe {
+            let mut storage: c::sockaddr_storage = mem::zeroed();
+            let mut len = mem::size_of_val(&storage) as c::socklen_t;
+            try!(::cvt(c::getsockname(self.inner.raw(),
+                                      &mut storage as *mut _ as *mut _,
+                                      &mut len)));
+            Ok(storage.ss_family as c_int)
         }
     }
 
@@ -59,6 +173,17 @@ impl Socket {
             raw2addr(&storage, len)
         }
     }
+
+    pub fn getpeername(&self) -> io::Result<SocketAddr> {
+        unsafe {
+            let mut storage: c::sockaddr_storage = mem::zeroed();
+            let mut len = mem::size_of_val(&storage) as c::socklen_t;
+            try!(::cvt(c::getpeername(self.inner.raw(),
+                                      &mut storage as *mut _ as *mut _,
+                                      &mut len)));
+            raw2addr(&storage, len)
+        }
+    }
 }
 
 impl fmt::Debug for Socket {
@@ -100,10 +225,10 @@ impl SocketAddrCRepr {
     }
 }
 
-fn addr2raw(addr: &SocketAddr) -> (SocketAddrCRepr, c::socklen_t) {
+pub(crate) fn addr2raw(addr: &SocketAddr) -> (SocketAddrCRepr, c::socklen_t) {
     match addr {
-        &SocketAddr::V4(ref v4) => addr2raw_v4(v4),
-        &SocketAddr::V6(ref v6) => addr2raw_v6(v6),
+        SocketAddr::V4(v4) => addr2raw_v4(v4),
+        SocketAddr::V6(v6) => addr2raw_v6(v6),
     }
 }
 
@@ -215,7 +340,7 @@ fn addr2raw_v6(addr: &SocketAddrV6) -> (SocketAddrCRepr, c::socklen_t) {
     (sockaddr, mem::size_of::<c::sockaddr_in6>() as c::socklen_t)
 }
 
-fn raw2addr(storage: &c::sockaddr_storage, len: c::socklen_t) -> io::Result<SocketAddr> {
+pub(crate) fn raw2addr(storage: &c::sockaddr_storage, len: c::socklen_t) -> io::Result<SocketAddr> {
     match storage.ss_family as c_int {
         c::AF_INET => {
             unsafe {
@@ -8,10 +8,12 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::os::unix::io::{FromRawFd, AsRawFd};
+use std::os::unix::io::{FromRawFd, AsRawFd, IntoRawFd};
 use libc::c_int;
 
-use {TcpBuilder, UdpBuilder, FromInner, AsInner};
+use {TcpBuilder, UdpBuilder, SocketBuilder, FromInner, AsInner, IntoInner};
+use {UnixStreamBuilder, UnixDatagramBuilder};
+use {OwnedTcpBuilder, OwnedUdpBuilder, Created};
 use socket::Socket;
 use sys;
 
@@ -42,3 +44,87 @@ impl AsRawFd for UdpBuilder {
         self.as_inner().borrow().as_ref().unwrap().as_inner().raw()
     }
 }
+
+impl FromRawFd for SocketBuilder {
+    unsafe fn from_raw_fd(fd: c_int) -> SocketBuilder {
+        let sock = sys::Socket::from_inner(fd);
+        SocketBuilder::from_inner(Socket::from_inner(sock))
+    }
+}
+
+impl AsRawFd for SocketBuilder {
+    fn as_raw_fd(&self) -> c_int {
+        // A builder whose socket was taken out has no socket to return
+        match *self.as_inner().borrow() {
+            Some(ref s) => s.as_inner().raw(),
+            None => -1,
+        }
+    }
+}
+
+impl IntoRawFd for SocketBuilder {
+    fn into_raw_fd(self) -> c_int {
+        self.into_inner().expect("socket has already been consumed")
+            .into_inner().into_fd()
+    }
+}
+
+impl FromRawFd for UnixStreamBuilder {
+    unsafe fn from_raw_fd(fd: c_int) -> UnixStreamBuilder {
+        let sock = sys::Socket::from_inner(fd);
+        UnixStreamBuilder::from_inner(Socket::from_inner(sock))
+    }
+}
+
+impl AsRawFd for UnixStreamBuilder {
+    fn as_raw_fd(&self) -> c_int {
+        // A builder whose socket was taken out has no socket to return
+        match *self.as_inner().borrow() {
+            Some(ref s) => s.as_inner().raw(),
+            None => -1,
+        }
+    }
+}
+
+impl FromRawFd for UnixDatagramBuilder {
+    unsafe fn from_raw_fd(fd: c_int) -> UnixDatagramBuilder {
+        let sock = sys::Socket::from_inner(fd);
+        UnixDatagramBuilder::from_inner(Socket::from_inner(sock))
+    }
+}
+
+impl AsRawFd for UnixDatagramBuilder {
+    fn as_raw_fd(&self) -> c_int {
+        // A builder whose socket was taken out has no socket to return
+        match *self.as_inner().borrow() {
+            Some(ref s) => s.as_inner().raw(),
+            None => -1,
+        }
+    }
+}
+
+impl FromRawFd for OwnedTcpBuilder<Created> {
+    unsafe fn from_raw_fd(fd: c_int) -> OwnedTcpBuilder<Created> {
+        let sock = sys::Socket::from_inner(fd);
+        OwnedTcpBuilder::from_socket(Socket::from_inner(sock))
+    }
+}
+
+impl<S> AsRawFd for OwnedTcpBuilder<S> {
+    fn as_raw_fd(&self) -> c_int {
+        self.socket().as_inner().raw()
+    }
+}
+
+impl FromRawFd for OwnedUdpBuilder {
+    unsafe fn from_raw_fd(fd: c_int) -> OwnedUdpBuilder {
+        let sock = sys::Socket::from_inner(fd);
+        OwnedUdpBuilder::from_socket(Socket::from_inner(sock))
+    }
+}
+
+impl AsRawFd for OwnedUdpBuilder {
+    fn as_raw_fd(&self) -> c_int {
+        self.socket().as_inner().raw()
+    }
+}
@@ -12,7 +12,9 @@
 use std::io;
 use std::mem;
 use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::time::{Duration, Instant};
 use std::os::unix::io::FromRawFd;
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
 use libc::{self, c_int};
 #[cfg(not(any(target_os = "emscripten", target_os = "haiku", target_os = "illumos", target_os = "solaris")))]
 use libc::{ioctl, FIOCLEX};
@@ -23,12 +25,32 @@ pub mod c {
     pub use libc::*;
 
     pub fn sockaddr_in_u32(sa: &sockaddr_in) -> u32 {
-        ::ntoh((*sa).sin_addr.s_addr)
+        ::ntoh(sa.sin_addr.s_addr)
     }
 
     pub fn in_addr_to_u32(addr: &in_addr) -> u32 {
         ::ntoh(addr.s_addr)
     }
+
+    // Options not exported by every version of libc we support
+    #[cfg(all(target_os = "linux", not(target_arch = "sparc64")))]
+    pub const SO_ATTACH_REUSEPORT_CBPF: c_int = 51;
+    #[cfg(all(target_os = "linux", not(target_arch = "sparc64")))]
+    pub const SO_DETACH_REUSEPORT_BPF: c_int = 68;
+    #[cfg(all(target_os = "linux", target_arch = "sparc64"))]
+    pub const SO_ATTACH_REUSEPORT_CBPF: c_int = 0x35;
+    #[cfg(all(target_os = "linux", target_arch = "sparc64"))]
+    pub const SO_DETACH_REUSEPORT_BPF: c_int = 0x47;
+    #[cfg(all(target_os = "linux", not(target_arch = "sparc64")))]
+    pub const SO_BINDTOIFINDEX: c_int = 62;
+    #[cfg(all(target_os = "linux", target_arch = "sparc64"))]
+    pub const SO_BINDTOIFINDEX: c_int = 0x41;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub const IP_LOCAL_PORT_RANGE: c_int = 51;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub const IP_UNICAST_IF: c_int = 50;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub const IPV6_UNICAST_IF: c_int = 76;
 }
 
 pub struct Socket {
@@ -37,39 +59,63 @@ pub struct Socket {
 
 impl Socket {
     #[cfg(not(any(target_os = "emscripten", target_os = "haiku", target_os = "illumos", target_os = "solaris")))]
-    pub fn new(family: c_int, ty: c_int) -> io::Result<Socket> {
+    pub fn new(family: c_int, ty: c_int, protocol: c_int) -> io::Result<Socket> {
         unsafe {
             // Linux >2.6.26 overloads the type argument to accept SOCK_CLOEXEC,
             // avoiding a race with another thread running fork/exec between
             // socket() and ioctl()
             #[cfg(any(target_os = "linux", target_os = "android"))]
-            match ::cvt(libc::socket(family, ty | libc::SOCK_CLOEXEC, 0)) {
-                Ok(fd) => return Ok(Socket { fd: fd }),
+            match ::cvt(libc::socket(family, ty | libc::SOCK_CLOEXEC, protocol)) {
+                Ok(fd) => return Ok(Socket { fd }),
                 // Older versions of Linux return EINVAL; fall back to ioctl
                 Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) => {}
                 Err(e) => return Err(e),
             }
 
-            let fd = try!(::cvt(libc::socket(family, ty, 0)));
+            let fd = try!(::cvt(libc::socket(family, ty, protocol)));
             ioctl(fd, FIOCLEX);
-            Ok(Socket { fd: fd })
+            Ok(Socket { fd })
         }
     }
 
     // ioctl(FIOCLEX) is not supported by Solaris/illumos or emscripten,
     // use fcntl(FD_CLOEXEC) instead
     #[cfg(any(target_os = "emscripten", target_os = "haiku", target_os = "illumos", target_os = "solaris"))]
-    pub fn new(family: c_int, ty: c_int) -> io::Result<Socket> {
+    pub fn new(family: c_int, ty: c_int, protocol: c_int) -> io::Result<Socket> {
         unsafe {
-            let fd = try!(::cvt(libc::socket(family, ty, 0)));
+            let fd = try!(::cvt(libc::socket(family, ty, protocol)));
             libc::fcntl(fd, libc::FD_CLOEXEC);
             Ok(Socket { fd: fd })
         }
     }
 
+    /// Creates a socket which is in nonblocking mode from the start.
+    pub fn new_nonblocking(family: c_int, ty: c_int,
+                           protocol: c_int) -> io::Result<Socket> {
+        // Like SOCK_CLOEXEC above, Linux accepts SOCK_NONBLOCK in the type
+        // argument, saving an ioctl() after the socket is created
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        unsafe {
+            let ty = ty | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK;
+            match ::cvt(libc::socket(family, ty, protocol)) {
+                Ok(fd) => return Ok(Socket { fd }),
+                Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let sock = try!(Socket::new(family, ty, protocol));
+        try!(::ext::set_nonblocking(sock.fd, true));
+        Ok(sock)
+    }
+
     pub fn raw(&self) -> c_int { self.fd }
 
-    fn into_fd(self) -> c_int {
+    pub fn wait_writable(&self, timeout: Duration) -> io::Result<bool> {
+        wait_writable(&[self], Some(timeout)).map(|r| r.is_some())
+    }
+
+    pub fn into_fd(self) -> c_int {
         let fd = self.fd;
         mem::forget(self);
         fd
@@ -86,12 +132,73 @@ impl Socket {
     pub fn into_udp_socket(self) -> UdpSocket {
         unsafe { UdpSocket::from_raw_fd(self.into_fd()) }
     }
+
+    pub fn into_unix_listener(self) -> UnixListener {
+        unsafe { UnixListener::from_raw_fd(self.into_fd()) }
+    }
+
+    pub fn into_unix_stream(self) -> UnixStream {
+        unsafe { UnixStream::from_raw_fd(self.into_fd()) }
+    }
+
+    pub fn into_unix_datagram(self) -> UnixDatagram {
+        unsafe { UnixDatagram::from_raw_fd(self.into_fd()) }
+    }
+}
+
+/// Waits for any of `socks` to become writable, returning its index, or
+/// `None` if `timeout` elapses first. No timeout waits indefinitely.
+pub fn wait_writable(socks: &[&Socket], timeout: Option<Duration>)
+                     -> io::Result<Option<usize>> {
+    let start = Instant::now();
+    let mut fds = socks.iter().map(|s| {
+        libc::pollfd { fd: s.fd, events: libc::POLLOUT, revents: 0 }
+    }).collect::<Vec<_>>();
+    loop {
+        let ms = match timeout {
+            Some(timeout) => {
+                let elapsed = start.elapsed();
+                if elapsed >= timeout {
+                    return Ok(None)
+                }
+                dur2poll(timeout - elapsed)
+            }
+            None => -1,
+        };
+        let n = unsafe {
+            libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, ms)
+        };
+        match ::cvt(n) {
+            Ok(0) => {}
+            Ok(_) => return Ok(fds.iter().position(|fd| fd.revents != 0)),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+pub fn is_in_progress(err: &io::Error) -> bool {
+    ::error::raw_os_error(err) == Some(libc::EINPROGRESS)
+}
+
+// Rounds up so that a timeout never elapses early and spins
+fn dur2poll(dur: Duration) -> c_int {
+    let mut ms = dur.as_secs().saturating_mul(1000)
+        .saturating_add(dur.subsec_millis() as u64);
+    if dur.subsec_nanos() > dur.subsec_millis() * 1_000_000 {
+        ms = ms.saturating_add(1);
+    }
+    if ms > c_int::MAX as u64 {
+        c_int::MAX
+    } else {
+        ms as c_int
+    }
 }
 
 impl ::FromInner for Socket {
     type Inner = c_int;
     fn from_inner(fd: c_int) -> Socket {
-        Socket { fd: fd }
+        Socket { fd }
     }
 }
 
@@ -1,6 +1,7 @@
-use std::os::wasi::io::{FromRawFd, AsRawFd};
+use std::os::wasi::io::{FromRawFd, AsRawFd, IntoRawFd};
 
-use {TcpBuilder, UdpBuilder, FromInner, AsInner};
+use {TcpBuilder, UdpBuilder, SocketBuilder, FromInner, AsInner, IntoInner};
+use {OwnedTcpBuilder, OwnedUdpBuilder, Created};
 use socket::Socket;
 use sys::{self, c::__wasi_fd_t};
 
@@ -31,3 +32,53 @@ impl AsRawFd for UdpBuilder {
         self.as_inner().borrow().as_ref().unwrap().as_inner().raw() as __wasi_fd_t
     }
 }
+
+impl FromRawFd for SocketBuilder {
+    unsafe fn from_raw_fd(fd: __wasi_fd_t) -> SocketBuilder {
+        let sock = sys::Socket::from_inner(fd);
+        SocketBuilder::from_inner(Socket::from_inner(sock))
+    }
+}
+
+impl AsRawFd for SocketBuilder {
+    fn as_raw_fd(&self) -> __wasi_fd_t {
+        // A builder whose socket was taken out has no socket to return
+        match *self.as_inner().borrow() {
+            Some(ref s) => s.as_inner().raw() as __wasi_fd_t,
+            None => !0,
+        }
+    }
+}
+
+impl IntoRawFd for SocketBuilder {
+    fn into_raw_fd(self) -> __wasi_fd_t {
+        self.into_inner().expect("socket has already been consumed")
+            .into_inner().into_fd()
+    }
+}
+
+impl FromRawFd for OwnedTcpBuilder<Created> {
+    unsafe fn from_raw_fd(fd: __wasi_fd_t) -> OwnedTcpBuilder<Created> {
+        let sock = sys::Socket::from_inner(fd);
+        OwnedTcpBuilder::from_socket(Socket::from_inner(sock))
+    }
+}
+
+impl<S> AsRawFd for OwnedTcpBuilder<S> {
+    fn as_raw_fd(&self) -> __wasi_fd_t {
+        self.socket().as_inner().raw() as __wasi_fd_t
+    }
+}
+
+impl FromRawFd for OwnedUdpBuilder {
+    unsafe fn from_raw_fd(fd: __wasi_fd_t) -> OwnedUdpBuilder {
+        let sock = sys::Socket::from_inner(fd);
+        OwnedUdpBuilder::from_socket(Socket::from_inner(sock))
+    }
+}
+
+impl AsRawFd for OwnedUdpBuilder {
+    fn as_raw_fd(&self) -> __wasi_fd_t {
+        self.socket().as_inner().raw() as __wasi_fd_t
+    }
+}
@@ -14,6 +14,7 @@ use std::io;
 use std::mem;
 use std::net::{TcpListener, TcpStream, UdpSocket};
 use std::os::wasi::io::FromRawFd;
+use std::time::Duration;
 
 mod impls;
 
@@ -52,6 +53,8 @@ pub mod c {
     pub const IPV6_DROP_MEMBERSHIP: c_int = 0x00;
     pub const SO_REUSEADDR: c_int = 0x00;
     pub const SOCK_STREAM: c_int = 0x00;
+    pub const SOCK_RAW: c_int = 0x00;
+    pub const SOCK_SEQPACKET: c_int = 0x00;
     pub const AF_INET: c_int = 0x00;
     pub const AF_INET6: c_int = 0x01;
 
@@ -142,7 +145,12 @@ pub struct Socket {
 }
 
 impl Socket {
-    pub fn new(_family: c_int, _ty: c_int) -> io::Result<Socket> {
+    pub fn new(_family: c_int, _ty: c_int, _protocol: c_int) -> io::Result<Socket> {
+        unimplemented!()
+    }
+
+    pub fn new_nonblocking(_family: c_int, _ty: c_int,
+                           _protocol: c_int) -> io::Result<Socket> {
         unimplemented!()
     }
 
@@ -150,7 +158,11 @@ impl Socket {
         self.fd
     }
 
-    fn into_fd(self) -> libc::__wasi_fd_t {
+    pub fn wait_writable(&self, _timeout: Duration) -> io::Result<bool> {
+        unimplemented!()
+    }
+
+    pub fn into_fd(self) -> libc::__wasi_fd_t {
         let fd = self.fd;
         mem::forget(self);
         fd
@@ -169,6 +181,15 @@ impl Socket {
     }
 }
 
+pub fn wait_writable(_socks: &[&Socket], _timeout: Option<Duration>)
+                     -> io::Result<Option<usize>> {
+    unimplemented!()
+}
+
+pub fn is_in_progress(_err: &io::Error) -> bool {
+    false
+}
+
 impl ::FromInner for Socket {
     type Inner = libc::__wasi_fd_t;
     fn from_inner(fd: libc::__wasi_fd_t) -> Socket {
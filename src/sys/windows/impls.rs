@@ -8,10 +8,11 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::os::windows::io::{FromRawSocket, RawSocket, AsRawSocket};
-use winapi::um::winsock2::SOCKET;
+use std::os::windows::io::{FromRawSocket, RawSocket, AsRawSocket, IntoRawSocket};
+use winapi::um::winsock2::{INVALID_SOCKET, SOCKET};
 
-use {TcpBuilder, UdpBuilder, FromInner, AsInner};
+use {TcpBuilder, UdpBuilder, SocketBuilder, FromInner, AsInner, IntoInner};
+use {OwnedTcpBuilder, OwnedUdpBuilder, Created};
 use socket::Socket;
 use sys;
 
@@ -42,3 +43,53 @@ impl AsRawSocket for UdpBuilder {
         self.as_inner().borrow().as_ref().unwrap().as_inner().raw() as RawSocket
     }
 }
+
+impl FromRawSocket for SocketBuilder {
+    unsafe fn from_raw_socket(fd: RawSocket) -> SocketBuilder {
+        let sock = sys::Socket::from_inner(fd as SOCKET);
+        SocketBuilder::from_inner(Socket::from_inner(sock))
+    }
+}
+
+impl AsRawSocket for SocketBuilder {
+    fn as_raw_socket(&self) -> RawSocket {
+        // A builder whose socket was taken out has no socket to return
+        match *self.as_inner().borrow() {
+            Some(ref s) => s.as_inner().raw() as RawSocket,
+            None => INVALID_SOCKET as RawSocket,
+        }
+    }
+}
+
+impl IntoRawSocket for SocketBuilder {
+    fn into_raw_socket(self) -> RawSocket {
+        self.into_inner().expect("socket has already been consumed")
+            .into_inner().into_socket() as RawSocket
+    }
+}
+
+impl FromRawSocket for OwnedTcpBuilder<Created> {
+    unsafe fn from_raw_socket(fd: RawSocket) -> OwnedTcpBuilder<Created> {
+        let sock = sys::Socket::from_inner(fd as SOCKET);
+        OwnedTcpBuilder::from_socket(Socket::from_inner(sock))
+    }
+}
+
+impl<S> AsRawSocket for OwnedTcpBuilder<S> {
+    fn as_raw_socket(&self) -> RawSocket {
+        self.socket().as_inner().raw() as RawSocket
+    }
+}
+
+impl FromRawSocket for OwnedUdpBuilder {
+    unsafe fn from_raw_socket(fd: RawSocket) -> OwnedUdpBuilder {
+        let sock = sys::Socket::from_inner(fd as SOCKET);
+        OwnedUdpBuilder::from_socket(Socket::from_inner(sock))
+    }
+}
+
+impl AsRawSocket for OwnedUdpBuilder {
+    fn as_raw_socket(&self) -> RawSocket {
+        self.socket().as_inner().raw() as RawSocket
+    }
+}
@@ -10,11 +10,13 @@
 
 #![allow(bad_style)]
 
+use std::cmp;
 use std::io;
 use std::mem;
 use std::net::{TcpListener, TcpStream, UdpSocket};
 use std::os::windows::io::{RawSocket, FromRawSocket};
 use std::sync::{Once, ONCE_INIT};
+use std::time::{Duration, Instant};
 
 const HANDLE_FLAG_INHERIT: DWORD = 0x00000001;
 
@@ -30,7 +32,7 @@ pub mod c {
     pub use winapi::shared::minwindef::*;
     pub use winapi::shared::ntdef::*;
     pub use winapi::shared::ws2def::*;
-    pub use winapi::shared::ws2def::{SOCK_STREAM, SOCK_DGRAM};
+    pub use winapi::shared::ws2def::{SOCK_STREAM, SOCK_DGRAM, SOCK_RAW, SOCK_SEQPACKET};
     pub use winapi::shared::ws2def::SOCKADDR as sockaddr;
     pub use winapi::shared::ws2def::SOCKADDR_STORAGE as sockaddr_storage;
     pub use winapi::shared::ws2def::SOCKADDR_IN as sockaddr_in;
@@ -40,6 +42,13 @@ pub mod c {
     pub use winapi::shared::ws2ipdef::IP_MREQ as ip_mreq;
     pub use winapi::shared::ws2ipdef::IPV6_MREQ as ipv6_mreq;
 
+    // Options not exported by every version of winapi we support
+    pub const IP_UNICAST_IF: c_int = 31;
+    pub const IPV6_UNICAST_IF: c_int = 31;
+    pub const TCP_KEEPIDLE: c_int = 3;
+    pub const TCP_KEEPCNT: c_int = 16;
+    pub const TCP_KEEPINTVL: c_int = 17;
+
     pub fn sockaddr_in_u32(sa: &sockaddr_in) -> u32 {
         ::ntoh(unsafe { *sa.sin_addr.S_un.S_addr() })
     }
@@ -69,10 +78,10 @@ pub struct Socket {
 }
 
 impl Socket {
-    pub fn new(family: c_int, ty: c_int) -> io::Result<Socket> {
+    pub fn new(family: c_int, ty: c_int, protocol: c_int) -> io::Result<Socket> {
         init();
         let socket = try!(unsafe {
-            match WSASocketW(family, ty, 0, 0 as *mut _, 0,
+            match WSASocketW(family, ty, protocol, 0 as *mut _, 0,
                              WSA_FLAG_OVERLAPPED) {
                 INVALID_SOCKET => Err(io::Error::last_os_error()),
                 n => Ok(Socket { socket: n }),
@@ -82,9 +91,21 @@ impl Socket {
         Ok(socket)
     }
 
+    /// Creates a socket which is in nonblocking mode from the start.
+    pub fn new_nonblocking(family: c_int, ty: c_int,
+                           protocol: c_int) -> io::Result<Socket> {
+        let socket = try!(Socket::new(family, ty, protocol));
+        try!(::ext::set_nonblocking(socket.socket, true));
+        Ok(socket)
+    }
+
     pub fn raw(&self) -> SOCKET { self.socket }
 
-    fn into_socket(self) -> SOCKET {
+    pub fn wait_writable(&self, timeout: Duration) -> io::Result<bool> {
+        wait_writable(&[self], Some(timeout)).map(|r| r.is_some())
+    }
+
+    pub fn into_socket(self) -> SOCKET {
         let socket = self.socket;
         mem::forget(self);
         socket
@@ -109,10 +130,68 @@ impl Socket {
     }
 }
 
+/// Waits for any of `socks` to become writable, returning its index, or
+/// `None` if `timeout` elapses first. No timeout waits indefinitely.
+///
+/// Sockets whose connection attempt failed are reported through the
+/// exception set, so they count as ready too.
+pub fn wait_writable(socks: &[&Socket], timeout: Option<Duration>)
+                     -> io::Result<Option<usize>> {
+    if socks.len() > FD_SETSIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  "too many sockets to wait on"))
+    }
+    let start = Instant::now();
+    loop {
+        let tv = match timeout {
+            Some(timeout) => {
+                let elapsed = start.elapsed();
+                if elapsed >= timeout {
+                    return Ok(None)
+                }
+                let remaining = timeout - elapsed;
+                Some(timeval {
+                    tv_sec: cmp::min(remaining.as_secs(), c_long::max_value() as u64) as c_long,
+                    tv_usec: cmp::min((remaining.subsec_nanos() + 999) / 1000,
+                                      999_999) as c_long,
+                })
+            }
+            None => None,
+        };
+        let mut writefds: fd_set = unsafe { mem::zeroed() };
+        let mut errorfds: fd_set = unsafe { mem::zeroed() };
+        for (i, sock) in socks.iter().enumerate() {
+            writefds.fd_array[i] = sock.socket;
+            errorfds.fd_array[i] = sock.socket;
+        }
+        writefds.fd_count = socks.len() as c_uint;
+        errorfds.fd_count = socks.len() as c_uint;
+        let n = unsafe {
+            select(1, 0 as *mut _, &mut writefds, &mut errorfds,
+                   tv.as_ref().map(|tv| tv as *const _).unwrap_or(0 as *const _))
+        };
+        match n {
+            SOCKET_ERROR => return Err(io::Error::last_os_error()),
+            0 => {}
+            _ => {
+                let ready = writefds.fd_array[..writefds.fd_count as usize].iter()
+                    .chain(&errorfds.fd_array[..errorfds.fd_count as usize])
+                    .filter_map(|s| socks.iter().position(|sock| sock.socket == *s))
+                    .next();
+                return Ok(ready)
+            }
+        }
+    }
+}
+
+pub fn is_in_progress(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::WouldBlock
+}
+
 impl ::FromInner for Socket {
     type Inner = SOCKET;
     fn from_inner(socket: SOCKET) -> Socket {
-        Socket { socket: socket }
+        Socket { socket }
     }
 }
 
@@ -8,20 +8,67 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::io;
+use std::mem;
 use std::net::{SocketAddr, ToSocketAddrs, TcpListener, TcpStream};
 use std::fmt;
+use std::slice;
+use std::time::{Duration, Instant};
 
-use IntoInner;
+use {AsInner, IntoInner};
+use error::{self, ConnectError};
+use ext;
 use socket::Socket;
-use sys::c;
+use sys;
+use sys::c::{self, c_int};
 
 /// An "in progress" TCP socket which has not yet been connected or listened.
 ///
 /// Allows configuration of a socket before one of these operations is executed.
+///
+/// Options set through the builder's methods are recorded so that they can
+/// be replayed if `connect` needs to retry on a fresh socket. Options set on
+/// the raw socket directly, or before it was adopted through
+/// `FromRaw{Fd,Socket}`, are not recorded.
 pub struct TcpBuilder {
     socket: RefCell<Option<Socket>>,
+    options: RefCell<Vec<SockOpt>>,
+    local_addr: RefCell<Option<SocketAddr>>,
+    nonblocking: Cell<bool>,
+}
+
+/// A socket option recorded by a builder, replayed onto fresh sockets.
+pub(crate) struct SockOpt {
+    level: c_int,
+    name: c_int,
+    payload: Vec<u8>,
+}
+
+/// The socket of a builder along with the options, local address and
+/// nonblocking mode recorded for it.
+pub(crate) type Parts = (Socket, Vec<SockOpt>, Option<SocketAddr>, bool);
+
+/// Sets a socket option on `sock` and records it in `options`, replacing any
+/// earlier value of the same option.
+pub(crate) fn record_opt<T: Copy>(sock: &Socket, options: &mut Vec<SockOpt>,
+                                  level: c_int, name: c_int,
+                                  payload: T) -> io::Result<()> {
+    let payload = unsafe {
+        slice::from_raw_parts(&payload as *const T as *const u8,
+                              mem::size_of::<T>())
+    };
+    record_opt_bytes(sock, options, level, name, payload)
+}
+
+/// Like `record_opt`, with the payload given as bytes.
+pub(crate) fn record_opt_bytes(sock: &Socket, options: &mut Vec<SockOpt>,
+                               level: c_int, name: c_int,
+                               payload: &[u8]) -> io::Result<()> {
+    try!(ext::set_opt_bytes(sock.as_inner().raw(), level, name, payload));
+    options.retain(|o| o.level != level || o.name != name);
+    options.push(SockOpt { level, name, payload: payload.to_vec() });
+    Ok(())
 }
 
 impl TcpBuilder {
@@ -43,6 +90,30 @@ impl TcpBuilder {
         Socket::new(c::AF_INET6, c::SOCK_STREAM).map(::FromInner::from_inner)
     }
 
+    /// Constructs a new TcpBuilder like `new_v4`, except that the socket is
+    /// in nonblocking mode from the start.
+    ///
+    /// Where supported this passes `SOCK_NONBLOCK` to socket(2), otherwise
+    /// the socket is switched to nonblocking mode right after it is created.
+    /// `listen` then returns a nonblocking `TcpListener`, and `connect`
+    /// returns as soon as the connection is in progress, see its
+    /// documentation for details.
+    pub fn new_v4_nonblocking() -> io::Result<TcpBuilder> {
+        Socket::new_nonblocking(c::AF_INET, c::SOCK_STREAM, 0)
+            .map(|s| TcpBuilder::from_parts(s, Vec::new(), None, true))
+    }
+
+    /// Constructs a new TcpBuilder like `new_v6`, except that the socket is
+    /// in nonblocking mode from the start.
+    ///
+    /// See [`new_v4_nonblocking`][link] for details.
+    ///
+    /// [link]: #method.new_v4_nonblocking
+    pub fn new_v6_nonblocking() -> io::Result<TcpBuilder> {
+        Socket::new_nonblocking(c::AF_INET6, c::SOCK_STREAM, 0)
+            .map(|s| TcpBuilder::from_parts(s, Vec::new(), None, true))
+    }
+
     /// Binds this socket to the specified address.
     ///
     /// This function directly corresponds to the bind(2) function on Windows
@@ -52,7 +123,9 @@ impl TcpBuilder {
     {
         self.with_socket(|sock| {
             let addr = try!(::one_addr(addr));
-            sock.bind(&addr)
+            try!(sock.bind(&addr));
+            *self.local_addr.borrow_mut() = Some(addr);
+            Ok(())
         }).map(|()| self)
     }
 
@@ -77,20 +150,135 @@ impl TcpBuilder {
     /// This function directly corresponds to the connect(2) function on Windows
     /// and Unix.
     ///
+    /// If `addr` resolves to several addresses they are tried in turn. A
+    /// socket is left in an unspecified state after a failed connect, so every
+    /// attempt after the first is made on a freshly created socket of the same
+    /// family, with the options recorded by this builder replayed onto it and
+    /// bound to the same local address if `bind` was called.
+    ///
+    /// If every address fails the returned error wraps a `ConnectError`
+    /// describing each attempt, even if only one address was tried.
+    ///
+    /// For a builder created by one of the `_nonblocking` constructors a
+    /// connection which is still in progress (`EINPROGRESS`) counts as
+    /// success, and the returned stream is nonblocking and may still be
+    /// connecting. Its outcome can be checked once the stream is writable
+    /// through `take_error`. The next address is only tried if connect(2)
+    /// fails outright.
+    ///
     /// An error will be returned if `listen` or `connect` has already been
     /// called on this builder.
     pub fn connect<T>(&self, addr: T) -> io::Result<TcpStream>
         where T: ToSocketAddrs
     {
-        self.with_socket(|sock| {
-            let err = io::Error::new(io::ErrorKind::Other,
-                                     "no socket addresses resolved");
-            try!(addr.to_socket_addrs()).fold(Err(err), |prev, addr| {
-                prev.or_else(|_| sock.connect(&addr))
+        try!(self.with_socket(|_| Ok(())));
+        try!(error::each_addr(addr, |i, addr| {
+            if i > 0 {
+                try!(self.renew(addr));
+            }
+            self.with_socket(|sock| {
+                match sock.connect(addr) {
+                    Err(ref e) if self.nonblocking.get() && sys::is_in_progress(e) => Ok(()),
+                    res => res,
+                }
             })
-        }).and_then(|()| {
-            self.to_tcp_stream()
-        })
+        }));
+        self.to_tcp_stream()
+    }
+
+    /// Initiate a connection on this socket to the specified address with
+    /// TCP Fast Open, sending `data` along with the SYN.
+    ///
+    /// This returns the stream along with the number of bytes of `data`
+    /// which were sent. If the server's Fast Open cookie is not known yet
+    /// only a cookie request is sent in the SYN and, once connected, `data`
+    /// is sent normally. Any part of `data` which was not sent has to be
+    /// written to the returned stream. For a builder created by one of the
+    /// `_nonblocking` constructors the connection may still be in progress,
+    /// in which case no data has been sent.
+    ///
+    /// Addresses are tried in turn just like `connect`. This is only
+    /// supported on Linux and Android, and needs bit 0 of the
+    /// `net.ipv4.tcp_fastopen` sysctl set for data to be sent in the SYN.
+    /// Without it the connection is established normally and `data` is sent
+    /// once connected, as if Fast Open was not used.
+    ///
+    /// An error will be returned if `listen` or `connect` has already been
+    /// called on this builder.
+    pub fn connect_with_data<T>(&self, addr: T, data: &[u8])
+                                -> io::Result<(TcpStream, usize)>
+        where T: ToSocketAddrs
+    {
+        try!(self.with_socket(|_| Ok(())));
+        let mut sent = 0;
+        try!(error::each_addr(addr, |i, addr| {
+            if i > 0 {
+                try!(self.renew(addr));
+            }
+            self.with_socket(|sock| {
+                match sock.connect_fastopen(addr, data) {
+                    Ok(n) => {
+                        sent = n;
+                        Ok(())
+                    }
+                    Err(ref e) if self.nonblocking.get() && sys::is_in_progress(e) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            })
+        }));
+        Ok((try!(self.to_tcp_stream()), sent))
+    }
+
+    /// Initiate a connection on this socket to the specified address, only
+    /// waiting up to `timeout` for it to be established.
+    ///
+    /// The timeout is an overall deadline: if `addr` resolves to several
+    /// addresses they are tried in turn, each with whatever time remains and
+    /// each on a fresh socket just like `connect`. The socket is put into
+    /// nonblocking mode while connecting and any error is retrieved through
+    /// `SO_ERROR`, just like `take_error`. An error of kind `TimedOut` is
+    /// returned if the deadline passes. As with `connect`, a failure
+    /// wraps a `ConnectError` describing each attempt.
+    ///
+    /// For a builder created by one of the `_nonblocking` constructors this
+    /// still waits for the connection and then returns a nonblocking stream.
+    ///
+    /// An error will be returned if `listen` or `connect` has already been
+    /// called on this builder.
+    pub fn connect_timeout<T>(&self, addr: T, timeout: Duration)
+                              -> io::Result<TcpStream>
+        where T: ToSocketAddrs
+    {
+        let deadline = Instant::now() + timeout;
+        try!(self.with_socket(|_| Ok(())));
+        let mut err = ConnectError::new();
+        for (i, addr) in try!(addr.to_socket_addrs()).enumerate() {
+            let start = Instant::now();
+            if start >= deadline {
+                err.push(addr, io::Error::new(io::ErrorKind::TimedOut,
+                                              "connection timed out"),
+                         Duration::from_secs(0));
+                break
+            }
+            let res = if i > 0 { self.renew(&addr) } else { Ok(()) };
+            let res = res.and_then(|()| {
+                self.with_socket(|sock| sock.connect_timeout(&addr, deadline - start))
+            });
+            let res = res.and_then(|()| {
+                if self.nonblocking.get() {
+                    self.with_socket(|sock| {
+                        ext::set_nonblocking(sock.as_inner().raw(), true)
+                    })
+                } else {
+                    Ok(())
+                }
+            });
+            match res {
+                Ok(()) => return self.to_tcp_stream(),
+                Err(e) => err.push(addr, e, start.elapsed()),
+            }
+        }
+        Err(err.into())
     }
 
     /// Converts this builder into a `TcpStream`
@@ -101,8 +289,7 @@ impl TcpBuilder {
     /// etc.
     pub fn to_tcp_stream(&self) -> io::Result<TcpStream> {
         self.socket.borrow_mut().take().map(|s| s.into_inner().into_tcp_stream())
-            .ok_or(io::Error::new(io::ErrorKind::Other,
-                                  "socket has already been consumed"))
+            .ok_or(error::consumed())
     }
 
     /// Converts this builder into a `TcpListener`
@@ -114,8 +301,7 @@ impl TcpBuilder {
     pub fn to_tcp_listener(&self) -> io::Result<TcpListener> {
         self.socket.borrow_mut().take()
             .map(|s| s.into_inner().into_tcp_listener())
-            .ok_or(io::Error::new(io::ErrorKind::Other,
-                                  "socket has already been consumed"))
+            .ok_or(error::consumed())
     }
 
     /// Returns the address of the local half of this TCP socket.
@@ -125,8 +311,19 @@ impl TcpBuilder {
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
         match *self.socket.borrow() {
             Some(ref s) => s.getsockname(),
-            None => Err(io::Error::new(io::ErrorKind::Other,
-                                       "builder has already finished its socket")),
+            None => Err(error::finished()),
+        }
+    }
+
+    /// Returns the address of the remote half of this TCP socket.
+    ///
+    /// This is mostly useful for connected sockets adopted through
+    /// `FromRaw{Fd,Socket}`. An error will be returned if the socket is not
+    /// connected or has already been consumed.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match *self.socket.borrow() {
+            Some(ref s) => s.getpeername(),
+            None => Err(error::finished()),
         }
     }
 
@@ -135,16 +332,93 @@ impl TcpBuilder {
     {
         match *self.socket.borrow() {
             Some(ref s) => f(s),
-            None => Err(io::Error::new(io::ErrorKind::Other,
-                                       "builder has already finished its socket")),
+            None => Err(error::finished()),
         }
     }
+
+    /// Sets a socket option on the current socket and records it so that it
+    /// is replayed if the socket is ever renewed.
+    pub(crate) fn set_opt<T: Copy>(&self, level: c_int, name: c_int,
+                                   payload: T) -> io::Result<()> {
+        self.with_socket(|sock| {
+            record_opt(sock, &mut self.options.borrow_mut(), level, name, payload)
+        })
+    }
+
+    /// Like `set_opt`, with the payload given as bytes.
+    pub(crate) fn set_opt_bytes(&self, level: c_int, name: c_int,
+                                payload: &[u8]) -> io::Result<()> {
+        self.with_socket(|sock| {
+            record_opt_bytes(sock, &mut self.options.borrow_mut(), level, name,
+                             payload)
+        })
+    }
+
+    /// Moves the current socket into or out of nonblocking mode and records
+    /// the mode for sockets it is renewed with.
+    pub(crate) fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        try!(self.with_socket(|sock| {
+            ext::set_nonblocking(sock.as_inner().raw(), nonblocking)
+        }));
+        self.nonblocking.set(nonblocking);
+        Ok(())
+    }
+
+    /// Takes the socket out of this builder along with the options and
+    /// local address recorded for it.
+    pub(crate) fn into_parts(self) -> io::Result<Parts> {
+        let sock = try!(self.socket.into_inner().ok_or_else(error::consumed));
+        Ok((sock, self.options.into_inner(), self.local_addr.into_inner(),
+            self.nonblocking.into_inner()))
+    }
+
+    pub(crate) fn from_parts(sock: Socket, options: Vec<SockOpt>,
+                             local_addr: Option<SocketAddr>,
+                             nonblocking: bool) -> TcpBuilder {
+        TcpBuilder {
+            socket: RefCell::new(Some(sock)),
+            options: RefCell::new(options),
+            local_addr: RefCell::new(local_addr),
+            nonblocking: Cell::new(nonblocking),
+        }
+    }
+
+    /// Replaces the current socket with a fresh one of the same family,
+    /// replaying every recorded option, the local address and nonblocking
+    /// mode.
+    fn renew(&self, addr: &SocketAddr) -> io::Result<()> {
+        let old = try!(self.socket.borrow_mut().take().ok_or_else(|| {
+            error::finished()
+        }));
+        let family = old.family().unwrap_or(match *addr {
+            SocketAddr::V4(..) => c::AF_INET,
+            SocketAddr::V6(..) => c::AF_INET6,
+        });
+        // Close the old socket first so the local address can be reused
+        drop(old);
+        let sock = if self.nonblocking.get() {
+            try!(Socket::new_nonblocking(family, c::SOCK_STREAM, 0))
+        } else {
+            try!(Socket::new(family, c::SOCK_STREAM))
+        };
+        for opt in self.options.borrow().iter() {
+            try!(ext::set_opt_bytes(sock.as_inner().raw(), opt.level, opt.name,
+                                    &opt.payload));
+        }
+        if let Some(ref local) = *self.local_addr.borrow() {
+            try!(sock.bind(local));
+        }
+        *self.socket.borrow_mut() = Some(sock);
+        Ok(())
+    }
 }
 
 impl fmt::Debug for TcpBuilder {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "TcpBuilder {{ socket: {:?} }}",
-               self.socket.borrow().as_ref().unwrap())
+        match *self.socket.borrow() {
+            Some(ref s) => write!(f, "TcpBuilder {{ socket: {:?} }}", s),
+            None => write!(f, "TcpBuilder {{ socket: (consumed) }}"),
+        }
     }
 }
 
@@ -156,6 +430,6 @@ impl ::AsInner for TcpBuilder {
 impl ::FromInner for TcpBuilder {
     type Inner = Socket;
     fn from_inner(sock: Socket) -> TcpBuilder {
-        TcpBuilder { socket: RefCell::new(Some(sock)) }
+        TcpBuilder::from_parts(sock, Vec::new(), None, false)
     }
 }
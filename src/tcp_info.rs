@@ -0,0 +1,326 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use ext::{self, Socket};
+use sys::c::{self, c_int};
+
+// Large enough for every field of `struct tcp_info` up to Linux 6.x, the
+// kernel truncates the structure to the buffer it is given.
+const BUF_LEN: usize = 256;
+
+// Size of the fields every kernel we support fills in, up to and including
+// `tcpi_total_retrans`.
+const BASE_LEN: usize = 104;
+
+/// The state of a TCP connection, as reported in [`TcpInfo`][link].
+///
+/// [link]: struct.TcpInfo.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpState {
+    /// `TCP_ESTABLISHED`
+    Established,
+    /// `TCP_SYN_SENT`
+    SynSent,
+    /// `TCP_SYN_RECV`
+    SynRecv,
+    /// `TCP_FIN_WAIT1`
+    FinWait1,
+    /// `TCP_FIN_WAIT2`
+    FinWait2,
+    /// `TCP_TIME_WAIT`
+    TimeWait,
+    /// `TCP_CLOSE`
+    Close,
+    /// `TCP_CLOSE_WAIT`
+    CloseWait,
+    /// `TCP_LAST_ACK`
+    LastAck,
+    /// `TCP_LISTEN`
+    Listen,
+    /// `TCP_CLOSING`
+    Closing,
+    /// A state this crate does not know about, with its raw value.
+    Other(u8),
+}
+
+impl TcpState {
+    fn from_raw(raw: u8) -> TcpState {
+        match raw {
+            1 => TcpState::Established,
+            2 => TcpState::SynSent,
+            3 => TcpState::SynRecv,
+            4 => TcpState::FinWait1,
+            5 => TcpState::FinWait2,
+            6 => TcpState::TimeWait,
+            7 => TcpState::Close,
+            8 => TcpState::CloseWait,
+            9 => TcpState::LastAck,
+            10 => TcpState::Listen,
+            11 => TcpState::Closing,
+            n => TcpState::Other(n),
+        }
+    }
+}
+
+/// Statistics about a TCP connection, read from the `TCP_INFO` option.
+///
+/// The kernel fills in as much of `struct tcp_info` as it knows about, which
+/// grew over time. Fields present in every supported kernel are returned
+/// directly, while fields added later return `None` when the running kernel
+/// did not fill them in; the release which added them is noted on each.
+///
+/// This is returned by [`TcpStreamExt::tcp_info`][link] and is only
+/// supported on Linux and Android.
+///
+/// [link]: trait.TcpStreamExt.html#method.tcp_info
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::net::TcpStream;
+/// use net2::TcpStreamExt;
+///
+/// let stream = TcpStream::connect("127.0.0.1:80").unwrap();
+/// let info = stream.tcp_info().unwrap();
+/// println!("rtt: {:?}, cwnd: {}", info.rtt(), info.snd_cwnd());
+/// if let Some(acked) = info.bytes_acked() {
+///     println!("acked: {}", acked);
+/// }
+/// ```
+#[derive(Clone, Copy)]
+pub struct TcpInfo {
+    buf: [u8; BUF_LEN],
+    len: usize,
+}
+
+impl TcpInfo {
+    /// Returns the number of bytes of `struct tcp_info` the kernel filled
+    /// in, which identifies the fields it knows about.
+    pub fn size(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the state of the connection.
+    pub fn state(&self) -> TcpState {
+        TcpState::from_raw(self.buf[0])
+    }
+
+    /// Returns the number of consecutive retransmission timeouts which have
+    /// not been recovered from yet.
+    pub fn retransmits(&self) -> u8 {
+        self.buf[2]
+    }
+
+    /// Returns the number of unanswered zero window or keepalive probes.
+    pub fn probes(&self) -> u8 {
+        self.buf[3]
+    }
+
+    /// Returns the current retransmission timeout.
+    pub fn rto(&self) -> Duration {
+        Duration::from_micros(self.base_u32(8) as u64)
+    }
+
+    /// Returns the maximum segment size used when sending.
+    pub fn snd_mss(&self) -> u32 {
+        self.base_u32(16)
+    }
+
+    /// Returns the maximum segment size seen from the peer.
+    pub fn rcv_mss(&self) -> u32 {
+        self.base_u32(20)
+    }
+
+    /// Returns the number of segments sent but not yet acknowledged.
+    pub fn unacked(&self) -> u32 {
+        self.base_u32(24)
+    }
+
+    /// Returns the number of segments currently considered lost.
+    pub fn lost(&self) -> u32 {
+        self.base_u32(32)
+    }
+
+    /// Returns the path MTU.
+    pub fn pmtu(&self) -> u32 {
+        self.base_u32(60)
+    }
+
+    /// Returns the smoothed round trip time.
+    pub fn rtt(&self) -> Duration {
+        Duration::from_micros(self.base_u32(68) as u64)
+    }
+
+    /// Returns the variance of the round trip time.
+    pub fn rtt_var(&self) -> Duration {
+        Duration::from_micros(self.base_u32(72) as u64)
+    }
+
+    /// Returns the slow start threshold, in segments.
+    ///
+    /// This is `0x7fffffff` until the first loss is detected.
+    pub fn snd_ssthresh(&self) -> u32 {
+        self.base_u32(76)
+    }
+
+    /// Returns the congestion window, in segments.
+    pub fn snd_cwnd(&self) -> u32 {
+        self.base_u32(80)
+    }
+
+    /// Returns the total number of segments retransmitted over the life of
+    /// the connection.
+    pub fn total_retrans(&self) -> u32 {
+        self.base_u32(100)
+    }
+
+    /// Returns the pacing rate in bytes per second, added in Linux 3.15.
+    pub fn pacing_rate(&self) -> Option<u64> {
+        self.u64_at(104)
+    }
+
+    /// Returns the number of bytes acknowledged by the peer, added in Linux
+    /// 4.1.
+    pub fn bytes_acked(&self) -> Option<u64> {
+        self.u64_at(120)
+    }
+
+    /// Returns the number of bytes received from the peer, added in Linux
+    /// 4.1.
+    pub fn bytes_received(&self) -> Option<u64> {
+        self.u64_at(128)
+    }
+
+    /// Returns the number of segments sent, added in Linux 4.2.
+    pub fn segs_out(&self) -> Option<u32> {
+        self.u32_at(136)
+    }
+
+    /// Returns the number of segments received, added in Linux 4.2.
+    pub fn segs_in(&self) -> Option<u32> {
+        self.u32_at(140)
+    }
+
+    /// Returns the number of bytes written but not yet sent, added in Linux
+    /// 4.6.
+    pub fn notsent_bytes(&self) -> Option<u32> {
+        self.u32_at(144)
+    }
+
+    /// Returns the minimum round trip time observed, added in Linux 4.6.
+    pub fn min_rtt(&self) -> Option<Duration> {
+        self.u32_at(148).map(|us| Duration::from_micros(us as u64))
+    }
+
+    /// Returns the most recent delivery rate in bytes per second, added in
+    /// Linux 4.9.
+    pub fn delivery_rate(&self) -> Option<u64> {
+        self.u64_at(160)
+    }
+
+    /// Returns the number of segments delivered to the peer, added in Linux
+    /// 4.18.
+    pub fn delivered(&self) -> Option<u32> {
+        self.u32_at(192)
+    }
+
+    /// Returns the number of bytes sent, including retransmissions, added
+    /// in Linux 4.19.
+    pub fn bytes_sent(&self) -> Option<u64> {
+        self.u64_at(200)
+    }
+
+    /// Returns the number of bytes retransmitted, added in Linux 4.19.
+    pub fn bytes_retrans(&self) -> Option<u64> {
+        self.u64_at(208)
+    }
+
+    fn base_u32(&self, off: usize) -> u32 {
+        self.u32_at(off).unwrap()
+    }
+
+    fn u32_at(&self, off: usize) -> Option<u32> {
+        if off + 4 > self.len {
+            return None
+        }
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&self.buf[off..off + 4]);
+        Some(u32::from_ne_bytes(bytes))
+    }
+
+    fn u64_at(&self, off: usize) -> Option<u64> {
+        if off + 8 > self.len {
+            return None
+        }
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.buf[off..off + 8]);
+        Some(u64::from_ne_bytes(bytes))
+    }
+}
+
+impl fmt::Debug for TcpInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TcpInfo")
+            .field("size", &self.len)
+            .field("state", &self.state())
+            .field("retransmits", &self.retransmits())
+            .field("probes", &self.probes())
+            .field("rto", &self.rto())
+            .field("snd_mss", &self.snd_mss())
+            .field("rcv_mss", &self.rcv_mss())
+            .field("unacked", &self.unacked())
+            .field("lost", &self.lost())
+            .field("pmtu", &self.pmtu())
+            .field("rtt", &self.rtt())
+            .field("rtt_var", &self.rtt_var())
+            .field("snd_ssthresh", &self.snd_ssthresh())
+            .field("snd_cwnd", &self.snd_cwnd())
+            .field("total_retrans", &self.total_retrans())
+            .field("pacing_rate", &self.pacing_rate())
+            .field("bytes_acked", &self.bytes_acked())
+            .field("bytes_received", &self.bytes_received())
+            .field("segs_out", &self.segs_out())
+            .field("segs_in", &self.segs_in())
+            .field("notsent_bytes", &self.notsent_bytes())
+            .field("min_rtt", &self.min_rtt())
+            .field("delivery_rate", &self.delivery_rate())
+            .field("delivered", &self.delivered())
+            .field("bytes_sent", &self.bytes_sent())
+            .field("bytes_retrans", &self.bytes_retrans())
+            .finish()
+    }
+}
+
+/// Reads the `TCP_INFO` option of `sock`.
+pub(crate) fn get(sock: Socket) -> io::Result<TcpInfo> {
+    let (level, name) = try!(info_opt());
+    let mut buf = [0; BUF_LEN];
+    let len = try!(ext::get_opt_bytes(sock, level, name, &mut buf));
+    if len < BASE_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                  "TCP_INFO returned by the kernel is too short"))
+    }
+    Ok(TcpInfo { buf, len: ::std::cmp::min(len, BUF_LEN) })
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn info_opt() -> io::Result<(c_int, c_int)> {
+    Ok((c::IPPROTO_TCP, c::TCP_INFO))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn info_opt() -> io::Result<(c_int, c_int)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported,
+                       "TCP_INFO is not supported by platform"))
+}
@@ -11,9 +11,10 @@
 use std::cell::RefCell;
 use std::fmt;
 use std::io;
-use std::net::{ToSocketAddrs, UdpSocket};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
 
 use IntoInner;
+use error;
 use socket::Socket;
 use sys::c;
 
@@ -43,6 +44,23 @@ impl UdpBuilder {
         Socket::new(c::AF_INET6, c::SOCK_DGRAM).map(::FromInner::from_inner)
     }
 
+    /// Constructs a new UdpBuilder like `new_v4`, except that the socket is
+    /// in nonblocking mode from the start.
+    ///
+    /// Where supported this passes `SOCK_NONBLOCK` to socket(2), otherwise
+    /// the socket is switched to nonblocking mode right after it is created.
+    pub fn new_v4_nonblocking() -> io::Result<UdpBuilder> {
+        Socket::new_nonblocking(c::AF_INET, c::SOCK_DGRAM, 0)
+            .map(::FromInner::from_inner)
+    }
+
+    /// Constructs a new UdpBuilder like `new_v6`, except that the socket is
+    /// in nonblocking mode from the start.
+    pub fn new_v6_nonblocking() -> io::Result<UdpBuilder> {
+        Socket::new_nonblocking(c::AF_INET6, c::SOCK_DGRAM, 0)
+            .map(::FromInner::from_inner)
+    }
+
     /// Binds this socket to the specified address.
     ///
     /// This function directly corresponds to the bind(2) function on Windows
@@ -57,21 +75,69 @@ impl UdpBuilder {
         Ok(self.socket.borrow_mut().take().unwrap().into_inner().into_udp_socket())
     }
 
+    /// Connects this socket to the specified remote address, returning a
+    /// connected `UdpSocket`.
+    ///
+    /// This function directly corresponds to the connect(2) function on
+    /// Windows and Unix. If the socket has not been bound the kernel picks
+    /// the source address and port, based on the route to `addr`.
+    ///
+    /// If `addr` resolves to several addresses they are tried in turn and
+    /// the first one accepted is used. If every address fails the returned
+    /// error wraps a `ConnectError`.
+    pub fn connect<T>(&self, addr: T) -> io::Result<UdpSocket>
+        where T: ToSocketAddrs
+    {
+        try!(self.with_socket(|sock| {
+            error::each_addr(addr, |_, addr| sock.connect(addr))
+        }));
+        Ok(self.socket.borrow_mut().take().unwrap().into_inner().into_udp_socket())
+    }
+
+    /// Binds this socket to `local` and then connects it to `remote`,
+    /// returning a connected `UdpSocket`.
+    ///
+    /// This is a shorthand for a `bind` which keeps the socket followed by
+    /// `connect`. A port of 0 in `local` still lets the kernel pick the
+    /// source port.
+    pub fn bind_and_connect<T, U>(&self, local: T, remote: U) -> io::Result<UdpSocket>
+        where T: ToSocketAddrs, U: ToSocketAddrs
+    {
+        try!(self.with_socket(|sock| {
+            let addr = try!(::one_addr(local));
+            sock.bind(&addr)
+        }));
+        self.connect(remote)
+    }
+
+    /// Returns the address of the remote half of this UDP socket.
+    ///
+    /// This is mostly useful for connected sockets adopted through
+    /// `FromRaw{Fd,Socket}`. An error will be returned if the socket is not
+    /// connected or has already been consumed.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match *self.socket.borrow() {
+            Some(ref s) => s.getpeername(),
+            None => Err(error::finished()),
+        }
+    }
+
     fn with_socket<F>(&self, f: F) -> io::Result<()>
         where F: FnOnce(&Socket) -> io::Result<()>
     {
         match *self.socket.borrow() {
             Some(ref s) => f(s),
-            None => Err(io::Error::new(io::ErrorKind::Other,
-                                       "builder has already finished its socket")),
+            None => Err(error::finished()),
         }
     }
 }
 
 impl fmt::Debug for UdpBuilder {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "UdpBuilder {{ socket: {:?} }}",
-               self.socket.borrow().as_ref().unwrap())
+        match *self.socket.borrow() {
+            Some(ref s) => write!(f, "UdpBuilder {{ socket: {:?} }}", s),
+            None => write!(f, "UdpBuilder {{ socket: (consumed) }}"),
+        }
     }
 }
 
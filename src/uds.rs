@@ -0,0 +1,491 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+use std::path::Path;
+use std::time::Duration;
+
+use {AsInner, IntoInner};
+use ext;
+use error;
+use socket::Socket;
+use sys::c::{self, c_int};
+
+/// The address of a Unix domain socket.
+///
+/// Unlike the `SocketAddr` type in `std::os::unix::net` this type can be
+/// constructed directly, which allows naming sockets in the Linux abstract
+/// namespace as well as requesting an autobound address.
+#[derive(Clone)]
+pub struct UnixSocketAddr {
+    addr: c::sockaddr_un,
+    len: c::socklen_t,
+}
+
+impl UnixSocketAddr {
+    /// Creates an address referring to the filesystem path `path`.
+    ///
+    /// An error is returned if the path contains a null byte or is too long
+    /// to fit in a `sockaddr_un`.
+    pub fn from_pathname<P: AsRef<Path>>(path: P) -> io::Result<UnixSocketAddr> {
+        let bytes = path.as_ref().as_os_str().as_bytes();
+        if bytes.contains(&0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "paths may not contain interior null bytes"))
+        }
+        let mut ret = UnixSocketAddr::unnamed();
+        // leave room for the trailing null byte
+        if bytes.len() >= ret.addr.sun_path.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "path must be shorter than SUN_LEN"))
+        }
+        for (dst, src) in ret.addr.sun_path.iter_mut().zip(bytes) {
+            *dst = *src as c::c_char;
+        }
+        ret.len += bytes.len() as c::socklen_t + 1;
+        Ok(ret)
+    }
+
+    /// Creates an address in the Linux abstract namespace.
+    ///
+    /// The leading null byte is added automatically, `name` should not
+    /// include it. Abstract names are not null terminated and may contain
+    /// arbitrary bytes.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn from_abstract_name(name: &[u8]) -> io::Result<UnixSocketAddr> {
+        let mut ret = UnixSocketAddr::unnamed();
+        if name.len() >= ret.addr.sun_path.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "abstract name must be shorter than SUN_LEN"))
+        }
+        for (dst, src) in ret.addr.sun_path[1..].iter_mut().zip(name) {
+            *dst = *src as c::c_char;
+        }
+        ret.len += name.len() as c::socklen_t + 1;
+        Ok(ret)
+    }
+
+    /// Creates an unnamed address.
+    ///
+    /// Binding a socket to this address on Linux asks the kernel to
+    /// autobind it to a unique name in the abstract namespace, which can then
+    /// be retrieved through `local_addr`.
+    pub fn unnamed() -> UnixSocketAddr {
+        let mut addr: c::sockaddr_un = unsafe { mem::zeroed() };
+        addr.sun_family = c::AF_UNIX as c::sa_family_t;
+        let len = sun_path_offset(&addr) as c::socklen_t;
+        UnixSocketAddr { addr, len }
+    }
+
+    /// Returns whether this address is unnamed.
+    pub fn is_unnamed(&self) -> bool {
+        self.len as usize == sun_path_offset(&self.addr)
+    }
+
+    /// Returns the filesystem path of this address, if it has one.
+    pub fn as_pathname(&self) -> Option<&Path> {
+        let path = self.path_bytes();
+        if path.is_empty() || path[0] == 0 {
+            return None
+        }
+        let end = path.iter().position(|b| *b == 0).unwrap_or(path.len());
+        Some(Path::new(OsStr::from_bytes(&path[..end])))
+    }
+
+    /// Returns the name of this address in the abstract namespace, if it is
+    /// an abstract address.
+    ///
+    /// The leading null byte is not included in the returned name.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn as_abstract_name(&self) -> Option<&[u8]> {
+        let path = self.path_bytes();
+        if !path.is_empty() && path[0] == 0 {
+            Some(&path[1..])
+        } else {
+            None
+        }
+    }
+
+    fn path_bytes(&self) -> &[u8] {
+        let len = self.len as usize - sun_path_offset(&self.addr);
+        let path: &[c::c_char] = &self.addr.sun_path[..len];
+        unsafe { &*(path as *const [c::c_char] as *const [u8]) }
+    }
+
+    fn as_ptr(&self) -> *const c::sockaddr {
+        &self.addr as *const _ as *const c::sockaddr
+    }
+
+    fn from_raw(addr: c::sockaddr_un, len: c::socklen_t) -> io::Result<UnixSocketAddr> {
+        if addr.sun_family as c_int != c::AF_UNIX {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "address is not an AF_UNIX address"))
+        }
+        // Some platforms report a length of zero for unnamed sockets
+        let len = if len == 0 {
+            sun_path_offset(&addr) as c::socklen_t
+        } else {
+            len
+        };
+        Ok(UnixSocketAddr { addr, len })
+    }
+}
+
+impl fmt::Debug for UnixSocketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_unnamed() {
+            return write!(f, "(unnamed)")
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            if let Some(name) = self.as_abstract_name() {
+                return write!(f, "{:?} (abstract)",
+                              String::from_utf8_lossy(name))
+            }
+        }
+        match self.as_pathname() {
+            Some(path) => write!(f, "{:?} (pathname)", path),
+            None => write!(f, "(unnamed)"),
+        }
+    }
+}
+
+fn sun_path_offset(addr: &c::sockaddr_un) -> usize {
+    let base = addr as *const _ as usize;
+    let path = &addr.sun_path as *const _ as usize;
+    path - base
+}
+
+pub(crate) fn bind(sock: &Socket, addr: &UnixSocketAddr) -> io::Result<()> {
+    unsafe {
+        ::cvt(c::bind(sock.as_inner().raw(), addr.as_ptr(), addr.len)).map(|_| ())
+    }
+}
+
+pub(crate) fn connect(sock: &Socket, addr: &UnixSocketAddr) -> io::Result<()> {
+    unsafe {
+        ::cvt(c::connect(sock.as_inner().raw(), addr.as_ptr(), addr.len)).map(|_| ())
+    }
+}
+
+pub(crate) fn getsockname(sock: &Socket) -> io::Result<UnixSocketAddr> {
+    unsafe {
+        let mut addr: c::sockaddr_un = mem::zeroed();
+        let mut len = mem::size_of_val(&addr) as c::socklen_t;
+        try!(::cvt(c::getsockname(sock.as_inner().raw(),
+                                  &mut addr as *mut _ as *mut _,
+                                  &mut len)));
+        UnixSocketAddr::from_raw(addr, len)
+    }
+}
+
+pub(crate) fn getpeername(sock: &Socket) -> io::Result<UnixSocketAddr> {
+    unsafe {
+        let mut addr: c::sockaddr_un = mem::zeroed();
+        let mut len = mem::size_of_val(&addr) as c::socklen_t;
+        try!(::cvt(c::getpeername(sock.as_inner().raw(),
+                                  &mut addr as *mut _ as *mut _,
+                                  &mut len)));
+        UnixSocketAddr::from_raw(addr, len)
+    }
+}
+
+/// An "in progress" Unix domain stream socket which has not yet been connected
+/// or listened.
+///
+/// Allows configuration of a socket before one of these operations is executed.
+pub struct UnixStreamBuilder {
+    socket: RefCell<Option<Socket>>,
+}
+
+impl UnixStreamBuilder {
+    /// Constructs a new UnixStreamBuilder with the `AF_UNIX` domain, the
+    /// `SOCK_STREAM` type, and with a protocol argument of 0.
+    pub fn new() -> io::Result<UnixStreamBuilder> {
+        Socket::new(c::AF_UNIX, c::SOCK_STREAM).map(::FromInner::from_inner)
+    }
+
+    /// Binds this socket to the specified address.
+    ///
+    /// This function directly corresponds to the bind(2) function.
+    pub fn bind(&self, addr: &UnixSocketAddr) -> io::Result<&UnixStreamBuilder> {
+        self.with_socket(|sock| bind(sock, addr)).map(|()| self)
+    }
+
+    /// Mark a socket as ready to accept incoming connection requests using
+    /// accept()
+    ///
+    /// This function directly corresponds to the listen(2) function.
+    ///
+    /// An error will be returned if `listen` or `connect` has already been
+    /// called on this builder.
+    pub fn listen(&self, backlog: i32) -> io::Result<UnixListener> {
+        self.with_socket(|sock| {
+            sock.listen(backlog)
+        }).and_then(|()| {
+            self.to_unix_listener()
+        })
+    }
+
+    /// Initiate a connection on this socket to the specified address.
+    ///
+    /// This function directly corresponds to the connect(2) function.
+    ///
+    /// An error will be returned if `listen` or `connect` has already been
+    /// called on this builder.
+    pub fn connect(&self, addr: &UnixSocketAddr) -> io::Result<UnixStream> {
+        self.with_socket(|sock| connect(sock, addr)).and_then(|()| {
+            self.to_unix_stream()
+        })
+    }
+
+    /// Converts this builder into a `UnixStream`
+    ///
+    /// This function will consume the internal socket and return it re-wrapped
+    /// as a `UnixStream`. An error will be returned if the internal socket has
+    /// already been consumed from a successful call to `connect`, `listen`,
+    /// etc.
+    pub fn to_unix_stream(&self) -> io::Result<UnixStream> {
+        self.socket.borrow_mut().take().map(|s| s.into_inner().into_unix_stream())
+            .ok_or(error::consumed())
+    }
+
+    /// Converts this builder into a `UnixListener`
+    ///
+    /// This function will consume the internal socket and return it re-wrapped
+    /// as a `UnixListener`. An error will be returned if the internal socket
+    /// has already been consumed from a successful call to `connect`,
+    /// `listen`, etc.
+    pub fn to_unix_listener(&self) -> io::Result<UnixListener> {
+        self.socket.borrow_mut().take().map(|s| s.into_inner().into_unix_listener())
+            .ok_or(error::consumed())
+    }
+
+    /// Returns the address of the local half of this socket.
+    ///
+    /// An error will be returned if `listen` or `connect` has already been
+    /// called on this builder.
+    pub fn local_addr(&self) -> io::Result<UnixSocketAddr> {
+        match *self.socket.borrow() {
+            Some(ref s) => getsockname(s),
+            None => Err(error::finished()),
+        }
+    }
+
+    /// Returns the address of the remote half of this socket.
+    ///
+    /// This is mostly useful for connected sockets adopted through
+    /// `FromRawFd`. An error will be returned if the socket is not connected
+    /// or has already been consumed.
+    pub fn peer_addr(&self) -> io::Result<UnixSocketAddr> {
+        match *self.socket.borrow() {
+            Some(ref s) => getpeername(s),
+            None => Err(error::finished()),
+        }
+    }
+
+    fn with_socket<F>(&self, f: F) -> io::Result<()>
+        where F: FnOnce(&Socket) -> io::Result<()>
+    {
+        match *self.socket.borrow() {
+            Some(ref s) => f(s),
+            None => Err(error::finished()),
+        }
+    }
+}
+
+/// An "in progress" Unix domain datagram socket which has not yet been bound
+/// or connected.
+///
+/// Allows configuration of a socket before the socket is bound.
+pub struct UnixDatagramBuilder {
+    socket: RefCell<Option<Socket>>,
+}
+
+impl UnixDatagramBuilder {
+    /// Constructs a new UnixDatagramBuilder with the `AF_UNIX` domain, the
+    /// `SOCK_DGRAM` type, and with a protocol argument of 0.
+    pub fn new() -> io::Result<UnixDatagramBuilder> {
+        Socket::new(c::AF_UNIX, c::SOCK_DGRAM).map(::FromInner::from_inner)
+    }
+
+    /// Binds this socket to the specified address.
+    ///
+    /// This function directly corresponds to the bind(2) function.
+    pub fn bind(&self, addr: &UnixSocketAddr) -> io::Result<UnixDatagram> {
+        try!(self.with_socket(|sock| bind(sock, addr)));
+        self.to_unix_datagram()
+    }
+
+    /// Connects this socket to the specified address, leaving the local half
+    /// unbound.
+    ///
+    /// This function directly corresponds to the connect(2) function.
+    pub fn connect(&self, addr: &UnixSocketAddr) -> io::Result<UnixDatagram> {
+        try!(self.with_socket(|sock| connect(sock, addr)));
+        self.to_unix_datagram()
+    }
+
+    /// Converts this builder into a `UnixDatagram`
+    ///
+    /// This function will consume the internal socket and return it re-wrapped
+    /// as a `UnixDatagram`. An error will be returned if the internal socket
+    /// has already been consumed from a successful call to `bind`, `connect`,
+    /// etc.
+    pub fn to_unix_datagram(&self) -> io::Result<UnixDatagram> {
+        self.socket.borrow_mut().take().map(|s| s.into_inner().into_unix_datagram())
+            .ok_or(error::consumed())
+    }
+
+    /// Returns the address of the local half of this socket.
+    pub fn local_addr(&self) -> io::Result<UnixSocketAddr> {
+        match *self.socket.borrow() {
+            Some(ref s) => getsockname(s),
+            None => Err(error::finished()),
+        }
+    }
+
+    /// Returns the address of the remote half of this socket.
+    ///
+    /// This is mostly useful for connected sockets adopted through
+    /// `FromRawFd`. An error will be returned if the socket is not connected
+    /// or has already been consumed.
+    pub fn peer_addr(&self) -> io::Result<UnixSocketAddr> {
+        match *self.socket.borrow() {
+            Some(ref s) => getpeername(s),
+            None => Err(error::finished()),
+        }
+    }
+
+    fn with_socket<F>(&self, f: F) -> io::Result<()>
+        where F: FnOnce(&Socket) -> io::Result<()>
+    {
+        match *self.socket.borrow() {
+            Some(ref s) => f(s),
+            None => Err(error::finished()),
+        }
+    }
+}
+
+macro_rules! unix_builder_opts {
+    ($($t:ident)*) => ($(
+        impl $t {
+            /// Sets the value of the `SO_RCVBUF` option on this socket.
+            ///
+            /// Changes the size of the operating system's receive buffer
+            /// associated with the socket.
+            pub fn recv_buffer_size(&self, size: usize) -> io::Result<&Self> {
+                ext::set_opt(try!(self.sock()), c::SOL_SOCKET, c::SO_RCVBUF,
+                             size as c_int).map(|()| self)
+            }
+
+            /// Gets the value of the `SO_RCVBUF` option on this socket.
+            pub fn get_recv_buffer_size(&self) -> io::Result<usize> {
+                ext::get_opt(try!(self.sock()), c::SOL_SOCKET, c::SO_RCVBUF)
+                    .map(ext::int2usize)
+            }
+
+            /// Sets the value of the `SO_SNDBUF` option on this socket.
+            ///
+            /// Changes the size of the operating system's send buffer
+            /// associated with the socket.
+            pub fn send_buffer_size(&self, size: usize) -> io::Result<&Self> {
+                ext::set_opt(try!(self.sock()), c::SOL_SOCKET, c::SO_SNDBUF,
+                             size as c_int).map(|()| self)
+            }
+
+            /// Gets the value of the `SO_SNDBUF` option on this socket.
+            pub fn get_send_buffer_size(&self) -> io::Result<usize> {
+                ext::get_opt(try!(self.sock()), c::SOL_SOCKET, c::SO_SNDBUF)
+                    .map(ext::int2usize)
+            }
+
+            /// Sets the `SO_RCVTIMEO` option for this socket.
+            ///
+            /// A value of `None` means that reads will block indefinitely.
+            pub fn read_timeout(&self, dur: Option<Duration>) -> io::Result<&Self> {
+                ext::set_opt(try!(self.sock()), c::SOL_SOCKET, c::SO_RCVTIMEO,
+                             ext::ms2timeout(dur.map(ext::dur2ms)))
+                    .map(|()| self)
+            }
+
+            /// Gets the value of the `SO_RCVTIMEO` option for this socket.
+            pub fn get_read_timeout(&self) -> io::Result<Option<Duration>> {
+                ext::get_opt(try!(self.sock()), c::SOL_SOCKET, c::SO_RCVTIMEO)
+                    .map(|t| ext::timeout2ms(t).map(ext::ms2dur))
+            }
+
+            /// Sets the `SO_SNDTIMEO` option for this socket.
+            ///
+            /// A value of `None` means that writes will block indefinitely.
+            pub fn write_timeout(&self, dur: Option<Duration>) -> io::Result<&Self> {
+                ext::set_opt(try!(self.sock()), c::SOL_SOCKET, c::SO_SNDTIMEO,
+                             ext::ms2timeout(dur.map(ext::dur2ms)))
+                    .map(|()| self)
+            }
+
+            /// Gets the value of the `SO_SNDTIMEO` option for this socket.
+            pub fn get_write_timeout(&self) -> io::Result<Option<Duration>> {
+                ext::get_opt(try!(self.sock()), c::SOL_SOCKET, c::SO_SNDTIMEO)
+                    .map(|t| ext::timeout2ms(t).map(ext::ms2dur))
+            }
+
+            /// Get the value of the `SO_ERROR` option on this socket.
+            ///
+            /// This will retrieve the stored error in the underlying socket,
+            /// clearing the field in the process. This can be useful for
+            /// checking errors between calls.
+            pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+                ext::get_opt(try!(self.sock()), c::SOL_SOCKET, c::SO_ERROR)
+                    .map(ext::int2err)
+            }
+
+            /// Returns the raw socket for setting options on it, or an error
+            /// if the socket has been taken out of the builder.
+            fn sock(&self) -> io::Result<ext::Socket> {
+                match *self.socket.borrow() {
+                    Some(ref s) => Ok(s.as_inner().raw()),
+                    None => Err(error::finished()),
+                }
+            }
+        }
+
+        impl fmt::Debug for $t {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match *self.socket.borrow() {
+                    Some(ref s) => write!(f, "{} {{ socket: {:?} }}",
+                                          stringify!($t), s),
+                    None => write!(f, "{} {{ socket: (consumed) }}",
+                                   stringify!($t)),
+                }
+            }
+        }
+
+        impl ::AsInner for $t {
+            type Inner = RefCell<Option<Socket>>;
+            fn as_inner(&self) -> &RefCell<Option<Socket>> { &self.socket }
+        }
+
+        impl ::FromInner for $t {
+            type Inner = Socket;
+            fn from_inner(sock: Socket) -> $t {
+                $t { socket: RefCell::new(Some(sock)) }
+            }
+        }
+    )*)
+}
+
+unix_builder_opts! { UnixStreamBuilder UnixDatagramBuilder }
@@ -1,9 +1,10 @@
 //! Unix-specific extensions to the `std::net` types.
 
 use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs, UdpSocket};
 use sys::c::{self, c_int};
 
-use {TcpBuilder, UdpBuilder};
+use {TcpBuilder, UdpBuilder, SocketBuilder, SocketOptions};
 use ext::{self, AsSock};
 
 /// Unix-specific extensions for the `TcpBuilder` type in this library.
@@ -21,8 +22,8 @@ pub trait UnixTcpBuilderExt {
 
 impl UnixTcpBuilderExt for TcpBuilder {
     fn reuse_port(&self, reuse: bool) -> io::Result<&Self> {
-        ext::set_opt(self.as_sock(), c::SOL_SOCKET, c::SO_REUSEPORT,
-                    reuse as c_int).map(|()| self)
+        self.set_opt(c::SOL_SOCKET, c::SO_REUSEPORT, reuse as c_int)
+            .map(|()| self)
     }
 
     fn get_reuse_port(&self) -> io::Result<bool> {
@@ -55,3 +56,339 @@ impl UnixUdpBuilderExt for UdpBuilder {
             .map(ext::int2bool)
     }
 }
+
+/// Unix-specific extensions for the `SocketBuilder` type in this library.
+pub trait UnixSocketBuilderExt {
+    /// Set value for the `SO_REUSEPORT` option on this socket.
+    ///
+    /// This is the same as [`UnixTcpBuilderExt::reuse_port`][other].
+    ///
+    /// [other]: trait.UnixTcpBuilderExt.html#tymethod.reuse_port
+    fn reuse_port(&self, reuse: bool) -> io::Result<&Self>;
+
+    /// Check the value of the `SO_REUSEPORT` option on this socket.
+    fn get_reuse_port(&self) -> io::Result<bool>;
+}
+
+impl UnixSocketBuilderExt for SocketBuilder {
+    fn reuse_port(&self, reuse: bool) -> io::Result<&Self> {
+        ext::set_opt(try!(self.sock()), c::SOL_SOCKET, c::SO_REUSEPORT,
+                    reuse as c_int).map(|()| self)
+    }
+
+    fn get_reuse_port(&self) -> io::Result<bool> {
+        ext::get_opt(try!(self.sock()), c::SOL_SOCKET, c::SO_REUSEPORT)
+            .map(ext::int2bool)
+    }
+}
+
+/// Creates groups of identically configured sockets bound to the same
+/// address with `SO_REUSEPORT`, for example one listener per thread.
+///
+/// Every socket is created from the same [`SocketOptions`][opts] template,
+/// with `reuse_port` always turned on, so the options of the sockets cannot
+/// drift apart and cause later binds to fail. If the address has port 0 the
+/// first socket picks a port and the remaining sockets are bound to it.
+///
+/// If creating, configuring or binding any socket fails the sockets created
+/// so far are closed and the error is returned.
+///
+/// [opts]: ../struct.SocketOptions.html
+///
+/// # Examples
+///
+/// ```no_run
+/// use net2::SocketOptions;
+/// use net2::unix::ReusePortGroup;
+///
+/// let listeners = ReusePortGroup::new(4)
+///     .options(SocketOptions { nodelay: Some(true), ..SocketOptions::new() })
+///     .listen("0.0.0.0:8080")
+///     .unwrap();
+/// for listener in listeners {
+///     std::thread::spawn(move || {
+///         for stream in listener.incoming() {
+///             drop(stream);
+///         }
+///     });
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReusePortGroup {
+    count: usize,
+    backlog: i32,
+    options: SocketOptions,
+    #[cfg(target_os = "linux")]
+    program: Option<ReusePortProgram>,
+}
+
+impl ReusePortGroup {
+    /// Creates a new group of `count` sockets with default options and a
+    /// listen backlog of 128.
+    ///
+    /// A `count` of 0 makes creating the group fail with an error of kind
+    /// `InvalidInput`.
+    pub fn new(count: usize) -> ReusePortGroup {
+        ReusePortGroup {
+            count,
+            backlog: 128,
+            options: SocketOptions::new(),
+            #[cfg(target_os = "linux")]
+            program: None,
+        }
+    }
+
+    /// Sets the options applied to every socket before it is bound.
+    ///
+    /// The `reuse_port` field is ignored as `SO_REUSEPORT` is always set.
+    pub fn options(&mut self, options: SocketOptions) -> &mut ReusePortGroup {
+        self.options = options;
+        self
+    }
+
+    /// Sets the backlog passed to `listen` for every TCP listener.
+    pub fn backlog(&mut self, backlog: i32) -> &mut ReusePortGroup {
+        self.backlog = backlog;
+        self
+    }
+
+    /// Sets a program steering incoming connections or datagrams between the
+    /// sockets of the group.
+    ///
+    /// The program is attached once every socket has been bound.
+    #[cfg(target_os = "linux")]
+    pub fn program(&mut self, program: ReusePortProgram)
+                   -> &mut ReusePortGroup {
+        self.program = Some(program);
+        self
+    }
+
+    /// Creates the group as TCP listeners, all bound to `addr`.
+    ///
+    /// Only the first address `addr` resolves to is used.
+    pub fn listen<A: ToSocketAddrs>(&self, addr: A)
+                                    -> io::Result<Vec<TcpListener>> {
+        try!(self.check_count());
+        let mut addr = try!(first_addr(addr));
+        let options = self.template();
+        let mut ret = Vec::with_capacity(self.count);
+        for _ in 0..self.count {
+            let b = try!(match addr {
+                SocketAddr::V4(..) => TcpBuilder::new_v4(),
+                SocketAddr::V6(..) => TcpBuilder::new_v6(),
+            });
+            try!(options.apply_to(&b));
+            try!(b.bind(addr));
+            let listener = try!(b.listen(self.backlog));
+            addr = try!(listener.local_addr());
+            ret.push(listener);
+        }
+        try!(self.attach(ret.first()));
+        Ok(ret)
+    }
+
+    /// Creates the group as UDP sockets, all bound to `addr`.
+    ///
+    /// Only the first address `addr` resolves to is used.
+    pub fn bind_udp<A: ToSocketAddrs>(&self, addr: A)
+                                      -> io::Result<Vec<UdpSocket>> {
+        try!(self.check_count());
+        let mut addr = try!(first_addr(addr));
+        let options = self.template();
+        let mut ret = Vec::with_capacity(self.count);
+        for _ in 0..self.count {
+            let b = try!(match addr {
+                SocketAddr::V4(..) => UdpBuilder::new_v4(),
+                SocketAddr::V6(..) => UdpBuilder::new_v6(),
+            });
+            try!(options.apply_to(&b));
+            let socket = try!(b.bind(addr));
+            addr = try!(socket.local_addr());
+            ret.push(socket);
+        }
+        try!(self.attach(ret.first()));
+        Ok(ret)
+    }
+
+    fn check_count(&self) -> io::Result<()> {
+        if self.count == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "a reuse port group needs at least one \
+                                       socket"))
+        }
+        Ok(())
+    }
+
+    fn template(&self) -> SocketOptions {
+        SocketOptions { reuse_port: Some(true), ..self.options.clone() }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn attach<S: AsSock>(&self, sock: Option<&S>) -> io::Result<()> {
+        match (&self.program, sock) {
+            (Some(program), Some(sock)) => program.attach(sock),
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn attach<S: AsSock>(&self, _sock: Option<&S>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn first_addr<A: ToSocketAddrs>(addr: A) -> io::Result<SocketAddr> {
+    match try!(addr.to_socket_addrs()).next() {
+        Some(addr) => Ok(addr),
+        None => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                   "no socket addresses resolved")),
+    }
+}
+
+/// A classic BPF program selecting which socket of a `SO_REUSEPORT` group
+/// receives a new connection or datagram, attached with
+/// `SO_ATTACH_REUSEPORT_CBPF`.
+///
+/// The program returns the index of the socket in the group, sockets being
+/// numbered in the order they were bound. If the index is out of range the
+/// kernel falls back to its usual hashing.
+///
+/// # Examples
+///
+/// ```no_run
+/// use net2::unix::{ReusePortGroup, ReusePortProgram};
+///
+/// // One socket per CPU, each receiving the datagrams handled by its CPU.
+/// let sockets = ReusePortGroup::new(4)
+///     .program(ReusePortProgram::cpu().modulo(4))
+///     .bind_udp("0.0.0.0:5353")
+///     .unwrap();
+/// # drop(sockets);
+/// ```
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReusePortProgram {
+    source: Source,
+    modulo: Option<u32>,
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Cpu,
+    Hash,
+    Index(u32),
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SockFilter {
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32,
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SockFprog {
+    len: u16,
+    filter: *const SockFilter,
+}
+
+#[cfg(target_os = "linux")]
+impl ReusePortProgram {
+    /// A program selecting the socket by the id of the CPU which received
+    /// the packet.
+    pub fn cpu() -> ReusePortProgram {
+        ReusePortProgram { source: Source::Cpu, modulo: None }
+    }
+
+    /// A program selecting the socket by the hash the kernel or network card
+    /// computed for the flow.
+    pub fn hash() -> ReusePortProgram {
+        ReusePortProgram { source: Source::Hash, modulo: None }
+    }
+
+    /// A program always selecting the socket at `index`.
+    pub fn index(index: u32) -> ReusePortProgram {
+        ReusePortProgram { source: Source::Index(index), modulo: None }
+    }
+
+    /// Reduces the selected index modulo `n`, usually the number of sockets
+    /// in the group.
+    ///
+    /// The kernel rejects the program if `n` is 0.
+    pub fn modulo(mut self, n: u32) -> ReusePortProgram {
+        self.modulo = Some(n);
+        self
+    }
+
+    /// Attaches this program to the `SO_REUSEPORT` group of `sock`,
+    /// replacing any program already attached.
+    pub fn attach<S: AsSock + ?Sized>(&self, sock: &S) -> io::Result<()> {
+        // Opcodes from linux/filter.h
+        const BPF_LD_W_ABS: u16 = 0x20; // BPF_LD | BPF_W | BPF_ABS
+        const BPF_LD_IMM: u16 = 0x00; // BPF_LD | BPF_W | BPF_IMM
+        const BPF_ALU_MOD_K: u16 = 0x94; // BPF_ALU | BPF_MOD | BPF_K
+        const BPF_RET_A: u16 = 0x16; // BPF_RET | BPF_A
+        const SKF_AD_OFF: i32 = -0x1000;
+        const SKF_AD_RXHASH: i32 = 32;
+        const SKF_AD_CPU: i32 = 36;
+
+        let op = |code, k| SockFilter { code, jt: 0, jf: 0, k };
+        let mut filter = Vec::new();
+        filter.push(match self.source {
+            Source::Cpu => op(BPF_LD_W_ABS, (SKF_AD_OFF + SKF_AD_CPU) as u32),
+            Source::Hash => op(BPF_LD_W_ABS, (SKF_AD_OFF + SKF_AD_RXHASH) as u32),
+            Source::Index(i) => op(BPF_LD_IMM, i),
+        });
+        if let Some(n) = self.modulo {
+            filter.push(op(BPF_ALU_MOD_K, n));
+        }
+        filter.push(op(BPF_RET_A, 0));
+
+        let prog = SockFprog {
+            len: filter.len() as u16,
+            filter: filter.as_ptr(),
+        };
+        ext::set_opt(sock.as_sock(), c::SOL_SOCKET,
+                     c::SO_ATTACH_REUSEPORT_CBPF, prog)
+    }
+
+    /// Detaches the program attached to the `SO_REUSEPORT` group of `sock`,
+    /// returning to the kernel's usual hashing.
+    ///
+    /// This requires Linux 5.3 or later.
+    pub fn detach<S: AsSock + ?Sized>(sock: &S) -> io::Result<()> {
+        ext::set_opt(sock.as_sock(), c::SOL_SOCKET, c::SO_DETACH_REUSEPORT_BPF,
+                     0 as c_int)
+    }
+}
+
+/// Returns the TCP congestion control algorithms available on this host,
+/// read from `/proc/sys/net/ipv4/tcp_available_congestion_control`.
+///
+/// Only privileged processes may select an algorithm which is available but
+/// not allowed.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn available_congestion_control() -> io::Result<Vec<String>> {
+    read_congestion_list("tcp_available_congestion_control")
+}
+
+/// Returns the TCP congestion control algorithms any process may select on
+/// this host, read from `/proc/sys/net/ipv4/tcp_allowed_congestion_control`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn allowed_congestion_control() -> io::Result<Vec<String>> {
+    read_congestion_list("tcp_allowed_congestion_control")
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read_congestion_list(name: &str) -> io::Result<Vec<String>> {
+    use std::fs;
+
+    let list = try!(fs::read_to_string(format!("/proc/sys/net/ipv4/{}", name)));
+    Ok(list.split_whitespace().map(|s| s.to_string()).collect())
+}
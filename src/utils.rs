@@ -36,16 +36,19 @@ macro_rules! one {
 one! { i8 i16 i32 i64 isize u8 u16 u32 u64 usize }
 
 
+#[cfg(windows)]
 #[doc(hidden)]
 pub trait Zero {
     fn zero() -> Self;
 }
 
+#[cfg(windows)]
 macro_rules! zero {
     ($($t:ident)*) => ($(
         impl Zero for $t { fn zero() -> $t { 0 } }
     )*)
 }
 
+#[cfg(windows)]
 zero! { i8 i16 i32 i64 isize u8 u16 u32 u64 usize }
 
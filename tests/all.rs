@@ -1,4 +1,9 @@
+// Some tests pass addresses by reference to `ToSocketAddrs` parameters
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 extern crate net2;
+#[cfg(unix)] extern crate libc;
+#[cfg(feature = "serde")] extern crate serde_json;
 
 use std::net::{TcpStream, IpAddr, Ipv4Addr, Ipv6Addr};
 use std::io::prelude::*;
@@ -43,3 +48,1169 @@ fn smoke_build_listener_v6() {
     let addr = t!(b.local_addr());
     assert_eq!(addr.ip(), IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1)));
 }
+
+#[cfg(unix)]
+#[test]
+fn smoke_build_unix_listener() {
+    use std::os::unix::net::UnixStream;
+    use net2::{UnixSocketAddr, UnixStreamBuilder};
+
+    let path = std::env::temp_dir()
+        .join(format!("net2-smoke-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let addr = t!(UnixSocketAddr::from_pathname(&path));
+
+    let b = t!(UnixStreamBuilder::new());
+    t!(b.recv_buffer_size(8192));
+    t!(b.bind(&addr));
+    assert_eq!(t!(b.local_addr()).as_pathname(), Some(path.as_path()));
+    let listener = t!(b.listen(10));
+
+    let t = thread::spawn(move || {
+        let mut s = t!(listener.accept()).0;
+        let mut b = [0; 4];
+        t!(s.read(&mut b));
+        assert_eq!(b, [1, 2, 3, 0]);
+    });
+
+    let mut stream = t!(UnixStream::connect(&path));
+    t!(stream.write(&[1, 2, 3]));
+    t.join().unwrap();
+    t!(std::fs::remove_file(&path));
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn smoke_build_unix_abstract() {
+    use net2::{UnixDatagramBuilder, UnixSocketAddr, UnixStreamBuilder};
+
+    let name = format!("net2-abstract-{}", std::process::id());
+    let addr = t!(UnixSocketAddr::from_abstract_name(name.as_bytes()));
+    let b = t!(UnixStreamBuilder::new());
+    t!(b.bind(&addr));
+    assert_eq!(t!(b.local_addr()).as_abstract_name(), Some(name.as_bytes()));
+    let listener = t!(b.listen(10));
+
+    let b = t!(UnixStreamBuilder::new());
+    let mut stream = t!(b.connect(&addr));
+    assert!(b.recv_buffer_size(4096).is_err());
+    assert!(b.take_error().is_err());
+    let mut s = t!(listener.accept()).0;
+    t!(stream.write(&[4, 5]));
+    let mut buf = [0; 2];
+    t!(s.read(&mut buf));
+    assert_eq!(buf, [4, 5]);
+
+    // Binding to an unnamed address autobinds into the abstract namespace
+    let b = t!(UnixDatagramBuilder::new());
+    t!(b.write_timeout(Some(std::time::Duration::from_secs(1))));
+    assert!(t!(b.local_addr()).is_unnamed());
+    let sock = t!(b.bind(&UnixSocketAddr::unnamed()));
+    let local = t!(sock.local_addr());
+    assert!(!local.is_unnamed());
+    assert!(local.as_pathname().is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn smoke_build_generic_socket() {
+    use std::net::UdpSocket;
+    use net2::{SocketBuilder, SocketType};
+
+    let b = t!(SocketBuilder::new(libc::AF_INET, SocketType::Dgram,
+                                  libc::IPPROTO_UDP));
+    t!(b.reuse_address(true));
+    assert!(t!(b.get_reuse_address()));
+    t!(b.bind("127.0.0.1:0"));
+    let addr = t!(b.local_addr());
+    let server = t!(b.to_udp_socket());
+    assert!(b.to_udp_socket().is_err());
+    assert!(b.reuse_address(true).is_err());
+    assert_eq!(std::os::unix::io::AsRawFd::as_raw_fd(&b), -1);
+
+    let client = t!(UdpSocket::bind("127.0.0.1:0"));
+    t!(client.send_to(&[1, 2, 3], addr));
+    let mut buf = [0; 4];
+    assert_eq!(t!(server.recv(&mut buf)), 3);
+    assert_eq!(buf, [1, 2, 3, 0]);
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn smoke_build_generic_unix_socket() {
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+    use std::os::unix::net::UnixStream;
+    use net2::{SocketBuilder, SocketType, UnixSocketAddr};
+
+    let name = format!("net2-seqpacket-{}", std::process::id());
+    let addr = t!(UnixSocketAddr::from_abstract_name(name.as_bytes()));
+    let b = t!(SocketBuilder::new(libc::AF_UNIX, SocketType::SeqPacket, 0));
+    t!(b.bind_unix(&addr));
+    assert_eq!(t!(b.unix_local_addr()).as_abstract_name(),
+               Some(name.as_bytes()));
+    assert!(b.local_addr().is_err());
+    t!(b.listen(1));
+    let listener = t!(b.to_unix_listener());
+
+    let b = t!(SocketBuilder::new(libc::AF_UNIX, SocketType::SeqPacket, 0));
+    t!(b.connect_unix(&addr));
+    assert_eq!(t!(b.unix_peer_addr()).as_abstract_name(),
+               Some(name.as_bytes()));
+    let mut client = unsafe { UnixStream::from_raw_fd(b.into_raw_fd()) };
+    let mut server = t!(listener.accept()).0;
+    t!(client.write(&[1, 2, 3]));
+    t!(client.write(&[4]));
+    let mut buf = [0; 4];
+    assert_eq!(t!(server.read(&mut buf)), 3);
+    assert_eq!(t!(server.read(&mut buf)), 1);
+
+    let b = t!(SocketBuilder::new(libc::AF_UNIX, SocketType::Dgram, 0));
+    t!(b.bind_unix(&UnixSocketAddr::unnamed()));
+    let sock = t!(b.to_unix_datagram());
+    assert!(!t!(sock.local_addr()).is_unnamed());
+    assert!(b.unix_local_addr().is_err());
+}
+
+#[test]
+fn connect_timeout() {
+    use std::io::ErrorKind;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    let listener = t!(TcpListener::bind("127.0.0.1:0"));
+    let addr = t!(listener.local_addr());
+
+    let b = t!(TcpBuilder::new_v4());
+    let stream = t!(b.connect_timeout(addr, Duration::from_secs(5)));
+    assert_eq!(t!(stream.peer_addr()), addr);
+    assert!(b.connect_timeout(addr, Duration::from_secs(5)).is_err());
+
+    // The stream is handed back in blocking mode
+    t!(stream.set_read_timeout(Some(Duration::from_millis(10))));
+    let mut buf = [0; 1];
+    let err = (&stream).read(&mut buf).unwrap_err();
+    assert!(err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut);
+
+    // Refused connections are reported through SO_ERROR
+    drop(listener);
+    let b = t!(TcpBuilder::new_v4());
+    let err = b.connect_timeout(addr, Duration::from_secs(5)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+}
+
+#[test]
+fn happy_eyeballs_falls_back_across_families() {
+    use std::net::{SocketAddr, TcpListener};
+    use std::time::Duration;
+    use net2::{HappyEyeballs, TcpStreamExt};
+
+    let listener = t!(TcpListener::bind("127.0.0.1:0"));
+    let v4 = t!(listener.local_addr());
+    let closed = t!(TcpListener::bind("[::1]:0"));
+    let v6 = t!(closed.local_addr());
+    drop(closed);
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let addrs: [SocketAddr; 2] = [v6, v4];
+    let mut connector = HappyEyeballs::new();
+    connector.attempt_delay(Duration::from_millis(50))
+             .timeout(Duration::from_secs(5))
+             .configure(|b| b.ttl(42).map(|_| ()));
+    assert_send_sync(&connector);
+    let stream = t!(connector.connect(&addrs[..]));
+    assert_eq!(t!(stream.peer_addr()), v4);
+    assert_eq!(t!(TcpStreamExt::ttl(&stream)), 42);
+}
+
+#[test]
+fn connect_retries_on_fresh_socket() {
+    use std::net::{SocketAddr, TcpListener};
+    use net2::TcpStreamExt;
+
+    let listener = t!(TcpListener::bind("127.0.0.1:0"));
+    let open = t!(listener.local_addr());
+    let closed = {
+        let l = t!(TcpListener::bind("127.0.0.1:0"));
+        t!(l.local_addr())
+    };
+    let addrs: [SocketAddr; 2] = [closed, open];
+
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.ttl(42));
+    let stream = t!(b.connect(&addrs[..]));
+    assert_eq!(t!(stream.peer_addr()), open);
+    assert_eq!(t!(TcpStreamExt::ttl(&stream)), 42);
+
+    let stream = t!(t!(TcpBuilder::new_v4()).to_tcp_stream());
+    t!(TcpStreamExt::set_nodelay(&stream, true));
+    t!(TcpStreamExt::connect(&stream, &addrs[..]));
+    assert_eq!(t!(stream.peer_addr()), open);
+    assert!(t!(TcpStreamExt::nodelay(&stream)));
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn connect_retries_keep_linux_options() {
+    use std::net::{SocketAddr, TcpListener};
+    use std::time::Duration;
+    use net2::{Dscp, TcpKeepalive, TcpStreamExt};
+
+    let listener = t!(TcpListener::bind("127.0.0.1:0"));
+    let open = t!(listener.local_addr());
+    let closed = {
+        let l = t!(TcpListener::bind("127.0.0.1:0"));
+        t!(l.local_addr())
+    };
+    let addrs: [SocketAddr; 2] = [closed, open];
+    let keepalive = TcpKeepalive {
+        idle: Some(Duration::from_secs(60)),
+        interval: Some(Duration::from_secs(7)),
+        retries: Some(4),
+    };
+
+    let stream = t!(t!(TcpBuilder::new_v4()).to_tcp_stream());
+    t!(stream.set_tcp_keepalive(Some(keepalive)));
+    t!(stream.set_user_timeout(Some(Duration::from_secs(9))));
+    t!(stream.set_congestion_control("reno"));
+    t!(stream.set_dscp(Dscp::new(10).unwrap()));
+    t!(TcpStreamExt::set_recv_buffer_size(&stream, 65536));
+    let recv_buffer_size = t!(TcpStreamExt::recv_buffer_size(&stream));
+    t!(stream.set_read_timeout(Some(Duration::from_secs(3))));
+    t!(TcpStreamExt::connect(&stream, &addrs[..]));
+    assert_eq!(t!(stream.peer_addr()), open);
+    assert_eq!(t!(stream.tcp_keepalive()), Some(keepalive));
+    assert_eq!(t!(stream.user_timeout()), Some(Duration::from_secs(9)));
+    assert_eq!(t!(stream.congestion_control()), "reno");
+    assert_eq!(t!(stream.dscp()), Dscp::new(10).unwrap());
+    assert_eq!(t!(TcpStreamExt::recv_buffer_size(&stream)), recv_buffer_size);
+    assert_eq!(t!(stream.read_timeout()), Some(Duration::from_secs(3)));
+}
+
+#[test]
+fn connect_error_lists_every_attempt() {
+    use std::io::ErrorKind;
+    use std::net::{SocketAddr, TcpListener};
+    use net2::ConnectError;
+
+    let closed = || {
+        let l = t!(TcpListener::bind("127.0.0.1:0"));
+        t!(l.local_addr())
+    };
+    let addrs: [SocketAddr; 2] = [closed(), closed()];
+
+    let err = t!(TcpBuilder::new_v4()).connect(&addrs[..]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+    let err = ConnectError::from_io(&err).unwrap();
+    assert_eq!(err.attempts().len(), 2);
+    for (attempt, addr) in err.attempts().iter().zip(addrs.iter()) {
+        assert_eq!(attempt.addr(), *addr);
+        assert_eq!(attempt.error().kind(), ErrorKind::ConnectionRefused);
+    }
+}
+
+#[test]
+fn error_records_operation() {
+    use std::io::ErrorKind;
+    use std::net::TcpListener;
+    use net2::{Error, Operation};
+
+    let listener = t!(TcpListener::bind("127.0.0.1:0"));
+    let addr = t!(listener.local_addr());
+    let err = t!(TcpBuilder::new_v4()).bind(addr).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AddrInUse);
+    let inner = Error::from_io(&err).unwrap();
+    assert_eq!(inner.operation(), Operation::Bind(addr));
+    assert!(inner.raw_os_error().is_some());
+    assert!(err.to_string().starts_with(&format!("bind({}): ", addr)));
+
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.to_tcp_stream());
+    let err = b.ttl(1).unwrap_err();
+    assert_eq!(Error::from_io(&err).unwrap().operation(), Operation::Consumed);
+}
+
+#[cfg(unix)]
+#[test]
+fn error_names_socket_option() {
+    use net2::UdpSocketExt;
+
+    let socket = t!(std::net::UdpSocket::bind("127.0.0.1:0"));
+    let err = UdpSocketExt::set_multicast_ttl_v4(&socket, 1000).unwrap_err();
+    assert_eq!(err.raw_os_error(), None);
+    assert_eq!(net2::Error::from_io(&err).unwrap().raw_os_error(),
+               Some(libc::EINVAL));
+    assert!(err.to_string()
+               .starts_with("setsockopt(IPPROTO_IP, IP_MULTICAST_TTL): "));
+}
+
+#[test]
+fn udp_builder_connect() {
+    use std::net::UdpSocket;
+    use net2::UdpBuilder;
+
+    let server = t!(UdpSocket::bind("127.0.0.1:0"));
+    let addr = t!(server.local_addr());
+
+    let client = t!(t!(UdpBuilder::new_v4()).connect(addr));
+    assert_eq!(t!(client.peer_addr()), addr);
+    assert_eq!(t!(client.local_addr()).ip(), addr.ip());
+    t!(client.send(b"hi"));
+    let mut buf = [0; 2];
+    let (n, from) = t!(server.recv_from(&mut buf));
+    assert_eq!(&buf[..n], b"hi");
+    assert_eq!(from, t!(client.local_addr()));
+
+    let client = t!(t!(UdpBuilder::new_v4()).bind_and_connect("127.0.0.1:0", addr));
+    assert_eq!(t!(client.peer_addr()), addr);
+}
+
+#[test]
+fn owned_builders() {
+    use std::net::{TcpStream, UdpSocket};
+    use net2::{OwnedTcpBuilder, OwnedUdpBuilder, TcpStreamExt};
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let b = t!(t!(OwnedTcpBuilder::new_v4()).reuse_address(true));
+    assert_send_sync(&b);
+    let b = t!(b.bind("127.0.0.1:0"));
+    let addr = t!(b.local_addr());
+    let listener = t!(b.listen(16));
+    assert_eq!(t!(listener.local_addr()), addr);
+
+    let b = t!(t!(OwnedTcpBuilder::new_v4()).ttl(33));
+    let stream = t!(TcpBuilder::from(b).connect(addr));
+    assert_eq!(t!(TcpStreamExt::ttl(&stream)), 33);
+
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.bind("127.0.0.1:0"));
+    assert!(t!(TcpBuilder::new_v4()).into_owned().is_ok());
+    let stream: TcpStream = t!(t!(b.into_owned_bound()).connect(addr));
+    drop(stream);
+
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.to_tcp_stream());
+    assert_eq!(format!("{:?}", b), "TcpBuilder { socket: (consumed) }");
+
+    let server = t!(UdpSocket::bind("127.0.0.1:0"));
+    let b = t!(t!(OwnedUdpBuilder::new_v4()).ttl(5));
+    assert_send_sync(&b);
+    let client = t!(b.connect(t!(server.local_addr())));
+    assert_eq!(t!(client.ttl()), 5);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn owned_builders_linux_options() {
+    use std::net::UdpSocket;
+    use std::time::Duration;
+    use net2::{Dscp, OwnedTcpBuilder, OwnedUdpBuilder, TcpKeepalive,
+               TcpStreamExt, UdpSocketExt};
+
+    let listener = t!(t!(t!(TcpBuilder::new_v4()).bind("127.0.0.1:0")).listen(1));
+    let keepalive = TcpKeepalive {
+        retries: Some(4),
+        ..TcpKeepalive::new()
+    };
+    let b = t!(OwnedTcpBuilder::new_v4());
+    let b = t!(b.tcp_keepalive(Some(keepalive)));
+    let b = t!(b.user_timeout(Some(Duration::from_secs(3))));
+    let b = t!(b.congestion_control("reno"));
+    let b = t!(b.maxseg(1000));
+    let b = t!(b.dscp(Dscp::new(10).unwrap()));
+    let b = t!(b.reuse_port(true));
+    let b = t!(b.freebind(true));
+    let stream = t!(b.connect(t!(listener.local_addr())));
+    assert_eq!(t!(stream.tcp_keepalive()).and_then(|k| k.retries), Some(4));
+    assert_eq!(t!(stream.user_timeout()), Some(Duration::from_secs(3)));
+    assert_eq!(t!(stream.congestion_control()), "reno");
+    assert_eq!(t!(stream.dscp()).value(), 10);
+
+    let server = t!(UdpSocket::bind("127.0.0.1:0"));
+    let b = t!(t!(OwnedUdpBuilder::new_v4()).dscp(Dscp::new(12).unwrap()));
+    let b = t!(t!(b.reuse_port(true)).freebind(true));
+    let client = t!(b.connect(t!(server.local_addr())));
+    assert_eq!(t!(client.dscp()).value(), 12);
+}
+
+#[cfg(unix)]
+#[test]
+fn builder_peer_addr() {
+    use std::net::TcpListener;
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+    use std::os::unix::net::UnixStream;
+    use net2::UnixStreamBuilder;
+
+    let listener = t!(TcpListener::bind("127.0.0.1:0"));
+    let addr = t!(listener.local_addr());
+    let stream = t!(TcpStream::connect(addr));
+    let b = unsafe { TcpBuilder::from_raw_fd(stream.into_raw_fd()) };
+    assert_eq!(t!(b.peer_addr()), addr);
+    assert!(t!(TcpBuilder::new_v4()).peer_addr().is_err());
+
+    let (a, _b) = t!(UnixStream::pair());
+    let b = unsafe { UnixStreamBuilder::from_raw_fd(a.into_raw_fd()) };
+    assert!(t!(b.peer_addr()).is_unnamed());
+}
+
+#[cfg(unix)]
+#[test]
+fn nonblocking_builders() {
+    use std::io::ErrorKind;
+    use std::os::unix::io::AsRawFd;
+
+    fn is_nonblocking<T: AsRawFd>(t: &T) -> bool {
+        let flags = unsafe { libc::fcntl(t.as_raw_fd(), libc::F_GETFL) };
+        flags & libc::O_NONBLOCK != 0
+    }
+
+    let b = t!(TcpBuilder::new_v4_nonblocking());
+    let listener = t!(t!(b.bind("127.0.0.1:0")).listen(16));
+    assert!(is_nonblocking(&listener));
+    assert_eq!(listener.accept().unwrap_err().kind(), ErrorKind::WouldBlock);
+
+    let addr = t!(listener.local_addr());
+    let stream = t!(t!(TcpBuilder::new_v4_nonblocking()).connect(addr));
+    assert!(is_nonblocking(&stream));
+    let (_peer, _) = loop {
+        match listener.accept() {
+            Ok(pair) => break pair,
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => thread::yield_now(),
+            Err(e) => panic!("accept failed: {}", e),
+        }
+    };
+}
+
+#[test]
+fn socket_options_apply() {
+    use std::net::UdpSocket;
+    use std::time::Duration;
+    use net2::{SocketOptions, TcpStreamExt, UdpSocketExt};
+
+    let opts = SocketOptions {
+        reuse_address: Some(true),
+        ttl: Some(42),
+        nodelay: Some(true),
+        read_timeout: Some(Some(Duration::from_secs(2))),
+        ..SocketOptions::default()
+    };
+
+    let b = t!(TcpBuilder::new_v4());
+    t!(opts.apply_to(&b));
+    assert!(t!(b.get_reuse_address()));
+    let listener = t!(t!(b.bind("127.0.0.1:0")).listen(1));
+    let addr = t!(listener.local_addr());
+
+    let stream = t!(TcpStream::connect(addr));
+    t!(opts.apply_to(&stream));
+    assert!(t!(TcpStreamExt::nodelay(&stream)));
+    assert_eq!(t!(TcpStreamExt::ttl(&stream)), 42);
+    assert_eq!(t!(TcpStreamExt::read_timeout(&stream)),
+               Some(Duration::from_secs(2)));
+
+    let udp_opts = SocketOptions {
+        broadcast: Some(true),
+        multicast_ttl_v4: Some(3),
+        ..SocketOptions::new()
+    };
+    let socket = t!(UdpSocket::bind("127.0.0.1:0"));
+    t!(udp_opts.apply_to(&socket));
+    assert!(t!(UdpSocketExt::broadcast(&socket)));
+    assert_eq!(t!(UdpSocketExt::multicast_ttl_v4(&socket)), 3);
+
+    let err = opts.apply_to(&t!(UdpSocket::bind("127.0.0.1:0"))).unwrap_err();
+    assert!(err.to_string().contains("TCP_NODELAY"), "{}", err);
+}
+
+#[test]
+fn socket_options_recorded_by_builder() {
+    use std::io::ErrorKind;
+    use std::net::{SocketAddr, TcpListener};
+    use std::time::Duration;
+    use net2::{SocketOptions, TcpStreamExt};
+
+    let listener = t!(TcpListener::bind("127.0.0.1:0"));
+    let open = t!(listener.local_addr());
+    let closed = {
+        let l = t!(TcpListener::bind("127.0.0.1:0"));
+        t!(l.local_addr())
+    };
+    let addrs: [SocketAddr; 2] = [closed, open];
+
+    let opts = SocketOptions {
+        nodelay: Some(true),
+        keepalive: Some(Some(Duration::from_secs(30))),
+        read_timeout: Some(Some(Duration::from_secs(2))),
+        ..SocketOptions::new()
+    };
+    let b = t!(TcpBuilder::new_v4());
+    t!(opts.apply_to(&b));
+    let stream = t!(b.connect(&addrs[..]));
+    assert_eq!(t!(stream.peer_addr()), open);
+    assert!(t!(TcpStreamExt::nodelay(&stream)));
+    assert_eq!(t!(TcpStreamExt::keepalive(&stream)),
+               Some(Duration::from_secs(30)));
+    assert_eq!(t!(TcpStreamExt::read_timeout(&stream)),
+               Some(Duration::from_secs(2)));
+
+    let opts = SocketOptions { nonblocking: Some(true), ..SocketOptions::new() };
+    let b = t!(TcpBuilder::new_v4());
+    t!(opts.apply_to(&b));
+    let mut stream = t!(b.connect(open));
+    let _accepted = t!(listener.accept());
+    let err = stream.read(&mut [0; 1]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn socket_options_linux() {
+    use std::net::TcpListener;
+    use std::time::Duration;
+    use net2::{Dscp, SocketOptions, TcpKeepalive, TcpStreamExt};
+
+    let listener = t!(TcpListener::bind("127.0.0.1:0"));
+    let opts = SocketOptions {
+        keepalive: Some(Some(Duration::from_secs(30))),
+        keepalive_interval: Some(Duration::from_secs(5)),
+        keepalive_retries: Some(4),
+        user_timeout: Some(Some(Duration::from_secs(3))),
+        congestion_control: Some("reno".to_string()),
+        notsent_lowat: Some(4096),
+        dscp: Some(Dscp::AF11),
+        ..SocketOptions::new()
+    };
+    let b = t!(TcpBuilder::new_v4());
+    t!(opts.apply_to(&b));
+    let stream = t!(b.connect(t!(listener.local_addr())));
+    assert_eq!(t!(stream.tcp_keepalive()), Some(TcpKeepalive {
+        idle: Some(Duration::from_secs(30)),
+        interval: Some(Duration::from_secs(5)),
+        retries: Some(4),
+    }));
+    assert_eq!(t!(stream.user_timeout()), Some(Duration::from_secs(3)));
+    assert_eq!(t!(stream.congestion_control()), "reno");
+    assert_eq!(t!(stream.notsent_lowat()), 4096);
+    assert_eq!(t!(stream.dscp()), Dscp::AF11);
+
+    let opts = SocketOptions { cork: Some(true), ..SocketOptions::new() };
+    t!(opts.apply_to(&stream));
+    assert!(t!(stream.cork()));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn socket_options_serde() {
+    use std::time::Duration;
+    use net2::SocketOptions;
+
+    let opts = SocketOptions {
+        nodelay: Some(true),
+        keepalive: Some(Some(Duration::from_millis(1500))),
+        linger: Some(None),
+        ..SocketOptions::new()
+    };
+    let json = t!(serde_json::to_string(&opts));
+    assert!(json.contains("\"keepalive\":1500"), "{}", json);
+    assert!(json.contains("\"linger\":false"), "{}", json);
+    assert!(!json.contains("read_timeout"), "{}", json);
+    assert_eq!(t!(serde_json::from_str::<SocketOptions>(&json)), opts);
+
+    let opts: SocketOptions =
+        t!(serde_json::from_str(r#"{"read_timeout": 250, "nodelay": true}"#));
+    assert_eq!(opts.read_timeout, Some(Some(Duration::from_millis(250))));
+    assert_eq!(opts.nodelay, Some(true));
+    assert_eq!(opts.keepalive, None);
+
+    assert!(serde_json::from_str::<SocketOptions>(r#"{"linger": true}"#)
+                .is_err());
+    assert!(serde_json::from_str::<SocketOptions>(r#"{"no_such": 1}"#)
+                .is_err());
+
+    let opts: SocketOptions = t!(serde_json::from_str(
+        r#"{"keepalive_interval": 5000, "dscp": 46, "mark": 7}"#));
+    assert_eq!(opts.keepalive_interval, Some(Duration::from_secs(5)));
+    assert_eq!(opts.dscp, Some(net2::Dscp::EF));
+    assert_eq!(opts.mark, Some(7));
+    assert_eq!(t!(serde_json::from_str::<SocketOptions>(
+        &t!(serde_json::to_string(&opts)))), opts);
+    assert!(serde_json::from_str::<SocketOptions>(r#"{"dscp": 64}"#)
+                .is_err());
+}
+
+#[test]
+fn socket_snapshot_diff() {
+    use net2::{OptionValue, SocketSnapshot, TcpStreamExt};
+
+    let listener = t!(t!(t!(TcpBuilder::new_v4()).bind("127.0.0.1:0")).listen(1));
+    let stream = t!(TcpStream::connect(t!(listener.local_addr())));
+    t!(TcpStreamExt::set_nodelay(&stream, false));
+
+    let before = SocketSnapshot::capture(&stream);
+    match before.get("TCP_NODELAY") {
+        Some(Ok(v)) => assert_eq!(*v, OptionValue::Bool(false)),
+        other => panic!("unexpected TCP_NODELAY: {:?}", other),
+    }
+    assert!(before.get("IPV6_V6ONLY").unwrap().is_err());
+    assert!(before.to_string().contains("TCP_NODELAY: false"));
+    assert!(before.diff(&SocketSnapshot::capture(&stream)).is_empty());
+
+    t!(TcpStreamExt::set_nodelay(&stream, true));
+    let after = SocketSnapshot::capture(&stream);
+    let diff = before.diff(&after);
+    assert_eq!(diff.len(), 1);
+    assert_eq!(diff[0].name(), "TCP_NODELAY");
+    assert_eq!(diff[0].to_string(), "TCP_NODELAY: false -> true");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn socket_snapshot_linux_options() {
+    use net2::{OptionValue, SocketSnapshot, TcpStreamExt};
+
+    let listener = t!(t!(t!(TcpBuilder::new_v4()).bind("127.0.0.1:0")).listen(1));
+    let stream = t!(TcpStream::connect(t!(listener.local_addr())));
+    let before = SocketSnapshot::capture(&stream);
+    match before.get("TCP_CONGESTION") {
+        Some(Ok(OptionValue::Name(Some(_)))) => {}
+        other => panic!("unexpected TCP_CONGESTION: {:?}", other),
+    }
+    match before.get("SO_BINDTODEVICE") {
+        Some(Ok(v)) => assert_eq!(*v, OptionValue::Name(None)),
+        other => panic!("unexpected SO_BINDTODEVICE: {:?}", other),
+    }
+    assert!(before.get("IP_TOS").unwrap().is_ok());
+    assert!(before.get("IP_LOCAL_PORT_RANGE").is_some());
+
+    t!(stream.set_cork(true));
+    t!(stream.set_notsent_lowat(4096));
+    let after = SocketSnapshot::capture(&stream);
+    let mut names = before.diff(&after).iter().map(|d| d.name())
+                          .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["TCP_CORK", "TCP_NOTSENT_LOWAT"]);
+}
+
+#[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+#[test]
+fn reuse_port_group() {
+    use net2::SocketOptions;
+    use net2::unix::{ReusePortGroup, UnixTcpBuilderExt};
+
+    let listeners = t!(ReusePortGroup::new(3)
+        .options(SocketOptions { nodelay: Some(true), ..SocketOptions::new() })
+        .listen("127.0.0.1:0"));
+    assert_eq!(listeners.len(), 3);
+    let addr = t!(listeners[0].local_addr());
+    assert!(addr.port() != 0);
+    for l in &listeners {
+        assert_eq!(t!(l.local_addr()), addr);
+    }
+    t!(TcpStream::connect(addr));
+
+    let sockets = t!(ReusePortGroup::new(2).bind_udp("127.0.0.1:0"));
+    assert_eq!(t!(sockets[0].local_addr()), t!(sockets[1].local_addr()));
+
+    // A socket without SO_REUSEPORT on the port makes the whole group fail.
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.reuse_port(false));
+    let taken = t!(t!(b.bind("127.0.0.1:0")).listen(1));
+    assert!(ReusePortGroup::new(2).listen(t!(taken.local_addr())).is_err());
+
+    let err = ReusePortGroup::new(0).listen("127.0.0.1:0").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    let err = ReusePortGroup::new(0).bind_udp("127.0.0.1:0").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn reuse_port_program_steers() {
+    use std::io;
+    use std::net::UdpSocket;
+    use net2::unix::{ReusePortGroup, ReusePortProgram};
+
+    let sockets = t!(ReusePortGroup::new(2)
+        .program(ReusePortProgram::index(1))
+        .bind_udp("127.0.0.1:0"));
+    let addr = t!(sockets[0].local_addr());
+    for s in &sockets {
+        t!(s.set_nonblocking(true));
+    }
+    let client = t!(UdpSocket::bind("127.0.0.1:0"));
+
+    let recv_on = |target: usize| {
+        for _ in 0..5 {
+            t!(client.send_to(b"x", addr));
+        }
+        let mut buf = [0; 1];
+        for _ in 0..5 {
+            t!(sockets[target].recv_from(&mut buf));
+        }
+        let err = sockets[1 - target].recv_from(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    };
+    recv_on(1);
+
+    t!(ReusePortProgram::index(3).modulo(2).attach(&sockets[1]));
+    recv_on(1);
+    t!(ReusePortProgram::index(0).attach(&sockets[1]));
+    recv_on(0);
+    t!(ReusePortProgram::cpu().attach(&sockets[0]));
+
+    if let Err(e) = ReusePortProgram::detach(&sockets[0]) {
+        // SO_DETACH_REUSEPORT_BPF needs Linux 5.3
+        let code = net2::Error::from_io(&e).and_then(|e| e.raw_os_error());
+        assert_eq!(code, Some(libc::ENOPROTOOPT), "{}", e);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn bind_device() {
+    use std::io;
+    use net2::UdpBuilder;
+
+    let lo = unsafe { libc::if_nametoindex(b"lo\0".as_ptr() as *const _) };
+    let b = t!(UdpBuilder::new_v4());
+    t!(b.bind_device(Some("lo")));
+    assert_eq!(t!(b.device()), Some("lo".to_string()));
+    assert_eq!(t!(b.device_index()), lo);
+    match b.bind_device(None) {
+        Ok(_) => assert_eq!(t!(b.device()), None),
+        // Changing an existing binding requires CAP_NET_RAW
+        Err(e) => {
+            let code = net2::Error::from_io(&e).and_then(|e| e.raw_os_error());
+            assert_eq!(code, Some(libc::EPERM), "{}", e);
+            assert!(e.to_string().contains("CAP_NET_RAW"), "{}", e);
+        }
+    }
+
+    let err = b.bind_device(Some("no-such-if0")).unwrap_err();
+    let code = net2::Error::from_io(&err).and_then(|e| e.raw_os_error());
+    assert_eq!(code, Some(libc::ENODEV), "{}", err);
+    assert!(err.to_string().contains("SO_BINDTODEVICE"), "{}", err);
+    assert!(err.to_string().contains("`no-such-if0`"), "{}", err);
+    assert!(!err.to_string().contains("CAP_NET_RAW"), "{}", err);
+    let err = b.bind_device(Some("an-interface-name-too-long")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.bind_device_by_index(lo));
+    assert_eq!(t!(b.device()), Some("lo".to_string()));
+    let listener = t!(t!(b.bind("127.0.0.1:0")).listen(1));
+    t!(TcpStream::connect(t!(listener.local_addr())));
+
+    for b in &[t!(UdpBuilder::new_v4()), t!(UdpBuilder::new_v6())] {
+        assert_eq!(t!(b.get_unicast_if()), 0);
+        t!(b.unicast_if(lo));
+        assert_eq!(t!(b.get_unicast_if()), lo);
+        t!(b.unicast_if(0));
+        assert_eq!(t!(b.get_unicast_if()), 0);
+    }
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.unicast_if(lo));
+    assert_eq!(t!(b.get_unicast_if()), lo);
+    let listener = t!(t!(b.bind("127.0.0.1:0")).listen(1));
+    t!(TcpStream::connect(t!(listener.local_addr())));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn transparent_proxy_options() {
+    use std::net::UdpSocket;
+    use net2::{TcpStreamExt, UdpBuilder, UdpSocketExt};
+
+    // 192.0.2.0/24 is reserved for documentation and never local
+    let b = t!(UdpBuilder::new_v4());
+    t!(b.freebind(true));
+    assert!(t!(b.get_freebind()));
+    t!(b.bind("192.0.2.1:0"));
+
+    let b = t!(UdpBuilder::new_v4());
+    t!(b.recv_orig_dst_addr(true));
+    let server = t!(b.bind("127.0.0.1:0"));
+    assert!(t!(server.recv_orig_dst_addr()));
+    let addr = t!(server.local_addr());
+    let client = t!(UdpSocket::bind("127.0.0.1:0"));
+    t!(client.send_to(b"hello", addr));
+    let mut buf = [0; 16];
+    let (n, from, dst, truncated) = t!(server.recv_from_orig_dst(&mut buf));
+    assert_eq!(&buf[..n], b"hello");
+    assert_eq!(from, t!(client.local_addr()));
+    assert_eq!(dst, Some(addr));
+    assert!(!truncated);
+
+    // IP_TRANSPARENT needs CAP_NET_ADMIN
+    let b = t!(TcpBuilder::new_v6());
+    match b.transparent(true) {
+        Ok(_) => assert!(t!(b.get_transparent())),
+        Err(e) => {
+            let code = net2::Error::from_io(&e).and_then(|e| e.raw_os_error());
+            assert_eq!(code, Some(libc::EPERM), "{}", e);
+        }
+    }
+
+    // Without a netfilter redirect there is no original destination
+    let listener = t!(t!(t!(TcpBuilder::new_v4()).bind("127.0.0.1:0")).listen(1));
+    let stream = t!(TcpStream::connect(t!(listener.local_addr())));
+    let err = stream.original_dst().unwrap_err();
+
+    // An IPv4 connection accepted on an IPv6 socket is looked up as IPv4
+    let b = t!(TcpBuilder::new_v6());
+    t!(b.only_v6(false));
+    let listener = t!(t!(b.bind("[::]:0")).listen(1));
+    let port = t!(listener.local_addr()).port();
+    let _client = t!(TcpStream::connect(("127.0.0.1", port)));
+    let (accepted, peer) = t!(listener.accept());
+    assert!(match peer.ip() {
+        IpAddr::V6(ip) => ip.to_ipv4_mapped().is_some(),
+        IpAddr::V4(..) => false,
+    });
+    let mapped_err = accepted.original_dst().unwrap_err();
+    let code = |e| net2::Error::from_io(e).and_then(|e| e.raw_os_error());
+    assert_eq!(code(&mapped_err), code(&err));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn bind_address_no_port() {
+    let listener = t!(t!(t!(TcpBuilder::new_v4()).bind("127.0.0.1:0")).listen(1));
+
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.bind_address_no_port(true));
+    assert!(t!(b.get_bind_address_no_port()));
+    match b.local_port_range((40000, 40100)) {
+        Ok(_) => assert_eq!(t!(b.get_local_port_range()), (40000, 40100)),
+        // IP_LOCAL_PORT_RANGE needs Linux 6.3
+        Err(e) => {
+            assert_eq!(e.kind(), std::io::ErrorKind::Unsupported);
+            assert!(e.to_string().contains("6.3"), "{}", e);
+            return
+        }
+    }
+    t!(b.bind("127.0.0.1:0"));
+    assert_eq!(t!(b.local_addr()).port(), 0);
+    let stream = t!(b.connect(t!(listener.local_addr())));
+    let port = t!(stream.local_addr()).port();
+    assert!((40000..=40100).contains(&port), "{}", port);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn tcp_fastopen() {
+    use net2::TcpListenerExt;
+
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.fastopen(16));
+    assert_eq!(t!(b.get_fastopen()), 16);
+    let listener = t!(t!(b.bind("127.0.0.1:0")).listen(16));
+    let addr = t!(listener.local_addr());
+
+    let key = [7; 16];
+    match listener.set_fastopen_keys(key, None) {
+        Ok(()) => assert_eq!(t!(listener.fastopen_keys()), (key, None)),
+        Err(e) => {
+            let code = net2::Error::from_io(&e).and_then(|e| e.raw_os_error());
+            assert_eq!(code, Some(libc::EPERM), "{}", e);
+        }
+    }
+
+    // Without client support in the sysctl this connects and then writes
+    for _ in 0..2 {
+        let b = t!(TcpBuilder::new_v4());
+        let (_stream, sent) = t!(b.connect_with_data(addr, b"hello"));
+        assert_eq!(sent, 5);
+        let mut buf = [0; 5];
+        t!(t!(listener.accept()).0.read_exact(&mut buf));
+        assert_eq!(&buf, b"hello");
+    }
+
+    let mut sysctl = String::new();
+    t!(t!(std::fs::File::open("/proc/sys/net/ipv4/tcp_fastopen"))
+           .read_to_string(&mut sysctl));
+    let b = t!(TcpBuilder::new_v4());
+    if t!(sysctl.trim().parse::<u32>()) & 1 == 0 {
+        let err = b.fastopen_connect(true).unwrap_err();
+        let code = net2::Error::from_io(&err).and_then(|e| e.raw_os_error());
+        assert_eq!(code, Some(libc::EOPNOTSUPP), "{}", err);
+        return
+    }
+    t!(b.fastopen_connect(true));
+    assert!(t!(b.get_fastopen_connect()));
+    let mut stream = t!(b.connect(addr));
+    t!(stream.write_all(b"world"));
+    let mut buf = [0; 5];
+    t!(t!(listener.accept()).0.read_exact(&mut buf));
+    assert_eq!(&buf, b"world");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn tcp_keepalive_params() {
+    use std::time::Duration;
+    use net2::{TcpKeepalive, TcpStreamExt};
+
+    let keepalive = TcpKeepalive {
+        idle: Some(Duration::from_secs(30)),
+        interval: Some(Duration::from_millis(5500)),
+        retries: Some(4),
+    };
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.tcp_keepalive(Some(keepalive)));
+    t!(b.user_timeout(Some(Duration::from_millis(2500))));
+    assert_eq!(t!(b.get_tcp_keepalive()), Some(TcpKeepalive {
+        interval: Some(Duration::from_secs(6)),
+        ..keepalive
+    }));
+    assert_eq!(t!(b.get_user_timeout()), Some(Duration::from_millis(2500)));
+
+    let listener = t!(t!(t!(TcpBuilder::new_v4()).bind("127.0.0.1:0")).listen(1));
+    let stream = t!(b.connect(t!(listener.local_addr())));
+    assert_eq!(t!(stream.tcp_keepalive()).and_then(|k| k.retries), Some(4));
+    t!(stream.set_tcp_keepalive(Some(TcpKeepalive {
+        retries: Some(9),
+        ..TcpKeepalive::new()
+    })));
+    assert_eq!(t!(stream.tcp_keepalive()).unwrap().retries, Some(9));
+    assert_eq!(t!(stream.tcp_keepalive()).unwrap().idle,
+               Some(Duration::from_secs(30)));
+    t!(stream.set_tcp_keepalive(None));
+    assert_eq!(t!(stream.tcp_keepalive()), None);
+    t!(stream.set_user_timeout(None));
+    assert_eq!(t!(stream.user_timeout()), None);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn tcp_info_stats() {
+    use net2::{TcpState, TcpStreamExt};
+
+    let listener = t!(t!(t!(TcpBuilder::new_v4()).bind("127.0.0.1:0")).listen(1));
+    let mut client = t!(TcpStream::connect(t!(listener.local_addr())));
+    let mut server = t!(listener.accept()).0;
+    t!(client.write_all(b"hello"));
+    let mut buf = [0; 5];
+    t!(server.read_exact(&mut buf));
+
+    let info = t!(server.tcp_info());
+    assert!(info.size() >= 104);
+    assert_eq!(info.state(), TcpState::Established);
+    assert!(info.snd_mss() > 0);
+    assert!(info.snd_cwnd() > 0);
+    assert_eq!(info.bytes_received(), Some(5));
+    assert_eq!(t!(client.tcp_info()).state(), TcpState::Established);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn congestion_control() {
+    use net2::TcpStreamExt;
+    use net2::unix::{allowed_congestion_control, available_congestion_control};
+
+    let available = t!(available_congestion_control());
+    assert!(available.iter().any(|a| a == "reno"));
+    for allowed in t!(allowed_congestion_control()) {
+        assert!(available.contains(&allowed));
+    }
+
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.congestion_control("reno"));
+    assert_eq!(t!(b.get_congestion_control()), "reno");
+    assert!(b.congestion_control("no-such-algorithm").is_err());
+    assert!(b.congestion_control("").is_err());
+
+    let listener = t!(t!(t!(TcpBuilder::new_v4()).bind("127.0.0.1:0")).listen(1));
+    let stream = t!(b.connect(t!(listener.local_addr())));
+    assert_eq!(t!(stream.congestion_control()), "reno");
+    if let Some(other) = available.iter().find(|a| *a != "reno") {
+        if stream.set_congestion_control(other).is_ok() {
+            assert_eq!(&t!(stream.congestion_control()), other);
+        }
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn tcp_write_path_options() {
+    use net2::{CorkGuard, TcpStreamExt};
+
+    let b = t!(TcpBuilder::new_v4());
+    t!(b.maxseg(1200));
+    assert_eq!(t!(b.get_maxseg()), 1200);
+
+    let listener = t!(t!(t!(TcpBuilder::new_v4()).bind("127.0.0.1:0")).listen(1));
+    let stream = t!(b.connect(t!(listener.local_addr())));
+    let mut server = t!(listener.accept()).0;
+    assert!(t!(stream.maxseg()) <= 1200);
+
+    t!(stream.set_notsent_lowat(16384));
+    assert_eq!(t!(stream.notsent_lowat()), 16384);
+    t!(stream.set_quickack(true));
+    t!(stream.quickack());
+
+    {
+        let mut corked = t!(CorkGuard::new(&stream));
+        assert!(t!(corked.cork()));
+        t!(corked.write_all(b"hello"));
+    }
+    assert!(!t!(stream.cork()));
+    let mut buf = [0; 5];
+    t!(server.read_exact(&mut buf));
+    assert_eq!(&buf, b"hello");
+
+    let corked = t!(CorkGuard::new(&stream));
+    t!(corked.uncork());
+    assert!(!t!(stream.cork()));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn packet_marking() {
+    use std::net::UdpSocket;
+    use net2::{Dscp, Ecn, TcpStreamExt, UdpBuilder, UdpSocketExt};
+
+    assert_eq!(Dscp::new(46), Some(Dscp::EF));
+    assert_eq!(Dscp::new(64), None);
+
+    let b = t!(TcpBuilder::new_v6());
+    t!(b.dscp(Dscp::AF41));
+    assert_eq!(t!(b.get_dscp()), Dscp::AF41);
+    t!(b.priority(3));
+    assert_eq!(t!(b.get_priority()), 3);
+    match b.mark(7) {
+        Ok(_) => assert_eq!(t!(b.get_mark()), 7),
+        Err(e) => {
+            let code = net2::Error::from_io(&e).and_then(|e| e.raw_os_error());
+            assert_eq!(code, Some(libc::EPERM), "{}", e);
+        }
+    }
+
+    let listener = t!(t!(t!(TcpBuilder::new_v4()).bind("127.0.0.1:0")).listen(1));
+    let stream = t!(TcpStream::connect(t!(listener.local_addr())));
+    t!(stream.set_dscp(Dscp::CS1));
+    assert_eq!(t!(stream.dscp()), Dscp::CS1);
+    t!(stream.set_priority(1));
+    assert_eq!(t!(stream.priority()), 1);
+
+    let u = t!(UdpBuilder::new_v4());
+    t!(u.ecn(Ecn::Ect0));
+    t!(u.dscp(Dscp::EF));
+    assert_eq!(t!(u.get_dscp()), Dscp::EF);
+    assert_eq!(t!(u.get_ecn()), Ecn::Ect0);
+
+    let socket = t!(UdpSocket::bind("127.0.0.1:0"));
+    t!(socket.set_dscp(Dscp::CS5));
+    t!(socket.set_ecn(Ecn::Ect1));
+    assert_eq!(t!(socket.dscp()), Dscp::CS5);
+    assert_eq!(t!(socket.ecn()), Ecn::Ect1);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn pktinfo() {
+    use std::net::{SocketAddr, UdpSocket};
+    use net2::{PacketInfo, UdpBuilder, UdpSocketExt};
+
+    let server = t!(t!(t!(UdpBuilder::new_v4()).recv_pktinfo(true))
+                    .bind("0.0.0.0:0"));
+    assert!(t!(server.recv_pktinfo()));
+    let port = t!(server.local_addr()).port();
+    let client = t!(UdpSocket::bind("127.0.0.1:0"));
+    t!(client.send_to(b"query", ("127.0.0.2", port)));
+
+    let mut buf = [0; 16];
+    let (n, from, info, truncated) = t!(server.recv_msg(&mut buf));
+    assert_eq!(&buf[..n], b"query");
+    assert!(!truncated);
+    assert_eq!(from, t!(client.local_addr()));
+    let info = info.unwrap();
+    assert_eq!(info.addr, IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)));
+    assert!(info.interface > 0);
+
+    t!(server.send_msg(b"reply", &from, Some(&info)));
+    let (n, reply_from) = t!(client.recv_from(&mut buf));
+    assert_eq!(&buf[..n], b"reply");
+    assert_eq!(reply_from, "127.0.0.2".parse::<IpAddr>().map(|ip| {
+        SocketAddr::new(ip, port)
+    }).unwrap());
+
+    // Without IP_PKTINFO no destination is reported
+    t!(server.set_recv_pktinfo(false));
+    t!(client.send_to(b"query", ("127.0.0.1", port)));
+    assert_eq!(t!(server.recv_msg(&mut buf)).2, None);
+
+    // Broadcasts are answered from the address of the interface
+    t!(server.set_recv_pktinfo(true));
+    t!(client.set_broadcast(true));
+    t!(client.send_to(b"query", ("127.255.255.255", port)));
+    let (_, _, info, _) = t!(server.recv_msg(&mut buf));
+    assert_eq!(info.unwrap().addr, IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
+
+    t!(client.send_to(b"a longer query", ("127.0.0.1", port)));
+    let (n, _, info, truncated) = t!(server.recv_msg(&mut buf[..4]));
+    assert_eq!(&buf[..n], b"a lo");
+    assert!(info.is_some());
+    assert!(truncated);
+
+    let server = t!(UdpSocket::bind("[::]:0"));
+    t!(server.set_recv_pktinfo(true));
+    let port = t!(server.local_addr()).port();
+    let client = t!(UdpSocket::bind("[::1]:0"));
+    t!(client.send_to(b"query", ("::1", port)));
+    let (_, from, info, _) = t!(server.recv_msg(&mut buf));
+    let info = info.unwrap();
+    assert_eq!(info.addr, IpAddr::V6(Ipv6Addr::LOCALHOST));
+    t!(server.send_msg(b"reply", &from, Some(&PacketInfo {
+        addr: IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        interface: info.interface,
+    })));
+    let (n, _) = t!(client.recv_from(&mut buf));
+    assert_eq!(&buf[..n], b"reply");
+
+    // IPv4 traffic on a dual-stack socket
+    let client = t!(UdpSocket::bind("127.0.0.1:0"));
+    t!(client.send_to(b"query", ("127.0.0.3", port)));
+    let (_, from, info, _) = t!(server.recv_msg(&mut buf));
+    let info = info.unwrap();
+    assert_eq!(info.addr,
+               IpAddr::V6(Ipv4Addr::new(127, 0, 0, 3).to_ipv6_mapped()));
+    t!(server.send_msg(b"reply", &from, Some(&info)));
+    let (_, reply_from) = t!(client.recv_from(&mut buf));
+    assert_eq!(reply_from.ip(), IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3)));
+}
+
+#[test]
+#[cfg(unix)]
+fn single_connect_attempt_keeps_os_error() {
+    use net2::{ConnectError, TcpStreamExt};
+
+    let listener = t!(std::net::TcpListener::bind("127.0.0.1:0"));
+    let addr = t!(listener.local_addr());
+    drop(listener);
+
+    // One address is reported like several, with its address and timing
+    let err = t!(TcpBuilder::new_v4()).connect(addr).unwrap_err();
+    let err = ConnectError::from_io(&err).unwrap();
+    assert_eq!(err.attempts().len(), 1);
+    assert_eq!(err.attempts()[0].addr(), addr);
+    assert_eq!(err.raw_os_error(), Some(libc::ECONNREFUSED));
+
+    // A nonblocking connect reports EINPROGRESS like connect(2) does
+    let listener = t!(std::net::TcpListener::bind("127.0.0.1:0"));
+    let stream = t!(t!(TcpBuilder::new_v4()).to_tcp_stream());
+    t!(stream.set_nonblocking(true));
+    match TcpStreamExt::connect(&stream, t!(listener.local_addr())) {
+        Ok(()) => {}
+        Err(e) => {
+            let code = ConnectError::from_io(&e).and_then(|e| e.raw_os_error());
+            assert_eq!(code, Some(libc::EINPROGRESS));
+        }
+    }
+
+    let err = t!(TcpBuilder::new_v4()).connect(&[addr, addr][..]).unwrap_err();
+    let err = ConnectError::from_io(&err).unwrap();
+    assert_eq!(err.attempts().len(), 2);
+    assert_eq!(err.raw_os_error(), Some(libc::ECONNREFUSED));
+}